@@ -1,7 +1,7 @@
 use anyhow::Result;
 use bytes::BytesMut;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use simple_redis::{parse_frame, parse_frame_length, RespFrame};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use simple_redis::{parse_frame, parse_frame_length, BulkString, RespEncode, RespFrame};
 
 // resp frames covers all kinds of real-world redis requests and responses
 // cmd 1: set key value
@@ -74,9 +74,45 @@ fn v2_decode_parse_frame(buf: &mut &[u8]) -> Result<Vec<RespFrame>> {
     Ok(frames)
 }
 
+// the replies of a pipelined batch, encoded one by one
+fn replies() -> Vec<RespFrame> {
+    (0..16)
+        .map(|i| BulkString::from(format!("value-{}", i)).into())
+        .collect()
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let buf = BytesMut::from(DATA);
 
+    // a fresh Vec per reply vs the reused write buffer of a connection
+    c.bench_function("encode_fresh_vec", |b| {
+        b.iter_batched(
+            replies,
+            |frames| {
+                frames
+                    .into_iter()
+                    .map(|frame| black_box(frame.encode()).len())
+                    .sum::<usize>()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    let mut write_buf = BytesMut::with_capacity(16 * 1024);
+    c.bench_function("encode_to_reused_buf", |b| {
+        b.iter_batched(
+            replies,
+            |frames| {
+                for frame in frames {
+                    frame.encode_to(&mut write_buf);
+                }
+                black_box(&write_buf);
+                write_buf.clear();
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
     c.bench_function("v1_decode", |b| {
         b.iter(|| v1_decode(black_box(&mut buf.clone())))
     });
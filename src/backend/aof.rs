@@ -0,0 +1,542 @@
+use super::Backend;
+use crate::{
+    cmd::{Command, CommandExecutor},
+    BulkString, RespArray, RespDecode, RespEncode, RespError, RespFrame,
+};
+use anyhow::{anyhow, Result};
+use bytes::BytesMut;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::Path,
+    sync::{atomic::Ordering, MutexGuard},
+    thread,
+};
+use tracing::warn;
+
+// bytes read at a time when replaying commands
+const REPLAY_CHUNK_SIZE: usize = 64 * 1024;
+
+// the append only file is a plain sequence of RESP encoded write commands
+#[derive(Debug, Default)]
+pub(crate) struct AofState {
+    file: Option<File>,
+    // writes made while a rewrite is running, they are appended to the rewritten file
+    // before it replaces the old one
+    rewrite_buffer: Option<Vec<u8>>,
+    // the database a replay of the file ends up on, a write to another one is preceded by SELECT
+    db: usize,
+}
+
+impl Backend {
+    pub fn aof_enabled(&self) -> bool {
+        self.config.appendonly
+    }
+
+    /// Run a write command and append its RESP encoded `entry` to the AOF. Writes are serialized
+    /// under the AOF lock, so the file replays them in the same order they were applied. A
+    /// command answered with an error changed nothing and isn't logged
+    pub fn log_write(&self, entry: &[u8], f: impl FnOnce() -> RespFrame) -> RespFrame {
+        let mut aof = self.lock_aof();
+        let ret = f();
+        if matches!(ret, RespFrame::Error(_)) {
+            return ret;
+        }
+        let select = (aof.db != self.db_index()).then(|| select_entry(self.db_index()));
+        aof.db = self.db_index();
+        for entry in select.as_deref().into_iter().chain([entry]) {
+            if let Some(buf) = aof.rewrite_buffer.as_mut() {
+                buf.extend_from_slice(entry);
+            }
+            if let Err(e) = append(&mut aof, &self.config.aof_path, entry) {
+                warn!("failed to append to the AOF: {:?}", e);
+            }
+        }
+        ret
+    }
+
+    /// Run a write command with `log_write`. EXPIRE and EXPIREAT run and are logged as the
+    /// PEXPIREAT of the deadline they resolve to, see `Command::absolute_expiry`
+    pub fn execute_logged(&self, cmd: Command, entry: Vec<u8>) -> RespFrame {
+        let (cmd, entry) = cmd
+            .absolute_expiry(self.config.clock.now_ms())
+            .unwrap_or((cmd, entry));
+        self.log_write(&entry, || cmd.execute(self))
+    }
+
+    /// Replace the AOF with one command per key (SET, HSET per field, RPUSH, ZADD and SADD) and
+    /// a PEXPIREAT per key with a time to live, the keys of a database other than 0 follow a
+    /// SELECT
+    pub fn rewrite_aof(&self) -> io::Result<()> {
+        let path = &self.config.aof_path;
+        let tmp = path.with_extension("rewrite");
+        // writes are blocked while the current state is captured, everything after it is buffered
+        let entries = {
+            let mut aof = self.lock_aof();
+            aof.rewrite_buffer = Some(Vec::new());
+            let mut entries = Vec::new();
+            let mut selected = 0;
+            for db in self.all_dbs() {
+                if db.all_keys().next().is_none() {
+                    continue;
+                }
+                if db.db_index() != selected {
+                    entries.extend(select_entry(db.db_index()));
+                    selected = db.db_index();
+                }
+                entries.extend(db.aof_entries());
+            }
+            // the buffered writes carry on from the database the old file was on
+            if selected != aof.db {
+                entries.extend(select_entry(aof.db));
+            }
+            entries
+        };
+
+        let ret = fs::write(&tmp, entries);
+        let mut aof = self.lock_aof();
+        let buffer = aof.rewrite_buffer.take().unwrap_or_default();
+        ret?;
+        let mut file = OpenOptions::new().append(true).open(&tmp)?;
+        file.write_all(&buffer)?;
+        fs::rename(&tmp, path)?;
+        // the handle follows the renamed file, new writes go to the rewritten AOF
+        aof.file = Some(file);
+        Ok(())
+    }
+
+    /// Rewrite on a background thread, returns false if a rewrite is already running
+    pub fn bg_rewrite_aof(&self) -> bool {
+        if self.aof_rewrite_in_progress.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        let backend = self.clone();
+        thread::spawn(move || {
+            if let Err(e) = backend.rewrite_aof() {
+                warn!("background AOF rewrite failed: {:?}", e);
+            }
+            backend
+                .aof_rewrite_in_progress
+                .store(false, Ordering::Release);
+        });
+        true
+    }
+
+    /// Replay the commands in the AOF, returns the number of commands executed. A partial
+    /// command at the end is cut off the file, so new writes aren't appended to it
+    pub fn load_aof(&self) -> Result<usize> {
+        let path = &self.config.aof_path;
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let len = file.metadata()?.len();
+        let (count, backend, partial) = self.replay(file)?;
+        if partial > 0 {
+            let len = len.saturating_sub(partial as u64);
+            OpenOptions::new().write(true).open(path)?.set_len(len)?;
+            warn!(
+                "Truncated the AOF to {} bytes, the last complete command",
+                len
+            );
+        }
+        self.lock_aof().db = backend.db_index();
+        Ok(count)
+    }
+
+    /// Execute the RESP encoded commands read from `reader` in order, returns the number of
+    /// commands executed. SELECT switches the database of the commands after it. A partial
+    /// command at the end, like a write cut short by a crash, is skipped with a warning
+    pub fn replay_commands(&self, reader: impl Read) -> Result<usize> {
+        self.replay(reader).map(|(count, _, _)| count)
+    }
+
+    // the count, the handle on the database the replay ended on and the size of the partial
+    // command left at the end
+    fn replay(&self, mut reader: impl Read) -> Result<(usize, Backend, usize)> {
+        let mut buf = BytesMut::new();
+        let mut chunk = vec![0; REPLAY_CHUNK_SIZE];
+        let mut count = 0;
+        let mut backend = self.clone();
+        loop {
+            let n = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            buf.extend_from_slice(&chunk[..n]);
+            loop {
+                let frame = match RespFrame::decode(&mut buf) {
+                    Ok(frame) => frame,
+                    Err(RespError::NotComplete) => break,
+                    Err(e) => return Err(e.into()),
+                };
+                match Command::try_from(frame)? {
+                    Command::Select(cmd) => {
+                        backend = usize::try_from(cmd.index)
+                            .ok()
+                            .and_then(|index| self.select(index))
+                            .ok_or_else(|| anyhow!("database {} is out of range", cmd.index))?;
+                    }
+                    cmd => {
+                        cmd.execute(&backend);
+                    }
+                }
+                count += 1;
+            }
+        }
+        if !buf.is_empty() {
+            warn!(
+                "Skipped a partial command of {} bytes at the end of the replay",
+                buf.len()
+            );
+        }
+        Ok((count, backend, buf.len()))
+    }
+
+    fn aof_entries(&self) -> Vec<u8> {
+        let db = self.db();
+        let mut buf = Vec::new();
+        for v in db.map.iter() {
+            buf.extend(aof_entry(["set", v.key()], [v.value().clone()]));
+        }
+        for v in db.hmap.iter() {
+            for field in v.value().iter() {
+                buf.extend(aof_entry(
+                    ["hset", v.key(), field.key()],
+                    [field.value().clone()],
+                ));
+            }
+        }
+        for v in db.lmap.iter() {
+            buf.extend(aof_entry(["rpush", v.key()], v.value().iter().cloned()));
+        }
+        for v in db.zmap.iter() {
+            let members = v.value().iter().flat_map(|(member, score)| {
+                [
+                    BulkString::from(score.to_string()).into(),
+                    BulkString::from(member).into(),
+                ]
+            });
+            buf.extend(aof_entry(["zadd", v.key()], members));
+        }
+        for v in db.smap.iter() {
+            let members = v
+                .value()
+                .iter()
+                .map(|member| BulkString::from(member.as_str()).into())
+                .collect::<Vec<RespFrame>>();
+            buf.extend(aof_entry(["sadd", v.key()], members));
+        }
+        for v in db.expires.iter() {
+            buf.extend(aof_entry(
+                ["pexpireat", v.key(), &v.value().to_string()],
+                [],
+            ));
+        }
+        buf
+    }
+
+    fn lock_aof(&self) -> MutexGuard<'_, AofState> {
+        self.aof.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn select_entry(index: usize) -> Vec<u8> {
+    aof_entry(["select", &index.to_string()], [])
+}
+
+fn append(aof: &mut AofState, path: &Path, entry: &[u8]) -> io::Result<()> {
+    let file = match aof.file {
+        Some(ref mut file) => file,
+        None => aof
+            .file
+            .insert(OpenOptions::new().create(true).append(true).open(path)?),
+    };
+    file.write_all(entry)
+}
+
+pub(super) fn aof_entry<'a>(
+    args: impl IntoIterator<Item = &'a str>,
+    values: impl IntoIterator<Item = RespFrame>,
+) -> Vec<u8> {
+    let frames = args
+        .into_iter()
+        .map(|v| BulkString::from(v).into())
+        .chain(values)
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(frames).encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendConfig, MockClock};
+    use std::{path::PathBuf, sync::Arc, time::Duration};
+
+    fn aof_backend(name: &str) -> (Backend, PathBuf) {
+        let aof_path =
+            std::env::temp_dir().join(format!("simple-redis-{}-{}.aof", name, std::process::id()));
+        let _ = fs::remove_file(&aof_path);
+        let backend = Backend::with_config(BackendConfig {
+            appendonly: true,
+            aof_path: aof_path.clone(),
+            ..Default::default()
+        });
+        (backend, aof_path)
+    }
+
+    fn run(backend: &Backend, args: &[&str]) -> RespFrame {
+        let frames = args
+            .iter()
+            .map(|v| BulkString::from(*v).into())
+            .collect::<Vec<RespFrame>>();
+        let entry = RespArray::new(frames.clone()).encode();
+        let cmd = Command::try_from(RespArray::new(frames)).unwrap();
+        backend.execute_logged(cmd, entry)
+    }
+
+    fn count_entries(path: &Path) -> usize {
+        let mut buf = BytesMut::from(fs::read(path).unwrap().as_slice());
+        let mut count = 0;
+        while !buf.is_empty() {
+            RespFrame::decode(&mut buf).unwrap();
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn test_rewrite_aof_should_compact_and_replay() -> Result<()> {
+        let (backend, aof_path) = aof_backend("rewrite");
+        for i in 0..10 {
+            run(&backend, &["set", "key", &i.to_string()]);
+            run(&backend, &["hset", "map", "field", &i.to_string()]);
+        }
+        run(&backend, &["rpush", "list", "a", "b"]);
+        run(&backend, &["rpoplpush", "list", "list"]);
+        assert_eq!(count_entries(&aof_path), 22);
+
+        backend.rewrite_aof()?;
+        assert_eq!(count_entries(&aof_path), 3);
+        // writes after the rewrite keep going to the new file
+        run(&backend, &["set", "other", "value"]);
+        assert_eq!(count_entries(&aof_path), 4);
+
+        let restored = Backend::with_config(BackendConfig {
+            aof_path: aof_path.clone(),
+            ..Default::default()
+        });
+        assert_eq!(restored.load_aof()?, 4);
+        assert_eq!(restored.get("key"), Some(BulkString::from("9").into()));
+        assert_eq!(
+            restored.get("other"),
+            Some(BulkString::from("value").into())
+        );
+        assert_eq!(
+            restored.hget("map", "field"),
+            Some(BulkString::from("9").into())
+        );
+        assert_eq!(
+            restored.db().lmap.get("list").map(|v| v.clone()),
+            backend.db().lmap.get("list").map(|v| v.clone())
+        );
+
+        fs::remove_file(&aof_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_writes_should_not_be_logged() -> Result<()> {
+        let (backend, aof_path) = aof_backend("failed");
+        run(&backend, &["set", "key", "value"]);
+        assert!(matches!(
+            run(&backend, &["lpush", "key", "a"]),
+            RespFrame::Error(_)
+        ));
+        assert!(matches!(
+            run(&backend, &["incr", "key"]),
+            RespFrame::Error(_)
+        ));
+        assert_eq!(count_entries(&aof_path), 1);
+
+        fs::remove_file(&aof_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_aof_should_keep_concurrent_writes() -> Result<()> {
+        let (backend, aof_path) = aof_backend("concurrent");
+        for i in 0..100 {
+            run(&backend, &["rpush", "list", &i.to_string()]);
+        }
+
+        let writer = {
+            let backend = backend.clone();
+            thread::spawn(move || {
+                for i in 100..1000 {
+                    run(&backend, &["rpush", "list", &i.to_string()]);
+                    run(&backend, &["set", &format!("key{}", i), "value"]);
+                }
+            })
+        };
+        backend.rewrite_aof()?;
+        writer.join().unwrap();
+
+        let restored = Backend::with_config(BackendConfig {
+            aof_path: aof_path.clone(),
+            ..Default::default()
+        });
+        restored.load_aof()?;
+        assert_eq!(
+            restored.db().lmap.get("list").map(|v| v.clone()),
+            backend.db().lmap.get("list").map(|v| v.clone())
+        );
+        assert_eq!(restored.db().map.len(), 900);
+
+        fs::remove_file(&aof_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_commands_should_stop_at_a_partial_command() -> Result<()> {
+        let mut data = Vec::new();
+        for (key, value) in [("a", "1"), ("b", "2"), ("c", "3")] {
+            let frames = ["set", key, value].map(|v| BulkString::from(v).into());
+            data.extend(RespArray::new(frames).encode());
+        }
+        data.extend_from_slice(b"*3\r\n$3\r\nset\r\n$1\r\nd");
+
+        let backend = Backend::new();
+        // a reader handing out one byte at a time splits every command across reads
+        let reader = io::Cursor::new(data).bytes().map_while(Result::ok);
+        let reader = ByteReader(reader);
+        assert_eq!(backend.replay_commands(reader)?, 3);
+        for (key, value) in [("a", "1"), ("b", "2"), ("c", "3")] {
+            assert_eq!(backend.get(key), Some(BulkString::from(value).into()));
+        }
+        assert_eq!(backend.get("d"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_aof_should_cut_off_a_partial_command() -> Result<()> {
+        let (backend, aof_path) = aof_backend("truncated");
+        run(&backend, &["set", "a", "1"]);
+        let mut file = OpenOptions::new().append(true).open(&aof_path)?;
+        file.write_all(b"*3\r\n$3\r\nset\r\n$1\r\nb")?;
+        drop(file);
+
+        let restored = Backend::with_config(backend.config.clone());
+        assert_eq!(restored.load_aof()?, 1);
+        run(&restored, &["set", "c", "3"]);
+
+        let reloaded = Backend::with_config(backend.config.clone());
+        assert_eq!(reloaded.load_aof()?, 2);
+        assert_eq!(reloaded.get("a"), Some(BulkString::from("1").into()));
+        assert_eq!(reloaded.get("b"), None);
+        assert_eq!(reloaded.get("c"), Some(BulkString::from("3").into()));
+        fs::remove_file(&aof_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_aof_should_keep_ttl_deadlines() -> Result<()> {
+        let (backend, aof_path) = aof_backend("ttl");
+        let clock = Arc::new(MockClock::new(1_700_000_000_000));
+        let backend = Backend::with_config(BackendConfig {
+            clock: clock.clone(),
+            ..backend.config.clone()
+        });
+        run(&backend, &["set", "a", "1"]);
+        run(&backend, &["expire", "a", "10"]);
+        run(&backend, &["set", "b", "1"]);
+        run(&backend, &["expireat", "b", "1700000020"]);
+        run(&backend, &["set", "c", "1"]);
+
+        // a replay 5s later keeps the deadlines instead of restarting the TTLs
+        let restore = |clock: &Arc<MockClock>| -> Result<Backend> {
+            let restored = Backend::with_config(BackendConfig {
+                aof_path: aof_path.clone(),
+                clock: clock.clone(),
+                ..Default::default()
+            });
+            restored.load_aof()?;
+            Ok(restored)
+        };
+        clock.advance(Duration::from_secs(5));
+        let restored = restore(&clock)?;
+        assert_eq!(restored.expire_time("a"), Some(Some(1_700_000_010_000)));
+        assert_eq!(restored.expire_time("b"), Some(Some(1_700_000_020_000)));
+        assert_eq!(restored.expire_time("c"), Some(None));
+
+        backend.rewrite_aof()?;
+        let restored = restore(&clock)?;
+        assert_eq!(restored.expire_time("a"), Some(Some(1_700_000_010_000)));
+        assert_eq!(restored.expire_time("b"), Some(Some(1_700_000_020_000)));
+        assert_eq!(restored.expire_time("c"), Some(None));
+
+        // once the deadline passed the key is gone after a replay
+        clock.advance(Duration::from_secs(10));
+        let restored = restore(&clock)?;
+        assert_eq!(restored.get("a"), None);
+        assert_eq!(restored.expire_time("b"), Some(Some(1_700_000_020_000)));
+
+        fs::remove_file(&aof_path)?;
+        Ok(())
+    }
+
+    struct ByteReader<I>(I);
+
+    impl<I: Iterator<Item = u8>> Read for ByteReader<I> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (buf.first_mut(), self.0.next()) {
+                (Some(slot), Some(byte)) => {
+                    *slot = byte;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_aof_should_replay_writes_to_their_database() -> Result<()> {
+        let (backend, aof_path) = aof_backend("select");
+        let db1 = backend.select(1).unwrap();
+        run(&backend, &["set", "key", "0"]);
+        run(&db1, &["set", "key", "1"]);
+        run(&db1, &["set", "other", "1"]);
+        run(&backend, &["set", "other", "0"]);
+        // two SELECT entries, to 1 and back to 0
+        assert_eq!(count_entries(&aof_path), 6);
+
+        let restored = Backend::with_config(BackendConfig {
+            aof_path: aof_path.clone(),
+            ..Default::default()
+        });
+        restored.load_aof()?;
+        let restored1 = restored.select(1).unwrap();
+        assert_eq!(restored.get("key"), Some(BulkString::from("0").into()));
+        assert_eq!(restored1.get("key"), Some(BulkString::from("1").into()));
+
+        backend.rewrite_aof()?;
+        // the rewrite ends on database 0 like the old file, so later writes land there
+        run(&db1, &["set", "late", "1"]);
+        let restored = Backend::with_config(BackendConfig {
+            aof_path: aof_path.clone(),
+            ..Default::default()
+        });
+        restored.load_aof()?;
+        let restored1 = restored.select(1).unwrap();
+        assert_eq!(restored.get("other"), Some(BulkString::from("0").into()));
+        assert_eq!(restored1.get("other"), Some(BulkString::from("1").into()));
+        assert_eq!(restored1.get("late"), Some(BulkString::from("1").into()));
+        assert_eq!(restored.get("late"), None);
+
+        fs::remove_file(&aof_path)?;
+        Ok(())
+    }
+}
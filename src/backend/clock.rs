@@ -0,0 +1,62 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Source of the current time for key expiry, tests swap in a `MockClock` to move time by hand
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Milliseconds since the unix epoch
+    fn now_ms(&self) -> u64;
+}
+
+/// The system clock
+#[derive(Debug, Default)]
+pub struct RealClock;
+
+/// A clock that only moves when told to
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicU64,
+}
+
+impl Clock for RealClock {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+impl MockClock {
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            now: AtomicU64::new(now_ms),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ms(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_should_only_move_when_advanced() {
+        let clock = MockClock::new(1000);
+        assert_eq!(clock.now_ms(), 1000);
+        assert_eq!(clock.now_ms(), 1000);
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(clock.now_ms(), 3000);
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+// how many steps run between two looks at the clock
+const CHECK_INTERVAL: u32 = 1024;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("ERR command timed out")]
+pub struct CommandTimedOut;
+
+/// Time budget of a single command. Commands are synchronous, so loop-heavy operations call
+/// `check` once per step and give up with `CommandTimedOut` when the budget is spent
+#[derive(Debug)]
+pub struct Deadline {
+    // no deadline when the budget is not configured
+    at: Option<Instant>,
+    steps: u32,
+}
+
+impl Deadline {
+    pub fn new(budget: Option<Duration>) -> Self {
+        Self {
+            at: budget.map(|budget| Instant::now() + budget),
+            steps: 0,
+        }
+    }
+
+    pub fn check(&mut self) -> Result<(), CommandTimedOut> {
+        let Some(at) = self.at else {
+            return Ok(());
+        };
+        self.steps = self.steps.wrapping_add(1);
+        if self.steps.is_multiple_of(CHECK_INTERVAL) && Instant::now() >= at {
+            return Err(CommandTimedOut);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_without_budget_never_expires() {
+        let mut deadline = Deadline::new(None);
+        for _ in 0..10_000 {
+            assert_eq!(deadline.check(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_deadline_should_expire() {
+        let mut deadline = Deadline::new(Some(Duration::ZERO));
+        let ret = (0..CHECK_INTERVAL).try_for_each(|_| deadline.check());
+        assert_eq!(ret, Err(CommandTimedOut));
+    }
+}
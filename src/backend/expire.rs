@@ -0,0 +1,230 @@
+use super::{sample_keys, Backend};
+use dashmap::mapref::entry::Entry;
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+// deadlines looked at per round of a sweep, a database is swept again while more than a
+// quarter of them were over, the same numbers redis uses
+const SWEEP_SAMPLES: usize = 20;
+// time a sweep of every database may take, a quarter of the 100ms between two sweeps
+const SWEEP_TIME_BUDGET: Duration = Duration::from_millis(25);
+
+impl Backend {
+    /// Give the key a time to live, returns false when the key doesn't exist
+    pub fn expire(&self, key: &str, ttl: Duration) -> bool {
+        let at = self
+            .config
+            .clock
+            .now_ms()
+            .saturating_add(ttl.as_millis() as u64);
+        self.expire_at(key, at)
+    }
+
+    /// Make the key expire at the unix time `at_ms` in milliseconds, returns false when the key
+    /// doesn't exist. A time in the past expires the key on its next access
+    pub fn expire_at(&self, key: &str, at_ms: u64) -> bool {
+        if self.key_type(key).is_none() {
+            return false;
+        }
+        self.db().expires.insert(key.to_string(), at_ms);
+        true
+    }
+
+    /// Remaining time to live of the key. None for a missing key, Some(None) for a key that
+    /// never expires
+    pub fn ttl(&self, key: &str) -> Option<Option<Duration>> {
+        self.key_type(key)?;
+        let now = self.config.clock.now_ms();
+        let ttl = self
+            .db()
+            .expires
+            .get(key)
+            .map(|at| Duration::from_millis(at.saturating_sub(now)));
+        Some(ttl)
+    }
+
+    /// Unix time in milliseconds at which the key expires. None for a missing key, Some(None)
+    /// for a key that never expires
+    pub fn expire_time(&self, key: &str) -> Option<Option<u64>> {
+        self.key_type(key)?;
+        Some(self.db().expires.get(key).map(|at| *at))
+    }
+
+    // delete the key if its time to live is over, returns whether it was deleted. Called before
+    // the key is looked up, so an expired key is never seen even if the sweeper didn't get to it
+    pub(crate) fn expire_if_needed(&self, key: &str) -> bool {
+        let db = self.db();
+        let now = self.config.clock.now_ms();
+        if db.expires.get(key).is_none_or(|at| *at > now) {
+            return false;
+        }
+        // the deadline stays locked until the value is gone. SET drops the deadline before it
+        // writes, so a SET racing with this waits for the delete instead of being deleted
+        let Entry::Occupied(deadline) = db.expires.entry(key.to_string()) else {
+            return false;
+        };
+        if *deadline.get() > now {
+            return false;
+        }
+        db.remove(key);
+        deadline.remove();
+        self.mark_changed(key);
+        true
+    }
+
+    /// Delete keys whose time to live is over in every database, returns how many were
+    /// deleted. Meant to run periodically, like redis it only looks at random samples of the
+    /// keys with a deadline and keeps sampling a database while many of them turn out expired,
+    /// within a time budget. Expired keys it misses are still hidden from lookups. Does nothing
+    /// while active expiry is turned off by DEBUG SET-ACTIVE-EXPIRE. Runs like a command, never
+    /// in the middle of EXEC or SWAPDB
+    pub fn sweep_expired(&self) -> usize {
+        if !self.active_expire.load(Ordering::Relaxed) {
+            return 0;
+        }
+        let _guard = self
+            .transaction_lock
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        let deadline = Instant::now() + SWEEP_TIME_BUDGET;
+        self.all_dbs().map(|db| db.sweep_db_expired(deadline)).sum()
+    }
+
+    fn sweep_db_expired(&self, deadline: Instant) -> usize {
+        let mut deleted = 0;
+        while Instant::now() < deadline {
+            let sample = sample_keys(&self.db().expires, SWEEP_SAMPLES);
+            let expired = sample
+                .iter()
+                .filter(|key| self.expire_if_needed(key))
+                .count();
+            deleted += expired;
+            if expired * 4 <= sample.len() {
+                break;
+            }
+        }
+        deleted
+    }
+
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.active_expire.store(enabled, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::CommandTimedOut, BackendConfig, BulkString, MockClock};
+    use std::sync::Arc;
+
+    fn mock_backend() -> (Backend, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let backend = Backend::with_config(BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        (backend, clock)
+    }
+
+    #[test]
+    fn test_key_should_expire_when_clock_passes_ttl() {
+        let (backend, clock) = mock_backend();
+        backend.set("key".to_string(), BulkString::from("value").into());
+        assert!(backend.expire("key", Duration::from_secs(10)));
+        assert!(!backend.expire("missing", Duration::from_secs(10)));
+        assert_eq!(backend.ttl("key"), Some(Some(Duration::from_secs(10))));
+
+        clock.advance(Duration::from_millis(9999));
+        assert_eq!(backend.get("key"), Some(BulkString::from("value").into()));
+        assert_eq!(backend.ttl("key"), Some(Some(Duration::from_millis(1))));
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(backend.get("key"), None);
+        assert_eq!(backend.ttl("key"), None);
+        assert!(backend.db().expires.is_empty());
+    }
+
+    #[test]
+    fn test_expired_keys_should_be_hidden_before_they_are_swept() -> Result<(), CommandTimedOut> {
+        let (backend, clock) = mock_backend();
+        backend.set_active_expire(false);
+        backend.set("volatile".to_string(), BulkString::from("a").into());
+        backend.set("other".to_string(), BulkString::from("b").into());
+        backend.expire("volatile", Duration::from_secs(1));
+        backend.expire("other", Duration::from_secs(1));
+        backend.set("persistent".to_string(), BulkString::from("c").into());
+        clock.advance(Duration::from_secs(1));
+
+        assert_eq!(backend.all_keys().collect::<Vec<_>>(), ["persistent"]);
+        assert_eq!(backend.keys("*")?, ["persistent"]);
+        assert_eq!(backend.scan(0, 10).1, ["persistent"]);
+        assert!(!backend.del("volatile"));
+        assert!(backend.db().map.get("volatile").is_none());
+        assert!(backend.del("persistent"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_should_clear_ttl() {
+        let (backend, clock) = mock_backend();
+        backend.set("key".to_string(), BulkString::from("value").into());
+        backend.expire("key", Duration::from_secs(1));
+        backend.set("key".to_string(), BulkString::from("other").into());
+        assert_eq!(backend.ttl("key"), Some(None));
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(backend.get("key"), Some(BulkString::from("other").into()));
+    }
+
+    #[test]
+    fn test_sweep_expired() {
+        let (backend, clock) = mock_backend();
+        for i in 0..10 {
+            let key = format!("key{}", i);
+            backend.hset(
+                key.clone(),
+                "field".to_string(),
+                BulkString::from("v").into(),
+            );
+            backend.expire(&key, Duration::from_secs(i + 1));
+        }
+        backend.set("forever".to_string(), BulkString::from("value").into());
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(backend.sweep_expired(), 5);
+        // the keys are gone from the stores, not only hidden from lookups
+        assert_eq!(backend.db().hmap.len(), 5);
+
+        backend.set_active_expire(false);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(backend.sweep_expired(), 0);
+        assert_eq!(backend.db().hmap.len(), 5);
+        backend.set_active_expire(true);
+        assert_eq!(backend.sweep_expired(), 5);
+        assert_eq!(backend.all_keys().collect::<Vec<_>>(), ["forever"]);
+    }
+
+    #[test]
+    fn test_sweep_should_sample_deadlines() {
+        let (backend, clock) = mock_backend();
+        for i in 0..1000 {
+            let key = format!("key{}", i);
+            backend.set(key.clone(), BulkString::from("v").into());
+            backend.expire(&key, Duration::from_secs(if i < 900 { 1 } else { 10 }));
+        }
+        assert_eq!(
+            sample_keys(&backend.db().expires, SWEEP_SAMPLES).len(),
+            SWEEP_SAMPLES
+        );
+
+        // while most samples are expired the database is sampled again, expired keys left
+        // behind once they are few are hidden from lookups all the same
+        clock.advance(Duration::from_secs(1));
+        let swept = backend.sweep_expired();
+        assert!(swept > 500, "{}", swept);
+        assert_eq!(backend.db().map.len(), 1000 - swept);
+        assert_eq!(backend.all_keys().count(), 100);
+    }
+}
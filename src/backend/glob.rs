@@ -0,0 +1,196 @@
+// glob-style matching with the same semantics as redis' stringmatchlen:
+// - `*` any sequence of bytes, `?` any single byte
+// - `[abc]`, `[a-z]` (either order) and `[^...]` byte classes, an unterminated class ends the pattern
+// - `\x` matches `x` literally, both outside and inside a class
+// like redis an empty string is only matched by an empty pattern
+
+// recursion happens once per `*`, patterns nesting deeper than this never match
+const MAX_NESTING: usize = 1000;
+
+/// Match `string` against the glob `pattern`
+pub(crate) fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let mut skip_longer = false;
+    match_impl(pattern, string, &mut skip_longer, 0)
+}
+
+// `skip_longer` is set once a `*` has tried every suffix of the string without a match, the
+// outer `*`s only try shorter remainders so they can't match either. This bounds the
+// backtracking of patterns like `*a*a*a*b`
+fn match_impl(p: &[u8], s: &[u8], skip_longer: &mut bool, nesting: usize) -> bool {
+    if nesting > MAX_NESTING {
+        return false;
+    }
+
+    let (mut pi, mut si) = (0, 0);
+    while pi < p.len() && si < s.len() {
+        match p[pi] {
+            b'*' => {
+                while p.get(pi + 1) == Some(&b'*') {
+                    pi += 1;
+                }
+                if pi + 1 == p.len() {
+                    return true;
+                }
+                while si < s.len() {
+                    if match_impl(&p[pi + 1..], &s[si..], skip_longer, nesting + 1) {
+                        return true;
+                    }
+                    if *skip_longer {
+                        return false;
+                    }
+                    si += 1;
+                }
+                *skip_longer = true;
+                return false;
+            }
+            b'?' => si += 1,
+            b'[' => {
+                pi += 1;
+                let not = p.get(pi) == Some(&b'^');
+                if not {
+                    pi += 1;
+                }
+                let c = s[si];
+                let mut matched = false;
+                loop {
+                    match p.get(pi) {
+                        None => {
+                            // step back so the end of the pattern is not skipped below
+                            pi -= 1;
+                            break;
+                        }
+                        Some(b'\\') if pi + 1 < p.len() => {
+                            pi += 1;
+                            matched |= p[pi] == c;
+                        }
+                        Some(b']') => break,
+                        Some(&start) if pi + 2 < p.len() && p[pi + 1] == b'-' => {
+                            let end = p[pi + 2];
+                            let (lo, hi) = if start <= end {
+                                (start, end)
+                            } else {
+                                (end, start)
+                            };
+                            matched |= (lo..=hi).contains(&c);
+                            pi += 2;
+                        }
+                        Some(&lit) => matched |= lit == c,
+                    }
+                    pi += 1;
+                }
+                if matched == not {
+                    return false;
+                }
+                si += 1;
+            }
+            b'\\' if pi + 1 < p.len() => {
+                pi += 1;
+                if p[pi] != s[si] {
+                    return false;
+                }
+                si += 1;
+            }
+            lit => {
+                if lit != s[si] {
+                    return false;
+                }
+                si += 1;
+            }
+        }
+        pi += 1;
+        if si == s.len() {
+            while p.get(pi) == Some(&b'*') {
+                pi += 1;
+            }
+            break;
+        }
+    }
+    pi == p.len() && si == s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(pattern: &str, string: &str) -> bool {
+        glob_match(pattern.as_bytes(), string.as_bytes())
+    }
+
+    #[test]
+    fn test_glob_literal_and_wildcards() {
+        assert!(m("hello", "hello"));
+        assert!(!m("hello", "hell"));
+        assert!(!m("hell", "hello"));
+        assert!(m("h?llo", "hallo"));
+        assert!(!m("h?llo", "hllo"));
+        assert!(m("*", "anything"));
+        assert!(m("h*", "h"));
+        assert!(m("h*o", "ho"));
+        assert!(m("h***o", "hello"));
+        assert!(m("*llo", "hello"));
+        assert!(!m("*llo", "hellx"));
+        assert!(m("", ""));
+        assert!(!m("*", ""));
+        assert!(!m("", "a"));
+    }
+
+    #[test]
+    fn test_glob_backtracking() {
+        assert!(m("*a*b", "xaxxb"));
+        assert!(m("*a*b", "ab"));
+        assert!(m("*a*b", "aaabbb"));
+        assert!(!m("*a*b", "bba"));
+        assert!(!m("*a*b", "xaxxbx"));
+        assert!(m("a*b*c", "abbbbc"));
+        assert!(m("*.*", "user.name"));
+        assert!(!m("a*a*a*a*a*a*a*a*a*b", &"a".repeat(64)));
+        assert!(m("*a*?", "bab"));
+        assert!(!m("*a*?", "ba"));
+        assert!(!m("*a*?", "a"));
+    }
+
+    #[test]
+    fn test_glob_classes() {
+        assert!(m("h[ae]llo", "hello"));
+        assert!(m("h[ae]llo", "hallo"));
+        assert!(!m("h[ae]llo", "hillo"));
+        assert!(m("h[a-f]llo", "hello"));
+        assert!(m("h[f-a]llo", "hello"));
+        assert!(!m("h[a-d]llo", "hello"));
+        assert!(m("h[^e]llo", "hallo"));
+        assert!(!m("h[^e]llo", "hello"));
+        assert!(!m("h[^a-z]llo", "hello"));
+        assert!(m("key:[0-9][0-9]", "key:42"));
+        // the end of the pattern closes an unterminated class
+        assert!(m("h[ae", "ha"));
+        assert!(!m("h[ae", "hx"));
+        assert!(!m("[]", "]"));
+    }
+
+    #[test]
+    fn test_glob_escaping() {
+        assert!(m("\\*", "*"));
+        assert!(!m("\\*", "a"));
+        assert!(m("a\\?c", "a?c"));
+        assert!(!m("a\\?c", "abc"));
+        assert!(m("\\[x]", "[x]"));
+        assert!(m("[\\]]", "]"));
+        assert!(m("[\\-a]", "-"));
+        assert!(m("\\\\", "\\"));
+        // a trailing backslash matches itself
+        assert!(m("a\\", "a\\"));
+    }
+
+    #[test]
+    fn test_glob_binary_safe() {
+        assert!(glob_match(b"a*\xff", b"a\x00\r\n\xff"));
+        assert!(glob_match(b"[\x00-\x10]", b"\x05"));
+    }
+
+    #[test]
+    fn test_glob_deep_nesting_should_not_overflow() {
+        let pattern = "*a".repeat(10_000);
+        assert!(!m(&pattern, &"a".repeat(5_000)));
+        assert!(m(&"*a".repeat(100), &"a".repeat(100)));
+    }
+}
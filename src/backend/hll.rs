@@ -0,0 +1,253 @@
+use super::Backend;
+use crate::{BulkString, RespFrame};
+use dashmap::mapref::entry::Entry;
+use thiserror::Error;
+
+// 2^14 registers, the same precision redis uses (standard error 0.81%)
+const HLL_P: u32 = 14;
+const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_MAGIC: &[u8] = b"HYLL";
+// the seed redis hashes HyperLogLog elements with
+const HLL_HASH_SEED: u64 = 0xadc83b19;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("WRONGTYPE Key is not a valid HyperLogLog string value.")]
+pub struct InvalidHyperLogLog;
+
+/// Why PFADD couldn't add to the key
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PfAddError {
+    #[error("the key holds a value that isn't a string")]
+    WrongType,
+    #[error(transparent)]
+    Invalid(#[from] InvalidHyperLogLog),
+}
+
+/// Dense HyperLogLog, one byte per register. It is stored in the string keyspace as a
+/// BulkString of "HYLL" followed by the registers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS],
+        }
+    }
+
+    /// Add an element, returns whether a register was updated
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let hash = murmur_hash64a(element, HLL_HASH_SEED);
+
+        let index = (hash & (HLL_REGISTERS as u64 - 1)) as usize;
+        // the sentinel bit caps the rank for a zero remainder
+        let rank = ((hash >> HLL_P) | (1 << (64 - HLL_P))).trailing_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Keep the max of every register, the union of both sets
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (a, b) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *a = (*a).max(*b);
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        // linear counting is more accurate for small cardinalities
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+// MurmurHash64A as redis implements it, the registers an element lands in must not change
+// between runs or builds since they are stored in the key
+fn murmur_hash64a(data: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (data.len() as u64).wrapping_mul(M);
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= (b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<HyperLogLog> for RespFrame {
+    fn from(hll: HyperLogLog) -> Self {
+        let mut data = Vec::with_capacity(HLL_MAGIC.len() + HLL_REGISTERS);
+        data.extend_from_slice(HLL_MAGIC);
+        data.extend(hll.registers);
+        BulkString::new(data).into()
+    }
+}
+
+impl TryFrom<&RespFrame> for HyperLogLog {
+    type Error = InvalidHyperLogLog;
+    fn try_from(frame: &RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::BulkString(s)
+                if s.len() == HLL_MAGIC.len() + HLL_REGISTERS && s.starts_with(HLL_MAGIC) =>
+            {
+                Ok(Self {
+                    registers: s[HLL_MAGIC.len()..].to_vec(),
+                })
+            }
+            _ => Err(InvalidHyperLogLog),
+        }
+    }
+}
+
+impl Backend {
+    /// Add the elements to the HyperLogLog at `key`, creating it if needed.
+    /// Returns whether the estimated cardinality may have changed
+    pub fn pfadd(&self, key: &str, elements: &[String]) -> Result<bool, PfAddError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+        // every element has to be added, don't short circuit on the first change
+        let add_all =
+            |hll: &mut HyperLogLog| elements.iter().filter(|v| hll.add(v.as_bytes())).count() > 0;
+        let db = self.db();
+        // the other stores are checked under the entry lock, like MOVE does
+        match db.map.entry(key.to_string()) {
+            Entry::Occupied(mut v) => {
+                let mut hll = HyperLogLog::try_from(v.get())?;
+                let changed = add_all(&mut hll);
+                if changed {
+                    v.insert(hll.into());
+                }
+                Ok(changed)
+            }
+            Entry::Vacant(_) if db.has_container(key) => Err(PfAddError::WrongType),
+            Entry::Vacant(v) => {
+                let mut hll = HyperLogLog::new();
+                add_all(&mut hll);
+                v.insert(hll.into());
+                Ok(true)
+            }
+        }
+    }
+
+    /// Estimated cardinality of the union of the HyperLogLogs, missing keys count as empty
+    pub fn pfcount(&self, keys: &[String]) -> Result<u64, InvalidHyperLogLog> {
+        let mut merged = HyperLogLog::new();
+        for key in keys {
+            if let Some(value) = self.get(key) {
+                merged.merge(&HyperLogLog::try_from(&value)?);
+            }
+        }
+        Ok(merged.count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendConfig, MockClock};
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn test_hll_estimate_should_be_close() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..10_000 {
+            hll.add(format!("element-{}", i).as_bytes());
+        }
+        let count = hll.count() as f64;
+        assert!((count - 10_000.0).abs() / 10_000.0 < 0.03, "{}", count);
+    }
+
+    #[test]
+    fn test_murmur_hash64a_should_match_redis() {
+        assert_eq!(murmur_hash64a(b"", HLL_HASH_SEED), 0xd8dfea6585bc9732);
+        assert_eq!(murmur_hash64a(b"a", HLL_HASH_SEED), 0x53d2470a9b43b1a7);
+        assert_eq!(murmur_hash64a(b"hello", HLL_HASH_SEED), 0x0f656f01eecfe400);
+        assert_eq!(
+            murmur_hash64a(b"element-12345", HLL_HASH_SEED),
+            0xa454f2e012bcce75
+        );
+    }
+
+    #[test]
+    fn test_pfadd_should_report_changes() {
+        let backend = Backend::new();
+        let elements = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(backend.pfadd("hll", &elements), Ok(true));
+        assert_eq!(backend.pfadd("hll", &elements), Ok(false));
+        assert_eq!(backend.pfcount(&["hll".to_string()]), Ok(2));
+
+        backend.set("str".to_string(), BulkString::from("hello").into());
+        assert_eq!(
+            backend.pfadd("str", &elements),
+            Err(PfAddError::Invalid(InvalidHyperLogLog))
+        );
+    }
+
+    #[test]
+    fn test_pfadd_on_another_type_should_fail() {
+        let backend = Backend::new();
+        backend.hset(
+            "hash".to_string(),
+            "field".to_string(),
+            BulkString::from("v").into(),
+        );
+        let elements = vec!["a".to_string()];
+        assert_eq!(backend.pfadd("hash", &elements), Err(PfAddError::WrongType));
+        assert_eq!(backend.key_type("hash"), Some("hash"));
+        assert!(backend.db().map.get("hash").is_none());
+    }
+
+    #[test]
+    fn test_pfadd_on_an_expired_key_should_start_over() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let backend = Backend::with_config(BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        let elements = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(backend.pfadd("hll", &elements), Ok(true));
+        backend.expire("hll", Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+
+        assert_eq!(backend.pfadd("hll", &elements[..1]), Ok(true));
+        assert_eq!(backend.ttl("hll"), Some(None));
+        assert_eq!(backend.pfcount(&["hll".to_string()]), Ok(1));
+    }
+}
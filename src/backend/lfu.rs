@@ -0,0 +1,84 @@
+use rand::Rng;
+use std::time::Instant;
+
+// same defaults as redis: lfu-log-factor 10, lfu-decay-time 1 minute
+const LFU_INIT_VAL: u8 = 5;
+const LFU_LOG_FACTOR: f64 = 10.0;
+const LFU_DECAY_SECS: u64 = 60;
+
+/// Logarithmic access frequency counter of a key, the same scheme redis uses for LFU eviction:
+/// the more hits a key already has, the less likely another hit increments the counter,
+/// and the counter decays by one for every minute the key isn't accessed.
+#[derive(Debug, Clone)]
+pub struct AccessCounter {
+    counter: u8,
+    last_access: Instant,
+}
+
+impl AccessCounter {
+    pub fn new() -> Self {
+        Self {
+            counter: LFU_INIT_VAL,
+            last_access: Instant::now(),
+        }
+    }
+
+    /// The counter after applying the decay for the idle time
+    pub fn freq(&self) -> u8 {
+        let periods = self.last_access.elapsed().as_secs() / LFU_DECAY_SECS;
+        self.counter
+            .saturating_sub(periods.min(u8::MAX as u64) as u8)
+    }
+
+    pub fn last_access(&self) -> Instant {
+        self.last_access
+    }
+
+    pub fn hit(&mut self) {
+        let counter = self.freq();
+        self.counter = log_incr(counter);
+        self.last_access = Instant::now();
+    }
+}
+
+impl Default for AccessCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn log_incr(counter: u8) -> u8 {
+    if counter == u8::MAX {
+        return counter;
+    }
+    let base = counter.saturating_sub(LFU_INIT_VAL) as f64;
+    let p = 1.0 / (base * LFU_LOG_FACTOR + 1.0);
+    if rand::thread_rng().gen::<f64>() < p {
+        counter + 1
+    } else {
+        counter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_counter_grows_logarithmically() {
+        let mut counter = AccessCounter::new();
+        assert_eq!(counter.freq(), LFU_INIT_VAL);
+
+        // the first hit above the initial value is always counted
+        counter.hit();
+        assert_eq!(counter.freq(), LFU_INIT_VAL + 1);
+
+        for _ in 0..1000 {
+            counter.hit();
+        }
+        let freq = counter.freq();
+        assert!(freq > LFU_INIT_VAL + 1);
+        // a linear counter would have saturated long ago
+        assert!(freq < u8::MAX);
+    }
+}
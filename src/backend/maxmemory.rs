@@ -0,0 +1,339 @@
+use super::{aof::aof_entry, sample_keys, Backend};
+use crate::RespFrame;
+use rand::seq::SliceRandom;
+use std::{fmt, mem::size_of, str::FromStr, sync::atomic::Ordering};
+use thiserror::Error;
+
+// rough bookkeeping cost of a key or an element on top of its payload
+const ENTRY_OVERHEAD: usize = 48;
+
+// elements of a container measured when it changes, the MEMORY USAGE default
+const ACCOUNTING_SAMPLES: usize = 5;
+
+// keys of each database an eviction picks its victim from, redis' maxmemory-samples default
+const EVICTION_SAMPLES: usize = 5;
+
+/// What a write does once the used memory is over `maxmemory`, same names as redis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MaxMemoryPolicy {
+    /// reject the write with an OOM error
+    #[default]
+    NoEviction,
+    /// evict the least recently used keys
+    AllKeysLru,
+    /// evict random keys
+    AllKeysRandom,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("OOM command not allowed when used memory > 'maxmemory'.")]
+pub struct OutOfMemory;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("invalid maxmemory policy: {0}")]
+pub struct InvalidMaxMemoryPolicy(String);
+
+impl Backend {
+    /// Approximate memory used by the keys and their values of every database, in bytes. Each
+    /// database keeps a running total, only the keys changed since the last call are measured
+    pub fn used_memory(&self) -> usize {
+        self.all_dbs().map(|db| db.account_changed_keys()).sum()
+    }
+
+    // have the next used_memory measure the key again, everything that writes or deletes a key
+    // calls this, mostly through touch
+    pub(crate) fn mark_changed(&self, key: &str) {
+        let db = self.db();
+        if !db.unmeasured.contains(key) {
+            db.unmeasured.insert(key.to_string());
+        }
+    }
+
+    // fold the keys changed since the last call into the total of the selected database
+    fn account_changed_keys(&self) -> usize {
+        let db = self.db();
+        let changed = db
+            .unmeasured
+            .iter()
+            .map(|v| v.key().clone())
+            .collect::<Vec<_>>();
+        for key in changed {
+            // unmarked before measuring, a write racing with it marks the key again
+            db.unmeasured.remove(&key);
+            let old = match self.size_estimate(&key, ACCOUNTING_SAMPLES) {
+                Some(size) => {
+                    db.used_memory.fetch_add(size, Ordering::Relaxed);
+                    db.measured.insert(key, size)
+                }
+                None => db.measured.remove(&key).map(|(_, size)| size),
+            };
+            db.used_memory
+                .fetch_sub(old.unwrap_or(0), Ordering::Relaxed);
+        }
+        db.used_memory.load(Ordering::Relaxed)
+    }
+
+    /// Approximate memory used by a single key and its value, 0 for a missing key
+    pub fn key_memory(&self, key: &str) -> usize {
+        self.size_estimate(key, 0).unwrap_or(0)
+    }
+
+    /// Approximate bytes taken by the key, its value and the container overhead, as reported
+    /// by MEMORY USAGE. Only `samples` elements of a container are measured and the size of the
+    /// rest is extrapolated from them, 0 measures every element. None for a missing key
+    pub fn size_estimate(&self, key: &str, samples: usize) -> Option<usize> {
+        let db = self.db();
+        let value = if let Some(v) = db.map.get(key) {
+            frame_memory(v.value())
+        } else if let Some(v) = db.hmap.get(key) {
+            let fields = v.iter();
+            sampled_memory(fields, v.len(), samples, |v| {
+                ENTRY_OVERHEAD + v.key().len() + frame_memory(v.value())
+            })
+        } else if let Some(v) = db.lmap.get(key) {
+            sampled_memory(v.iter(), v.len(), samples, frame_memory)
+        } else if let Some(v) = db.zmap.get(key) {
+            sampled_memory(v.iter(), v.len(), samples, |(member, _)| {
+                ENTRY_OVERHEAD + member.len() + 8
+            })
+        } else if let Some(v) = db.smap.get(key) {
+            sampled_memory(v.iter(), v.len(), samples, |member| {
+                ENTRY_OVERHEAD + member.len()
+            })
+        } else {
+            return None;
+        };
+        Some(ENTRY_OVERHEAD + key.len() + value)
+    }
+
+    /// Make room for a write. Called before commands that may grow the dataset, it evicts keys
+    /// per the policy until the used memory fits in `maxmemory` again, or fails with
+    /// `OutOfMemory` when the policy doesn't allow evicting. A `maxmemory` of 0 is unlimited.
+    /// Like redis, every eviction only looks at a few random keys of each database, the least
+    /// recently used of them goes under allkeys-lru
+    pub fn free_memory(&self) -> Result<(), OutOfMemory> {
+        let limit = self.maxmemory();
+        if limit == 0 {
+            return Ok(());
+        }
+
+        while self.used_memory() > limit {
+            let key = match self.maxmemory_policy() {
+                MaxMemoryPolicy::NoEviction => None,
+                MaxMemoryPolicy::AllKeysLru => self
+                    .eviction_candidates()
+                    .into_iter()
+                    .filter_map(|(db, key)| {
+                        let last_access = db.db().access.get(&key)?.last_access();
+                        Some((last_access, db, key))
+                    })
+                    .min_by_key(|(last_access, _, _)| *last_access)
+                    .map(|(_, db, key)| (db, key)),
+                MaxMemoryPolicy::AllKeysRandom => self
+                    .eviction_candidates()
+                    .choose(&mut rand::thread_rng())
+                    .cloned(),
+            };
+            let Some((db, key)) = key else {
+                return Err(OutOfMemory);
+            };
+            db.evict(&key);
+        }
+        Ok(())
+    }
+
+    /// The memory limit in bytes, 0 is unlimited
+    pub fn maxmemory(&self) -> usize {
+        self.maxmemory.load(Ordering::Relaxed)
+    }
+
+    pub fn set_maxmemory(&self, bytes: usize) {
+        self.maxmemory.store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn maxmemory_policy(&self) -> MaxMemoryPolicy {
+        *self
+            .maxmemory_policy
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_maxmemory_policy(&self, policy: MaxMemoryPolicy) {
+        *self
+            .maxmemory_policy
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    // a few random keys of every database, each paired with the handle it lives in. Every key
+    // has an access counter, the sample is taken from those
+    fn eviction_candidates(&self) -> Vec<(Backend, String)> {
+        self.all_dbs()
+            .flat_map(|db| {
+                let keys = sample_keys(&db.db().access, EVICTION_SAMPLES);
+                keys.into_iter().map(move |key| (db.clone(), key))
+            })
+            .collect()
+    }
+
+    // evictions are written to the AOF as DEL, so the key doesn't come back on restart
+    fn evict(&self, key: &str) {
+        if self.aof_enabled() {
+            let entry = aof_entry(["del", key], []);
+            self.log_write(&entry, || (self.del(key) as i64).into());
+        } else {
+            self.del(key);
+        }
+    }
+}
+
+// size of the first `samples` of the `len` elements scaled up to all of them
+fn sampled_memory<T>(
+    elements: impl Iterator<Item = T>,
+    len: usize,
+    samples: usize,
+    size: impl Fn(T) -> usize,
+) -> usize {
+    let samples = match samples {
+        0 => len,
+        n => n.min(len),
+    };
+    if samples == 0 {
+        return 0;
+    }
+    let sampled: usize = elements.take(samples).map(size).sum();
+    sampled * len / samples
+}
+
+fn frame_memory(frame: &RespFrame) -> usize {
+    size_of::<RespFrame>() + frame.heap_size()
+}
+
+impl MaxMemoryPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaxMemoryPolicy::NoEviction => "noeviction",
+            MaxMemoryPolicy::AllKeysLru => "allkeys-lru",
+            MaxMemoryPolicy::AllKeysRandom => "allkeys-random",
+        }
+    }
+}
+
+impl fmt::Display for MaxMemoryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MaxMemoryPolicy {
+    type Err = InvalidMaxMemoryPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "noeviction" => Ok(MaxMemoryPolicy::NoEviction),
+            "allkeys-lru" => Ok(MaxMemoryPolicy::AllKeysLru),
+            "allkeys-random" => Ok(MaxMemoryPolicy::AllKeysRandom),
+            _ => Err(InvalidMaxMemoryPolicy(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendConfig, BulkString, ListSide};
+    use std::{thread, time::Duration};
+
+    fn backend(maxmemory: usize, maxmemory_policy: MaxMemoryPolicy) -> Backend {
+        Backend::with_config(BackendConfig {
+            maxmemory,
+            maxmemory_policy,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_maxmemory_policy_from_str() {
+        assert_eq!("allkeys-lru".parse(), Ok(MaxMemoryPolicy::AllKeysLru));
+        assert_eq!("NOEVICTION".parse(), Ok(MaxMemoryPolicy::NoEviction));
+        assert!("volatile-lru".parse::<MaxMemoryPolicy>().is_err());
+        assert_eq!(MaxMemoryPolicy::AllKeysRandom.to_string(), "allkeys-random");
+    }
+
+    #[test]
+    fn test_noeviction_should_fail_with_oom() {
+        let backend = backend(1024, MaxMemoryPolicy::NoEviction);
+        let mut ret = Ok(());
+        for i in 0..100 {
+            ret = backend.free_memory();
+            if ret.is_err() {
+                break;
+            }
+            backend.set(
+                format!("key:{}", i),
+                BulkString::new(vec![b'x'; 100]).into(),
+            );
+        }
+        assert_eq!(ret, Err(OutOfMemory));
+        assert!(backend.get("key:0").is_some());
+    }
+
+    #[test]
+    fn test_allkeys_lru_should_evict_old_keys() {
+        let backend = backend(1024, MaxMemoryPolicy::AllKeysLru);
+        for i in 0..100 {
+            backend.free_memory().unwrap();
+            backend.set(
+                format!("key:{}", i),
+                BulkString::new(vec![b'x'; 100]).into(),
+            );
+            // keep key:0 recently used
+            backend.get("key:0");
+            thread::sleep(Duration::from_micros(10));
+        }
+        assert!(backend.get("key:0").is_some());
+        assert!(backend.get("key:98").is_some());
+        assert!(backend.get("key:1").is_none());
+        assert!(backend.used_memory() <= 1024 + backend.key_memory("key:99"));
+    }
+
+    #[test]
+    fn test_allkeys_random_should_stay_under_the_limit() {
+        let backend = backend(1024, MaxMemoryPolicy::AllKeysRandom);
+        for i in 0..100 {
+            backend.free_memory().unwrap();
+            backend.set(
+                format!("key:{}", i),
+                BulkString::new(vec![b'x'; 100]).into(),
+            );
+        }
+        backend.free_memory().unwrap();
+        assert!(backend.used_memory() <= 1024);
+        assert!(backend.all_keys().count() < 100);
+    }
+
+    #[test]
+    fn test_used_memory_should_follow_writes_and_deletes() {
+        let backend = Backend::new();
+        assert_eq!(backend.used_memory(), 0);
+        backend.set("key".to_string(), BulkString::from("value").into());
+        assert_eq!(backend.used_memory(), backend.key_memory("key"));
+        backend.append("key".to_string(), &[b'x'; 100]).unwrap();
+        assert_eq!(backend.used_memory(), backend.key_memory("key"));
+
+        backend.rpush("list".to_string(), vec![BulkString::from("a").into(); 10]);
+        let other = backend.select(1).unwrap();
+        other.sadd("set".to_string(), ["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            backend.used_memory(),
+            backend.key_memory("key") + backend.key_memory("list") + other.key_memory("set")
+        );
+
+        backend.del("key");
+        while backend.pop("list", ListSide::Left).is_some() {}
+        assert_eq!(backend.used_memory(), other.key_memory("set"));
+        other.flushdb();
+        assert_eq!(backend.used_memory(), 0);
+        assert!(backend.db().measured.is_empty());
+    }
+}
@@ -1,36 +1,246 @@
-use crate::RespFrame;
-use dashmap::DashMap;
+mod aof;
+mod clock;
+mod deadline;
+mod expire;
+mod glob;
+mod hll;
+mod lfu;
+mod maxmemory;
+mod pubsub;
+mod range;
+mod scan;
+mod snapshot;
+mod zset;
+
+pub use self::clock::{Clock, MockClock, RealClock};
+pub use self::deadline::{CommandTimedOut, Deadline};
+pub(crate) use self::glob::glob_match;
+pub use self::hll::{HyperLogLog, InvalidHyperLogLog, PfAddError};
+pub use self::lfu::AccessCounter;
+pub use self::maxmemory::{InvalidMaxMemoryPolicy, MaxMemoryPolicy, OutOfMemory};
+pub use self::pubsub::Subscriber;
+pub use self::range::normalize_range;
+pub use self::snapshot::unix_timestamp;
+pub use self::zset::{ScoreRange, SortedSet};
+
+use crate::{BulkString, RespFrame};
+use dashmap::{
+    mapref::{entry::Entry, one::RefMut},
+    DashMap, DashSet, SharedValue,
+};
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
 use std::ops::Deref;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Why INCRBY and DECRBY couldn't update the value of a key
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum IncrError {
+    #[error("the key holds a value that isn't a string")]
+    WrongType,
+    #[error("value is not an integer or out of range")]
+    NotAnInteger,
+    #[error("increment or decrement would overflow")]
+    Overflow,
+}
+
+/// HRANDFIELD with a negative count wanting more fields than this is refused, the reply
+/// would be built in memory all at once
+pub const HRANDFIELD_MAX_REPEATED: u64 = 1_000_000;
+
+/// A count HRANDFIELD refuses, see `HRANDFIELD_MAX_REPEATED`
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("value is out of range")]
+pub struct CountOutOfRange;
+
+/// LMOVE found a key holding a value that isn't a list
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("the key holds a value that isn't a list")]
+pub struct NotAList;
+
+/// Why APPEND couldn't extend the value of a key
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AppendError {
+    #[error("the key holds a value that isn't a string")]
+    WrongType,
+    #[error("string exceeds maximum allowed size")]
+    TooLarge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSide {
+    Left,
+    Right,
+}
 
 #[derive(Debug, Clone)]
-pub struct Backend(Arc<BackendInner>);
+pub struct Backend {
+    inner: Arc<BackendInner>,
+    // the database this handle reads and writes, see `select`
+    db: usize,
+}
 
-#[derive(Debug)]
-pub struct BackendInner {
+#[derive(Debug, Clone)]
+pub struct BackendConfig {
+    /// file SAVE and BGSAVE write the snapshot to
+    pub dump_path: PathBuf,
+    /// log every write command to the append only file
+    pub appendonly: bool,
+    pub aof_path: PathBuf,
+    /// published messages queued for a subscriber before it gets disconnected
+    pub pubsub_buffer_limit: usize,
+    /// time budget of loop-heavy commands like KEYS, unlimited when None
+    pub command_timeout: Option<Duration>,
+    /// memory limit in bytes enforced before write commands, unlimited when 0
+    pub maxmemory: usize,
+    pub maxmemory_policy: MaxMemoryPolicy,
+    /// hashes with more fields than this switch from the listpack to the hashtable encoding
+    pub hash_max_listpack_entries: usize,
+    /// time source of key expiry
+    pub clock: Arc<dyn Clock>,
+    /// lowercase command name -> the name it's called by instead, an empty name disables the
+    /// command. Like rename-command in redis, a renamed command is unknown under its own name
+    pub rename_commands: HashMap<String, String>,
+    /// clients have to AUTH with this password before running other commands
+    pub requirepass: Option<String>,
+    /// number of databases SELECT can switch between
+    pub databases: usize,
+    /// reject the commands flagged as write with a READONLY error, like a read only replica
+    pub read_only: bool,
+    /// largest string value in bytes SET and APPEND may produce
+    pub max_string_size: usize,
+}
+
+/// The keys of one database, every store of a type plus the per key bookkeeping
+#[derive(Debug, Default)]
+pub struct Db {
     pub(crate) map: DashMap<String, RespFrame>,
     pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
+    pub(crate) lmap: DashMap<String, VecDeque<RespFrame>>,
+    pub(crate) zmap: DashMap<String, SortedSet>,
+    pub(crate) smap: DashMap<String, DashSet<String>>,
+    // hashes that outgrew the listpack encoding, they stay hashtable encoded until deleted
+    pub(crate) large_hashes: DashSet<String>,
+    pub(crate) access: DashMap<String, AccessCounter>,
+    // key -> unix time in milliseconds it expires at, by the configured clock
+    pub(crate) expires: DashMap<String, u64>,
+    // key names SCAN iterates over, see scan.rs
+    pub(crate) scan_keys: Mutex<Arc<Vec<String>>>,
+    // running total of `measured`, keys in `unmeasured` changed since, see maxmemory.rs
+    pub(crate) used_memory: AtomicUsize,
+    pub(crate) measured: DashMap<String, usize>,
+    pub(crate) unmeasured: DashSet<String>,
+}
+
+#[derive(Debug)]
+pub struct BackendInner {
+    // twice the number of databases, a snapshot is loaded into the ones no slot points to and
+    // then swapped in, see snapshot.rs
+    pub(crate) dbs: Vec<Db>,
+    // database index -> position in `dbs`, SWAPDB swaps two entries instead of moving data
+    pub(crate) db_slots: RwLock<Vec<usize>>,
+    // one snapshot load at a time, they all load into the same spare databases
+    pub(crate) snapshot_load: Mutex<()>,
+    // whether sweep_expired deletes expired keys, see expire.rs
+    pub(crate) active_expire: AtomicBool,
+    // channel -> subscribers keyed by client id
+    pub(crate) channels: DashMap<String, DashMap<u64, Subscriber>>,
+    // glob pattern -> subscribers keyed by client id
+    pub(crate) patterns: DashMap<String, DashMap<u64, Subscriber>>,
+    // 40 hex chars identifying this server instance, regenerated by DEBUG CHANGE-REPL-ID
+    pub(crate) run_id: RwLock<String>,
+    // unix timestamp of the server start
+    pub(crate) start_time: u64,
+    pub(crate) connected_clients: AtomicUsize,
+    pub(crate) next_client_id: AtomicU64,
+    pub(crate) config: BackendConfig,
+    // CONFIG SET may change these at runtime, they start out from the config
+    pub(crate) maxmemory: AtomicUsize,
+    pub(crate) maxmemory_policy: RwLock<MaxMemoryPolicy>,
+    pub(crate) requirepass: RwLock<Option<String>>,
+    pub(crate) read_only: AtomicBool,
+    // unix timestamp of the last successful save
+    pub(crate) last_save: AtomicU64,
+    pub(crate) bgsave_in_progress: AtomicBool,
+    pub(crate) aof: Mutex<aof::AofState>,
+    pub(crate) aof_rewrite_in_progress: AtomicBool,
+    // commands run under the read side, EXEC takes the write side so no other client's command
+    // runs in the middle of a transaction
+    pub(crate) transaction_lock: RwLock<()>,
 }
 
 impl Deref for Backend {
     type Target = BackendInner;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner
     }
 }
 
 impl Default for Backend {
     fn default() -> Self {
-        Self(Arc::new(BackendInner::default()))
+        Self::with_config(BackendConfig::default())
+    }
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self {
+            dump_path: PathBuf::from("dump.rdb"),
+            appendonly: false,
+            aof_path: PathBuf::from("appendonly.aof"),
+            pubsub_buffer_limit: 1024,
+            command_timeout: None,
+            maxmemory: 0,
+            maxmemory_policy: MaxMemoryPolicy::NoEviction,
+            hash_max_listpack_entries: 128,
+            clock: Arc::new(RealClock),
+            rename_commands: HashMap::new(),
+            requirepass: None,
+            databases: 16,
+            read_only: false,
+            max_string_size: 512 * 1024 * 1024,
+        }
     }
 }
 
 impl Default for BackendInner {
     fn default() -> Self {
+        Self::new(BackendConfig::default())
+    }
+}
+
+impl BackendInner {
+    fn new(config: BackendConfig) -> Self {
+        // at least one database, a handle always has one to work on
+        let databases = config.databases.max(1);
         Self {
-            map: DashMap::new(),
-            hmap: DashMap::new(),
+            dbs: (0..databases * 2).map(|_| Db::default()).collect(),
+            db_slots: RwLock::new((0..databases).collect()),
+            snapshot_load: Mutex::new(()),
+            active_expire: AtomicBool::new(true),
+            channels: DashMap::new(),
+            patterns: DashMap::new(),
+            run_id: RwLock::new(random_run_id()),
+            start_time: unix_timestamp(),
+            connected_clients: AtomicUsize::new(0),
+            next_client_id: AtomicU64::new(1),
+            maxmemory: AtomicUsize::new(config.maxmemory),
+            maxmemory_policy: RwLock::new(config.maxmemory_policy),
+            requirepass: RwLock::new(config.requirepass.clone()),
+            read_only: AtomicBool::new(config.read_only),
+            config,
+            // like redis, LASTSAVE starts out as the startup time
+            last_save: AtomicU64::new(unix_timestamp()),
+            bgsave_in_progress: AtomicBool::new(false),
+            aof: Mutex::new(aof::AofState::default()),
+            aof_rewrite_in_progress: AtomicBool::new(false),
+            transaction_lock: RwLock::new(()),
         }
     }
 }
@@ -40,26 +250,802 @@ impl Backend {
         Self::default()
     }
 
+    pub fn with_config(config: BackendConfig) -> Self {
+        Self {
+            inner: Arc::new(BackendInner::new(config)),
+            db: 0,
+        }
+    }
+
+    /// A handle on the database `index` sharing everything else with this one, like SELECT.
+    /// None when the index is out of range
+    pub fn select(&self, index: usize) -> Option<Backend> {
+        (index < self.databases()).then(|| Self {
+            inner: self.inner.clone(),
+            db: index,
+        })
+    }
+
+    /// Index of the database this handle works on
+    pub fn db_index(&self) -> usize {
+        self.db
+    }
+
+    pub fn databases(&self) -> usize {
+        self.dbs.len() / 2
+    }
+
+    /// Swap the data of two databases. Handles on either of them see the other one's keys right
+    /// away, returns false when an index is out of range. Commands resolve their database more
+    /// than once, the caller keeps other commands from running meanwhile, see network.rs
+    pub fn swapdb(&self, a: usize, b: usize) -> bool {
+        let mut slots = self.db_slots.write().unwrap_or_else(|e| e.into_inner());
+        if a >= slots.len() || b >= slots.len() {
+            return false;
+        }
+        slots.swap(a, b);
+        true
+    }
+
+    // a handle on each database in index order
+    pub(crate) fn all_dbs(&self) -> impl Iterator<Item = Backend> + '_ {
+        (0..self.databases()).filter_map(|i| self.select(i))
+    }
+
+    // the keys of the selected database
+    pub(crate) fn db(&self) -> &Db {
+        let slot = self.db_slots.read().unwrap_or_else(|e| e.into_inner())[self.db];
+        &self.dbs[slot]
+    }
+
     pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+        self.expire_if_needed(key);
+        let value = self.db().map.get(key).map(|v| v.value().clone());
+        self.touch_if(key, value.is_some());
+        value
     }
 
+    /// Set the string value, a value of another type under the key is replaced like in redis
     pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+        let db = self.db();
+        // the deadline goes first, an expiry of the old value in progress finishes before the
+        // new one is written, see expire_if_needed
+        db.expires.remove(&key);
+        self.touch(&key);
+        db.hmap.remove(&key);
+        db.lmap.remove(&key);
+        db.zmap.remove(&key);
+        db.smap.remove(&key);
+        db.large_hashes.remove(&key);
+        db.map.insert(key, value);
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
+    /// Append to the string at the key, creating it when missing. Returns the new length, the
+    /// value is left untouched when it would grow past `max_string_size`. `WrongType` when the
+    /// key holds another type
+    pub fn append(&self, key: String, value: &[u8]) -> Result<usize, AppendError> {
+        // checked up front too so a missing key isn't created empty
+        if value.len() > self.config.max_string_size {
+            return Err(AppendError::TooLarge);
+        }
+        self.expire_if_needed(&key);
+        self.touch(&key);
+        let Some(mut entry) = self
+            .db()
+            .string_entry(key, || BulkString::new(vec![]).into())
+        else {
+            return Err(AppendError::WrongType);
+        };
+        match entry.value_mut() {
+            RespFrame::BulkString(s) => {
+                if s.len().saturating_add(value.len()) > self.config.max_string_size {
+                    return Err(AppendError::TooLarge);
+                }
+                s.0.extend_from_slice(value);
+                Ok(s.len())
+            }
+            _ => Err(AppendError::WrongType),
+        }
+    }
+
+    /// Add `delta` to the integer stored as a string at the key, a missing key counts as 0.
+    /// Returns the new value, `WrongType` when the key holds another type
+    pub fn incr_by(&self, key: String, delta: i64) -> Result<i64, IncrError> {
+        self.expire_if_needed(&key);
+        self.touch(&key);
+        // the entry stays locked from the read to the write, concurrent increments don't race
+        let Some(mut entry) = self.db().string_entry(key, || BulkString::from("0").into()) else {
+            return Err(IncrError::WrongType);
+        };
+        let current = match entry.value() {
+            RespFrame::BulkString(s) => std::str::from_utf8(s)
+                .ok()
+                .and_then(|s| s.parse::<i64>().ok()),
+            RespFrame::Integer(n) => Some(*n),
+            _ => None,
+        };
+        let value = current
+            .ok_or(IncrError::NotAnInteger)?
+            .checked_add(delta)
+            .ok_or(IncrError::Overflow)?;
+        *entry.value_mut() = BulkString::from(value.to_string()).into();
+        Ok(value)
+    }
+
+    /// Name of the type stored at the key, as reported by TYPE
+    pub fn key_type(&self, key: &str) -> Option<&'static str> {
+        let db = self.db();
+        self.expire_if_needed(key);
+        if db.map.contains_key(key) {
+            Some("string")
+        } else if db.hmap.contains_key(key) {
+            Some("hash")
+        } else if db.lmap.contains_key(key) {
+            Some("list")
+        } else if db.zmap.contains_key(key) {
+            Some("zset")
+        } else if db.smap.contains_key(key) {
+            Some("set")
+        } else {
+            None
+        }
+    }
+
+    /// Internal encoding of the value at the key, as reported by OBJECT ENCODING. Only hashes
+    /// track a real transition, the other types report what redis would use for them
+    pub fn object_encoding(&self, key: &str) -> Option<&'static str> {
+        let db = self.db();
+        self.expire_if_needed(key);
+        if let Some(value) = db.map.get(key) {
+            let encoding = match value.value() {
+                RespFrame::BulkString(s)
+                    if std::str::from_utf8(s).is_ok_and(|s| s.parse::<i64>().is_ok()) =>
+                {
+                    "int"
+                }
+                RespFrame::BulkString(s) if s.len() > 44 => "raw",
+                _ => "embstr",
+            };
+            Some(encoding)
+        } else if db.hmap.contains_key(key) {
+            if db.large_hashes.contains(key) {
+                Some("hashtable")
+            } else {
+                Some("listpack")
+            }
+        } else if db.lmap.contains_key(key) {
+            Some("quicklist")
+        } else if db.zmap.contains_key(key) {
+            Some("skiplist")
+        } else if db.smap.contains_key(key) {
+            Some("hashtable")
+        } else {
+            None
+        }
+    }
+
+    /// The DEBUG OBJECT line of the key: its encoding, the size of its snapshot entry, the idle
+    /// time and the element count of a container. None for a missing key
+    pub fn debug_object(&self, key: &str) -> Option<String> {
+        let db = self.db();
+        let encoding = self.object_encoding(key)?;
+        let serialized = self.dump_entry(key)?.len();
+        let idle = db
+            .access
             .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+            .map(|v| v.last_access().elapsed().as_secs())
+            .unwrap_or(0);
+        let mut line = format!(
+            "encoding:{} serializedlength:{} lru_seconds_idle:{}",
+            encoding, serialized, idle
+        );
+        if let Some(v) = db.hmap.get(key) {
+            line.push_str(&format!(" fields:{}", v.len()));
+        } else if let Some(v) = db.lmap.get(key) {
+            line.push_str(&format!(" length:{}", v.len()));
+        } else if let Some(v) = db.zmap.get(key) {
+            line.push_str(&format!(" members:{}", v.len()));
+        } else if let Some(v) = db.smap.get(key) {
+            line.push_str(&format!(" members:{}", v.len()));
+        }
+        Some(line)
+    }
+
+    /// Names of the keys in every store, in no particular order. Keys whose time to live is over
+    /// are left out even when they haven't been deleted yet
+    pub fn all_keys(&self) -> impl Iterator<Item = String> + '_ {
+        let db = self.db();
+        let now = self.config.clock.now_ms();
+        // collected before the deadlines are looked up, expiring a key locks its deadline first
+        // and the stores after it, see expire_if_needed
+        let keys = db
+            .map
+            .iter()
+            .map(|v| v.key().clone())
+            .chain(db.hmap.iter().map(|v| v.key().clone()))
+            .chain(db.lmap.iter().map(|v| v.key().clone()))
+            .chain(db.zmap.iter().map(|v| v.key().clone()))
+            .chain(db.smap.iter().map(|v| v.key().clone()))
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter(move |key| db.expires.get(key).is_none_or(|at| *at > now))
+    }
+
+    /// Remove the key from every store, returns whether it existed. A key whose time to live is
+    /// over doesn't count
+    pub fn del(&self, key: &str) -> bool {
+        if self.expire_if_needed(key) {
+            return false;
+        }
+        let db = self.db();
+        let removed = db.remove(key);
+        db.expires.remove(key);
+        self.mark_changed(key);
+        removed
+    }
+
+    /// Move the key with its time to live to the database `index`, returns false when the key
+    /// is missing or the target database already has it
+    pub fn move_key(&self, key: &str, index: usize) -> bool {
+        let Some(target) = self.select(index) else {
+            return false;
+        };
+        let (src, dst) = (self.db(), target.db());
+        if std::ptr::eq(src, dst) {
+            return false;
+        }
+        self.expire_if_needed(key);
+        target.expire_if_needed(key);
+        let Some(value) = src.take(key) else {
+            return false;
+        };
+        // the entry of the string store locks the key name in the target while it's checked
+        // and filled, every MOVE to that database goes through it first
+        let conflict = match dst.map.entry(key.to_string()) {
+            Entry::Vacant(slot) if !dst.has_container(key) => {
+                match value {
+                    Value::String(value) => {
+                        slot.insert(value);
+                    }
+                    value => dst.put(key.to_string(), value),
+                }
+                None
+            }
+            _ => Some(value),
+        };
+        // the target has the key, it goes back where it came from. Only taken out of the
+        // source, no lock is held across the two databases
+        if let Some(value) = conflict {
+            src.put(key.to_string(), value);
+            return false;
+        }
+        move_entry(&src.expires, &dst.expires, key);
+        move_entry(&src.access, &dst.access, key);
+        if src.large_hashes.remove(key).is_some() {
+            dst.large_hashes.insert(key.to_string());
+        }
+        self.mark_changed(key);
+        target.mark_changed(key);
+        true
+    }
+
+    /// Remove every key of every database
+    pub fn flushall(&self) {
+        for db in self.all_dbs() {
+            db.flushdb();
+        }
+    }
+
+    /// Remove every key of the selected database
+    pub fn flushdb(&self) {
+        self.db().clear();
+    }
+
+    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
+        self.expire_if_needed(key);
+        let hmap = self.db().hmap.get(key);
+        self.touch_if(key, hmap.is_some());
+        hmap.and_then(|v| v.get(field).map(|v| v.value().clone()))
     }
 
     pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
+        self.expire_if_needed(&key);
+        self.touch(&key);
+        let hmap = self.db().hmap.entry(key.clone()).or_default();
         hmap.insert(field, value);
+        if hmap.len() > self.config.hash_max_listpack_entries {
+            self.db().large_hashes.insert(key);
+        }
+    }
+
+    /// Set the field only if the hash doesn't have it yet, returns whether it was set. The
+    /// check and the insert happen under the lock of the hash entry
+    pub fn hsetnx(&self, key: String, field: String, value: RespFrame) -> bool {
+        self.expire_if_needed(&key);
+        self.touch(&key);
+        let hmap = self.db().hmap.entry(key.clone()).or_default();
+        if hmap.contains_key(&field) {
+            return false;
+        }
+        hmap.insert(field, value);
+        if hmap.len() > self.config.hash_max_listpack_entries {
+            self.db().large_hashes.insert(key);
+        }
+        true
     }
 
     pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+        self.expire_if_needed(key);
+        let hmap = self.db().hmap.get(key).map(|v| v.clone());
+        self.touch_if(key, hmap.is_some());
+        hmap
+    }
+
+    /// Random fields of the hash with their values, None when the key is missing. A positive
+    /// count returns up to count distinct fields, a negative one exactly -count fields which
+    /// may repeat, up to `HRANDFIELD_MAX_REPEATED`
+    pub fn hrandfield(
+        &self,
+        key: &str,
+        count: i64,
+    ) -> Result<Option<Vec<(String, RespFrame)>>, CountOutOfRange> {
+        if count < 0 && count.unsigned_abs() > HRANDFIELD_MAX_REPEATED {
+            return Err(CountOutOfRange);
+        }
+        self.expire_if_needed(key);
+        let Some(hmap) = self.db().hmap.get(key) else {
+            return Ok(None);
+        };
+        self.touch(key);
+        let mut rng = rand::thread_rng();
+        let entries = hmap.iter().map(|v| (v.key().clone(), v.value().clone()));
+        if count >= 0 {
+            // never more than the hash holds, choose_multiple allocates room for all of them
+            let count = (count as u64).min(hmap.len() as u64) as usize;
+            let mut fields = entries.choose_multiple(&mut rng, count);
+            // choose_multiple keeps the iteration order, mix it up like redis does
+            fields.shuffle(&mut rng);
+            return Ok(Some(fields));
+        }
+        let entries = entries.collect::<Vec<_>>();
+        let fields = (0..count.unsigned_abs())
+            .filter_map(|_| entries.choose(&mut rng).cloned())
+            .collect();
+        Ok(Some(fields))
+    }
+
+    /// Add the members to the set, returns how many of them were new
+    pub fn sadd(&self, key: String, members: impl IntoIterator<Item = String>) -> usize {
+        self.expire_if_needed(&key);
+        self.touch(&key);
+        let set = self.db().smap.entry(key).or_default();
+        members
+            .into_iter()
+            .filter(|member| set.insert(member.clone()))
+            .count()
+    }
+
+    /// Whether each member is in the set, in the order given. A missing set has no members
+    pub fn smismember(&self, key: &str, members: &[String]) -> Vec<bool> {
+        self.expire_if_needed(key);
+        let set = self.db().smap.get(key);
+        self.touch_if(key, set.is_some());
+        members
+            .iter()
+            .map(|member| set.as_ref().is_some_and(|v| v.contains(member)))
+            .collect()
+    }
+
+    /// Number of members in the intersection of the sets, counting stops at `limit` unless it's
+    /// 0. A missing set is empty and so is the intersection with it
+    pub fn sintercard(&self, keys: &[String], limit: usize) -> usize {
+        let db = self.db();
+        let mut sizes = Vec::with_capacity(keys.len());
+        for key in keys {
+            self.expire_if_needed(key);
+            match db.smap.get(key) {
+                Some(set) => sizes.push((set.len(), key)),
+                None => return 0,
+            }
+            self.touch(key);
+        }
+        // only one set is locked at a time, the members of the smallest one are the candidates
+        sizes.sort_unstable();
+        let Some(((_, smallest), others)) = sizes.split_first() else {
+            return 0;
+        };
+        let candidates = match db.smap.get(*smallest) {
+            Some(set) => set.iter().map(|v| v.key().clone()).collect::<Vec<_>>(),
+            None => return 0,
+        };
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        candidates
+            .iter()
+            .filter(|member| {
+                others
+                    .iter()
+                    .all(|(_, key)| db.smap.get(*key).is_some_and(|set| set.contains(*member)))
+            })
+            .take(limit)
+            .count()
+    }
+
+    pub fn rpush(&self, key: String, values: impl IntoIterator<Item = RespFrame>) -> usize {
+        self.expire_if_needed(&key);
+        self.touch(&key);
+        let mut list = self.db().lmap.entry(key).or_default();
+        list.extend(values);
+        list.len()
+    }
+
+    /// Push the values to the head of the list one by one, so they end up in reverse order.
+    /// Returns the length of the list
+    pub fn lpush(&self, key: String, values: impl IntoIterator<Item = RespFrame>) -> usize {
+        self.expire_if_needed(&key);
+        self.touch(&key);
+        let mut list = self.db().lmap.entry(key).or_default();
+        for value in values {
+            list.push_front(value);
+        }
+        list.len()
+    }
+
+    /// Push the values to the given side of the list only when the key already holds a list,
+    /// the values end up in the same order as `rpush` and `lpush` leave them. Returns the
+    /// length of the list, 0 when the key is missing
+    pub fn pushx(&self, key: &str, side: ListSide, values: Vec<RespFrame>) -> usize {
+        self.expire_if_needed(key);
+        let Some(mut list) = self.db().lmap.get_mut(key) else {
+            return 0;
+        };
+        for value in values {
+            match side {
+                ListSide::Left => list.push_front(value),
+                ListSide::Right => list.push_back(value),
+            }
+        }
+        let len = list.len();
+        drop(list);
+        self.touch(key);
+        len
+    }
+
+    /// Insert the value right before or after the first element equal to `pivot`. Returns the
+    /// new length of the list, 0 when the key is missing and None when the pivot isn't found
+    pub fn linsert(
+        &self,
+        key: &str,
+        before: bool,
+        pivot: &RespFrame,
+        value: RespFrame,
+    ) -> Option<usize> {
+        self.expire_if_needed(key);
+        let Some(mut list) = self.db().lmap.get_mut(key) else {
+            return Some(0);
+        };
+        let index = list.iter().position(|v| v == pivot)?;
+        list.insert(if before { index } else { index + 1 }, value);
+        let len = list.len();
+        drop(list);
+        self.touch(key);
+        Some(len)
+    }
+
+    /// Pop an element from one side of the list, the key is deleted once the list is empty
+    pub fn pop(&self, key: &str, side: ListSide) -> Option<RespFrame> {
+        self.expire_if_needed(key);
+        let db = self.db();
+        let value = pop_side(&mut *db.lmap.get_mut(key)?, side)?;
+        if db.lmap.remove_if(key, |_, list| list.is_empty()).is_some() {
+            db.access.remove(key);
+            self.mark_changed(key);
+        } else {
+            self.touch(key);
+        }
+        Some(value)
+    }
+
+    // the number of elements of the list at the key, 0 for a missing key
+    #[cfg(test)]
+    pub(crate) fn list_len(&self, key: &str) -> usize {
+        self.db().lmap.get(key).map(|v| v.len()).unwrap_or(0)
+    }
+
+    /// Pop an element from one side of `src` and push it to one side of `dst`. Both lists stay
+    /// locked from the pop to the push, and under those locks neither key may hold another
+    /// type, `NotAList` otherwise. None when `src` is missing
+    pub fn lmove(
+        &self,
+        src: &str,
+        dst: &str,
+        from: ListSide,
+        to: ListSide,
+    ) -> Result<Option<RespFrame>, NotAList> {
+        let db = self.db();
+        self.expire_if_needed(src);
+        self.expire_if_needed(dst);
+        if src == dst {
+            if let Some(mut list) = db.lmap.get_mut(src) {
+                let Some(value) = pop_side(&mut list, from) else {
+                    return Ok(None);
+                };
+                push_side(&mut list, to, value.clone());
+                drop(list);
+                self.touch(src);
+                return Ok(Some(value));
+            }
+        }
+
+        // the stores are always locked in the order map, hmap, lmap, zmap, smap. The other
+        // stores are only read, holding them keeps either key from turning into another type
+        let strings = [db.map.get(src), db.map.get(dst)];
+        let hashes = [db.hmap.get(src), db.hmap.get(dst)];
+        // the lists are locked by shard, in shard order so moves in opposite directions can't
+        // deadlock. Both keys may live in the same shard, then it's locked once
+        let (a, b) = (db.lmap.determine_map(src), db.lmap.determine_map(dst));
+        let shards = db.lmap.shards();
+        let mut low = shards[a.min(b)].write();
+        let mut high = (a != b).then(|| shards[a.max(b)].write());
+        let zsets = [db.zmap.get(src), db.zmap.get(dst)];
+        let sets = [db.smap.get(src), db.smap.get(dst)];
+        if strings.iter().any(Option::is_some)
+            || hashes.iter().any(Option::is_some)
+            || zsets.iter().any(Option::is_some)
+            || sets.iter().any(Option::is_some)
+        {
+            return Err(NotAList);
+        }
+
+        let src_shard = match high.as_mut() {
+            Some(high) if a > b => &mut **high,
+            _ => &mut *low,
+        };
+        let Some(list) = src_shard.get_mut(src) else {
+            return Ok(None);
+        };
+        let list = list.get_mut();
+        let Some(value) = pop_side(list, from) else {
+            return Ok(None);
+        };
+        let src_emptied = list.is_empty();
+        if src_emptied {
+            src_shard.remove(src);
+        }
+        let dst_shard = match high.as_mut() {
+            Some(high) if b > a => &mut **high,
+            _ => &mut *low,
+        };
+        let list = dst_shard
+            .entry(dst.to_string())
+            .or_insert_with(|| SharedValue::new(VecDeque::new()));
+        push_side(list.get_mut(), to, value.clone());
+        drop((low, high, strings, hashes, zsets, sets));
+
+        if src_emptied {
+            db.access.remove(src);
+            self.mark_changed(src);
+        } else {
+            self.touch(src);
+        }
+        self.touch(dst);
+        Ok(Some(value))
+    }
+
+    /// Access frequency of the key as reported by OBJECT FREQ
+    pub fn freq(&self, key: &str) -> Option<u8> {
+        self.db().access.get(key).map(|v| v.freq())
+    }
+
+    // record a hit on the key for the LFU counter, writes go through here too so the key is
+    // measured again for the used memory
+    fn touch(&self, key: &str) {
+        self.mark_changed(key);
+        match self.db().access.get_mut(key) {
+            Some(mut counter) => counter.hit(),
+            None => {
+                let mut counter = AccessCounter::new();
+                counter.hit();
+                self.db().access.insert(key.to_string(), counter);
+            }
+        }
+    }
+
+    fn touch_if(&self, key: &str, exists: bool) {
+        if exists {
+            self.touch(key);
+        }
+    }
+
+    pub fn connected_clients(&self) -> usize {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    pub fn run_id(&self) -> String {
+        self.run_id
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replace the run id with a new random one
+    pub fn change_run_id(&self) {
+        *self.run_id.write().unwrap_or_else(|e| e.into_inner()) = random_run_id();
+    }
+
+    /// Seconds since the server started
+    pub fn uptime(&self) -> u64 {
+        unix_timestamp().saturating_sub(self.start_time)
+    }
+
+    /// Password clients have to AUTH with, None when no AUTH is needed
+    pub fn requirepass(&self) -> Option<String> {
+        self.requirepass
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Change the password like CONFIG SET requirepass, an empty one turns AUTH off. Clients
+    /// that already authenticated stay authenticated
+    pub fn set_requirepass(&self, password: &str) {
+        *self.requirepass.write().unwrap_or_else(|e| e.into_inner()) =
+            (!password.is_empty()).then(|| password.to_string());
+    }
+
+    /// Whether write commands are rejected with a READONLY error
+    pub fn read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// Turn the read only mode on or off like CONFIG SET replica-read-only
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Hand out a unique id for a new client connection
+    pub fn next_client_id(&self) -> u64 {
+        self.next_client_id.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+fn random_run_id() -> String {
+    let mut rng = rand::thread_rng();
+    (0..40)
+        .map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap_or('0'))
+        .collect()
+}
+
+// the value of a key taken out of whichever store held it
+enum Value {
+    String(RespFrame),
+    Hash(DashMap<String, RespFrame>),
+    List(VecDeque<RespFrame>),
+    ZSet(SortedSet),
+    Set(DashSet<String>),
+}
+
+impl Db {
+    fn take(&self, key: &str) -> Option<Value> {
+        if let Some((_, v)) = self.map.remove(key) {
+            Some(Value::String(v))
+        } else if let Some((_, v)) = self.hmap.remove(key) {
+            Some(Value::Hash(v))
+        } else if let Some((_, v)) = self.lmap.remove(key) {
+            Some(Value::List(v))
+        } else if let Some((_, v)) = self.zmap.remove(key) {
+            Some(Value::ZSet(v))
+        } else {
+            self.smap.remove(key).map(|(_, v)| Value::Set(v))
+        }
+    }
+
+    fn put(&self, key: String, value: Value) {
+        match value {
+            Value::String(v) => self.map.insert(key, v).map(|_| ()),
+            Value::Hash(v) => self.hmap.insert(key, v).map(|_| ()),
+            Value::List(v) => self.lmap.insert(key, v).map(|_| ()),
+            Value::ZSet(v) => self.zmap.insert(key, v).map(|_| ()),
+            Value::Set(v) => self.smap.insert(key, v).map(|_| ()),
+        };
+    }
+
+    // remove the value of the key from whichever store holds it with its bookkeeping, except
+    // for the deadline. Returns whether there was a value
+    fn remove(&self, key: &str) -> bool {
+        let removed = self.map.remove(key).is_some()
+            | self.hmap.remove(key).is_some()
+            | self.lmap.remove(key).is_some()
+            | self.zmap.remove(key).is_some()
+            | self.smap.remove(key).is_some();
+        self.large_hashes.remove(key);
+        self.access.remove(key);
+        removed
+    }
+
+    pub(crate) fn clear(&self) {
+        self.map.clear();
+        self.hmap.clear();
+        self.lmap.clear();
+        self.zmap.clear();
+        self.smap.clear();
+        self.large_hashes.clear();
+        self.expires.clear();
+        self.access.clear();
+        self.measured.clear();
+        self.unmeasured.clear();
+        self.used_memory.store(0, Ordering::Relaxed);
+    }
+
+    // the locked entry of the key in the string store, a missing key is created with `default`.
+    // None when another store has the key, checked under the entry lock so it can't change type
+    // in between
+    fn string_entry(
+        &self,
+        key: String,
+        default: impl FnOnce() -> RespFrame,
+    ) -> Option<RefMut<'_, String, RespFrame>> {
+        match self.map.entry(key) {
+            Entry::Occupied(entry) => Some(entry.into_ref()),
+            Entry::Vacant(entry) if self.has_container(entry.key()) => None,
+            Entry::Vacant(entry) => Some(entry.insert(default())),
+        }
+    }
+
+    // whether one of the container stores has the key
+    fn has_container(&self, key: &str) -> bool {
+        self.hmap.contains_key(key)
+            || self.lmap.contains_key(key)
+            || self.zmap.contains_key(key)
+            || self.smap.contains_key(key)
+    }
+}
+
+// up to `count` distinct keys of the map picked at random. dashmap has no random access, the
+// sample starts at a random position of a random shard and goes on from there, so it costs a
+// walk over one shard instead of the whole map
+pub(crate) fn sample_keys<V>(map: &DashMap<String, V>, count: usize) -> Vec<String> {
+    let shards = map.shards();
+    let mut rng = rand::thread_rng();
+    let first = rng.gen_range(0..shards.len());
+    let mut keys = Vec::with_capacity(count);
+    for i in 0..shards.len() {
+        if keys.len() >= count {
+            break;
+        }
+        let shard = shards[(first + i) % shards.len()].read();
+        if shard.is_empty() {
+            continue;
+        }
+        let start = rng.gen_range(0..shard.len());
+        let wanted = count - keys.len();
+        keys.extend(
+            shard
+                .keys()
+                .skip(start)
+                .chain(shard.keys().take(start))
+                .take(wanted)
+                .cloned(),
+        );
+    }
+    keys
+}
+
+fn move_entry<V>(src: &DashMap<String, V>, dst: &DashMap<String, V>, key: &str) {
+    if let Some((key, value)) = src.remove(key) {
+        dst.insert(key, value);
+    }
+}
+
+fn pop_side(list: &mut VecDeque<RespFrame>, side: ListSide) -> Option<RespFrame> {
+    match side {
+        ListSide::Left => list.pop_front(),
+        ListSide::Right => list.pop_back(),
+    }
+}
+
+fn push_side(list: &mut VecDeque<RespFrame>, side: ListSide, value: RespFrame) {
+    match side {
+        ListSide::Left => list.push_front(value),
+        ListSide::Right => list.push_back(value),
     }
 }
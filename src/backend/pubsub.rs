@@ -0,0 +1,279 @@
+use super::{glob_match, Backend};
+use crate::{BulkString, RespArray, RespEncode, RespFrame};
+use bytes::Bytes;
+use dashmap::DashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, mpsc::error::TrySendError, Notify};
+use tracing::warn;
+
+/// The sending half of a subscribed connection, published messages are queued through it.
+/// The queue is bounded, a subscriber whose queue is full gets evicted instead of buffering
+/// messages without limit (redis' client-output-buffer-limit for pubsub clients).
+/// Messages are queued already encoded, so one buffer is shared by all the receivers
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    tx: mpsc::Sender<Bytes>,
+    evict: Arc<Notify>,
+}
+
+impl Subscriber {
+    pub fn new(tx: mpsc::Sender<Bytes>) -> Self {
+        Self {
+            tx,
+            evict: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Resolves once the subscriber has been evicted for falling behind
+    pub async fn evicted(&self) {
+        self.evict.notified().await
+    }
+
+    fn send(&self, message: Bytes) -> Result<(), TrySendError<Bytes>> {
+        let ret = self.tx.try_send(message);
+        if let Err(TrySendError::Full(_)) = ret {
+            // notify_one keeps the permit, so the eviction isn't lost if nobody waits yet
+            self.evict.notify_one();
+        }
+        ret
+    }
+}
+
+// channel or pattern -> subscribers keyed by client id
+type Registry = DashMap<String, DashMap<u64, Subscriber>>;
+
+impl Backend {
+    pub fn subscribe(&self, channel: &str, client_id: u64, subscriber: Subscriber) {
+        add_subscriber(&self.channels, channel, client_id, subscriber);
+    }
+
+    pub fn unsubscribe(&self, channel: &str, client_id: u64) {
+        remove_subscriber(&self.channels, channel, client_id);
+    }
+
+    /// Subscribe to every channel matching the glob `pattern`
+    pub fn psubscribe(&self, pattern: &str, client_id: u64, subscriber: Subscriber) {
+        add_subscriber(&self.patterns, pattern, client_id, subscriber);
+    }
+
+    pub fn punsubscribe(&self, pattern: &str, client_id: u64) {
+        remove_subscriber(&self.patterns, pattern, client_id);
+    }
+
+    /// Send the message to every subscriber of the channel and of every pattern matching it,
+    /// returns the number of receivers. A client subscribed through both receives it twice
+    pub fn publish(&self, channel: &str, message: RespFrame) -> usize {
+        let mut count = 0;
+        let mut evicted = Vec::new();
+        if let Some(subscribers) = self.channels.get(channel) {
+            let frame: RespFrame = RespArray::new([
+                BulkString::from("message").into(),
+                BulkString::from(channel).into(),
+                message.clone(),
+            ])
+            .into();
+            count += deliver(&subscribers, frame, |id| {
+                evicted.push((false, channel.to_string(), id))
+            });
+        }
+        for v in self.patterns.iter() {
+            if !glob_match(v.key().as_bytes(), channel.as_bytes()) {
+                continue;
+            }
+            let frame: RespFrame = RespArray::new([
+                BulkString::from("pmessage").into(),
+                BulkString::from(v.key().as_str()).into(),
+                BulkString::from(channel).into(),
+                message.clone(),
+            ])
+            .into();
+            count += deliver(v.value(), frame, |id| {
+                evicted.push((true, v.key().clone(), id))
+            });
+        }
+
+        for (is_pattern, name, client_id) in evicted {
+            warn!("Subscriber {} can't keep up with {}", client_id, name);
+            if is_pattern {
+                self.punsubscribe(&name, client_id);
+            } else {
+                self.unsubscribe(&name, client_id);
+            }
+        }
+        count
+    }
+
+    /// Channels with at least one subscriber, sorted, only the ones matching the glob
+    /// `pattern` when given. Pattern subscriptions don't count
+    pub fn pubsub_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        let mut channels = self
+            .channels
+            .iter()
+            .filter(|v| !v.value().is_empty())
+            .filter(|v| pattern.is_none_or(|p| glob_match(p.as_bytes(), v.key().as_bytes())))
+            .map(|v| v.key().clone())
+            .collect::<Vec<_>>();
+        channels.sort_unstable();
+        channels
+    }
+
+    /// Number of subscribers of the channel, pattern subscriptions don't count
+    pub fn pubsub_numsub(&self, channel: &str) -> usize {
+        self.channels.get(channel).map_or(0, |v| v.len())
+    }
+
+    /// Number of distinct patterns subscribed to
+    pub fn pubsub_numpat(&self) -> usize {
+        self.patterns
+            .iter()
+            .filter(|v| !v.value().is_empty())
+            .count()
+    }
+}
+
+fn add_subscriber(registry: &Registry, name: &str, client_id: u64, subscriber: Subscriber) {
+    registry
+        .entry(name.to_string())
+        .or_default()
+        .insert(client_id, subscriber);
+}
+
+fn remove_subscriber(registry: &Registry, name: &str, client_id: u64) {
+    if let Some(subscribers) = registry.get(name) {
+        subscribers.remove(&client_id);
+    }
+    registry.remove_if(name, |_, subscribers| subscribers.is_empty());
+}
+
+// send the frame to every subscriber, the ones whose queue is full are passed to `evict`.
+// The frame is encoded once, each subscriber gets a reference counted handle to the same
+// buffer. Returns the number of receivers
+fn deliver(
+    subscribers: &DashMap<u64, Subscriber>,
+    frame: RespFrame,
+    mut evict: impl FnMut(u64),
+) -> usize {
+    let message = Bytes::from(frame.encode());
+    let mut count = 0;
+    for v in subscribers.iter() {
+        match v.value().send(message.clone()) {
+            Ok(_) => count += 1,
+            Err(TrySendError::Full(_)) => evict(*v.key()),
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_should_reach_subscribers() {
+        let backend = Backend::new();
+        let (tx, mut rx) = mpsc::channel(16);
+        backend.subscribe("news", 1, Subscriber::new(tx));
+
+        let message: RespFrame = BulkString::from("hello").into();
+        assert_eq!(backend.publish("news", message.clone()), 1);
+        assert_eq!(backend.publish("sports", message.clone()), 0);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespFrame::from(RespArray::new([
+                BulkString::from("message").into(),
+                BulkString::from("news").into(),
+                message.clone(),
+            ]))
+            .encode()
+        );
+
+        backend.unsubscribe("news", 1);
+        assert_eq!(backend.publish("news", message), 0);
+        assert!(backend.channels.is_empty());
+    }
+
+    #[test]
+    fn test_publish_should_reach_pattern_subscribers() {
+        let backend = Backend::new();
+        let (tx, mut rx) = mpsc::channel(16);
+        let subscriber = Subscriber::new(tx);
+        backend.psubscribe("news.*", 1, subscriber.clone());
+        backend.subscribe("news.tech", 1, subscriber);
+
+        let message: RespFrame = BulkString::from("hello").into();
+        assert_eq!(backend.publish("news.tech", message.clone()), 2);
+        assert_eq!(backend.publish("sports.tech", message.clone()), 0);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespFrame::from(RespArray::new([
+                BulkString::from("message").into(),
+                BulkString::from("news.tech").into(),
+                message.clone(),
+            ]))
+            .encode()
+        );
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespFrame::from(RespArray::new([
+                BulkString::from("pmessage").into(),
+                BulkString::from("news.*").into(),
+                BulkString::from("news.tech").into(),
+                message.clone(),
+            ]))
+            .encode()
+        );
+
+        backend.punsubscribe("news.*", 1);
+        assert_eq!(backend.publish("news.sports", message), 0);
+        assert!(backend.patterns.is_empty());
+    }
+
+    #[test]
+    fn test_pubsub_introspection() {
+        let backend = Backend::new();
+        let (tx, _rx) = mpsc::channel(16);
+        let subscriber = Subscriber::new(tx);
+        backend.subscribe("news.tech", 1, subscriber.clone());
+        backend.subscribe("news.tech", 2, subscriber.clone());
+        backend.subscribe("sports", 1, subscriber.clone());
+        backend.psubscribe("news.*", 1, subscriber.clone());
+        backend.psubscribe("news.*", 2, subscriber);
+
+        assert_eq!(backend.pubsub_channels(None), vec!["news.tech", "sports"]);
+        assert_eq!(backend.pubsub_channels(Some("news.*")), vec!["news.tech"]);
+        assert_eq!(backend.pubsub_numsub("news.tech"), 2);
+        assert_eq!(backend.pubsub_numsub("nosuchchannel"), 0);
+        assert_eq!(backend.pubsub_numpat(), 1);
+
+        backend.unsubscribe("sports", 1);
+        assert_eq!(backend.pubsub_channels(None), vec!["news.tech"]);
+    }
+
+    #[test]
+    fn test_publish_should_share_one_encoded_message() {
+        let backend = Backend::new();
+        let receivers = (0..100)
+            .map(|id| {
+                let (tx, rx) = mpsc::channel(16);
+                backend.subscribe("news", id, Subscriber::new(tx));
+                rx
+            })
+            .collect::<Vec<_>>();
+
+        let message: RespFrame = BulkString::from("hello").into();
+        assert_eq!(backend.publish("news", message), 100);
+        let messages = receivers
+            .into_iter()
+            .map(|mut rx| rx.try_recv().unwrap())
+            .collect::<Vec<_>>();
+        // every subscriber holds the very same buffer, it was encoded a single time
+        assert!(messages
+            .iter()
+            .all(|v| v.as_ptr() == messages[0].as_ptr() && v == &messages[0]));
+        assert_eq!(
+            messages[0],
+            b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n"[..]
+        );
+    }
+}
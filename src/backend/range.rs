@@ -0,0 +1,68 @@
+/// Resolve the inclusive `start`..=`stop` index range of a collection of `len` items the way
+/// redis does: negative indexes count from the end, out of bounds ends are clamped, and the
+/// range is empty (None) if start ends up past stop or past the end
+pub fn normalize_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        stop + len
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some((start as usize, stop as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_range_in_bounds() {
+        assert_eq!(normalize_range(0, 4, 5), Some((0, 4)));
+        assert_eq!(normalize_range(1, 2, 5), Some((1, 2)));
+        assert_eq!(normalize_range(3, 3, 5), Some((3, 3)));
+        assert_eq!(normalize_range(0, 0, 1), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_normalize_range_negative() {
+        assert_eq!(normalize_range(0, -1, 5), Some((0, 4)));
+        assert_eq!(normalize_range(-2, -1, 5), Some((3, 4)));
+        assert_eq!(normalize_range(-5, -5, 5), Some((0, 0)));
+        assert_eq!(normalize_range(1, -2, 5), Some((1, 3)));
+        assert_eq!(normalize_range(-3, 3, 5), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_normalize_range_inverted() {
+        assert_eq!(normalize_range(3, 1, 5), None);
+        assert_eq!(normalize_range(-1, -2, 5), None);
+        assert_eq!(normalize_range(4, -2, 5), None);
+        assert_eq!(normalize_range(2, -6, 5), None);
+    }
+
+    #[test]
+    fn test_normalize_range_out_of_bounds() {
+        assert_eq!(normalize_range(-100, 100, 5), Some((0, 4)));
+        assert_eq!(normalize_range(2, 100, 5), Some((2, 4)));
+        assert_eq!(normalize_range(-100, 1, 5), Some((0, 1)));
+        assert_eq!(normalize_range(5, 10, 5), None);
+        assert_eq!(normalize_range(-100, -6, 5), None);
+        assert_eq!(normalize_range(i64::MIN, i64::MAX, 5), Some((0, 4)));
+    }
+
+    #[test]
+    fn test_normalize_range_empty_collection() {
+        assert_eq!(normalize_range(0, -1, 0), None);
+        assert_eq!(normalize_range(0, 0, 0), None);
+        assert_eq!(normalize_range(-1, -1, 0), None);
+        assert_eq!(normalize_range(-100, 100, 0), None);
+    }
+}
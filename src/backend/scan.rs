@@ -0,0 +1,297 @@
+use super::{glob_match, Backend, CommandTimedOut, Deadline};
+use crate::RespFrame;
+use std::sync::{Arc, MutexGuard};
+
+// SCAN walks a point in time copy of the key names instead of the live maps, a cursor is simply
+// the position in that copy. The copy is regenerated when an iteration starts (cursor 0) and
+// shared by every cursor after that, so:
+// - keys present for the whole iteration are returned exactly once
+// - keys deleted since the copy was taken are skipped when the page is built
+// - keys added since the copy was taken may be missed, like redis allows
+// Another client starting a new iteration replaces the copy, the keys are sorted so the
+// positions in the new copy stay close to the old ones and a running iteration only sees a few
+// duplicates or misses. Every call moves the cursor forward so iterations always terminate.
+impl Backend {
+    /// The key names captured by the last SCAN starting at cursor 0
+    pub fn snapshot_keys(&self) -> Arc<Vec<String>> {
+        self.lock_scan_keys().clone()
+    }
+
+    /// Capture the key names of every store, sorted
+    pub fn refresh_snapshot_keys(&self) -> Arc<Vec<String>> {
+        let mut keys = self.all_keys().collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+
+        let keys = Arc::new(keys);
+        *self.lock_scan_keys() = keys.clone();
+        keys
+    }
+
+    /// Return the next cursor and up to `count` keys starting at `cursor`, cursor 0 starts a new
+    /// iteration and a returned cursor 0 means the iteration is complete
+    pub fn scan(&self, cursor: usize, count: usize) -> (usize, Vec<String>) {
+        let keys = if cursor == 0 {
+            self.refresh_snapshot_keys()
+        } else {
+            self.snapshot_keys()
+        };
+
+        let end = cursor.saturating_add(count.max(1));
+        let page = keys
+            .iter()
+            .take(end)
+            .skip(cursor)
+            .filter(|key| self.key_type(key).is_some())
+            .cloned()
+            .collect();
+        let next = if end >= keys.len() { 0 } else { end };
+        (next, page)
+    }
+
+    /// Return the next cursor and up to `count` fields of the hash with their values, None when
+    /// the key is missing. The cursor is a position derived from the field names, see
+    /// `scan_by_position`, so fields present for the whole iteration are returned exactly once
+    /// whatever else changes in between
+    pub fn hscan(
+        &self,
+        key: &str,
+        cursor: usize,
+        count: usize,
+    ) -> Option<(usize, Vec<(String, RespFrame)>)> {
+        self.expire_if_needed(key);
+        let hmap = self.db().hmap.get(key)?;
+        self.touch(key);
+        Some(scan_by_position(
+            || hmap.iter(),
+            |v| v.key(),
+            |v| (v.key().clone(), v.value().clone()),
+            cursor,
+            count,
+        ))
+    }
+
+    /// Same as `hscan` over the members of a set
+    pub fn sscan(&self, key: &str, cursor: usize, count: usize) -> Option<(usize, Vec<String>)> {
+        self.expire_if_needed(key);
+        let set = self.db().smap.get(key)?;
+        self.touch(key);
+        Some(scan_by_position(
+            || set.iter(),
+            |v| v.key(),
+            |v| v.key().clone(),
+            cursor,
+            count,
+        ))
+    }
+
+    /// Same as `hscan` over the members of a sorted set with their scores
+    pub fn zscan(
+        &self,
+        key: &str,
+        cursor: usize,
+        count: usize,
+    ) -> Option<(usize, Vec<(String, f64)>)> {
+        self.expire_if_needed(key);
+        let zset = self.db().zmap.get(key)?;
+        self.touch(key);
+        Some(scan_by_position(
+            || zset.iter(),
+            |(member, _)| member,
+            |(member, score)| (member.to_string(), score),
+            cursor,
+            count,
+        ))
+    }
+
+    /// Every key matching the glob `pattern` in one go, gives up when the command budget is spent
+    pub fn keys(&self, pattern: &str) -> Result<Vec<String>, CommandTimedOut> {
+        let mut deadline = Deadline::new(self.config.command_timeout);
+        let mut keys = Vec::new();
+        for key in self.all_keys() {
+            deadline.check()?;
+            if glob_match(pattern.as_bytes(), key.as_bytes()) {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    fn lock_scan_keys(&self) -> MutexGuard<'_, Arc<Vec<String>>> {
+        self.db()
+            .scan_keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// HSCAN, SSCAN and ZSCAN don't keep a copy of the container. Every element has a fixed position
+// computed from its name, a page is the `count` elements with the lowest positions at or after
+// the cursor and the next cursor is the position of the element that comes after them. Elements
+// added or removed meanwhile don't shift the others, and only the elements of the page are
+// cloned. The rare elements sharing a position are returned on the same page.
+fn scan_by_position<I, E, T>(
+    elements: impl Fn() -> I,
+    name: impl Fn(&E) -> &str,
+    item: impl Fn(E) -> T,
+    cursor: usize,
+    count: usize,
+) -> (usize, Vec<T>)
+where
+    I: Iterator<Item = E>,
+{
+    let mut positions = elements()
+        .map(|e| scan_position(name(&e)))
+        .filter(|position| *position >= cursor)
+        .collect::<Vec<_>>();
+    positions.sort_unstable();
+    positions.dedup();
+    let next = positions.get(count.max(1)).copied().unwrap_or(0);
+    let page = elements()
+        .filter(|e| {
+            let position = scan_position(name(e));
+            position >= cursor && (next == 0 || position < next)
+        })
+        .map(item)
+        .collect();
+    (next, page)
+}
+
+// FNV-1a of the name, 63 bits so clients reading the cursor as a signed integer cope with it and
+// never 0, which starts and ends an iteration
+fn scan_position(name: &str) -> usize {
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    ((hash >> 1) as usize).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendConfig, BulkString};
+    use std::{
+        collections::HashSet,
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn test_scan_should_return_every_key_once() {
+        let backend = Backend::new();
+        for i in 0..25 {
+            backend.set(format!("key:{}", i), BulkString::from("value").into());
+        }
+        backend.rpush("list".to_string(), [BulkString::from("a").into()]);
+
+        let mut cursor = 0;
+        let mut keys = Vec::new();
+        loop {
+            let (next, page) = backend.scan(cursor, 10);
+            keys.extend(page);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(keys.len(), 26);
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 26);
+        assert_eq!(backend.snapshot_keys().len(), 26);
+    }
+
+    #[test]
+    fn test_scan_with_concurrent_writes_should_terminate() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(format!("stable:{}", i), BulkString::from("value").into());
+        }
+
+        let done = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let backend = backend.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut i = 0;
+                while !done.load(Ordering::Relaxed) {
+                    let key = format!("churn:{}", i % 50);
+                    if i % 3 == 0 {
+                        backend.del(&key);
+                    } else {
+                        backend.set(key, BulkString::from("value").into());
+                    }
+                    i += 1;
+                }
+            })
+        };
+
+        for _ in 0..20 {
+            let mut cursor = 0;
+            let mut stable = HashSet::new();
+            for calls in 0.. {
+                assert!(calls < 1000, "SCAN did not terminate");
+                let (next, page) = backend.scan(cursor, 7);
+                stable.extend(page.into_iter().filter(|k| k.starts_with("stable:")));
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+            // keys present for the whole iteration are never missed
+            assert_eq!(stable.len(), 100);
+        }
+
+        done.store(true, Ordering::Relaxed);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_hscan_should_return_untouched_fields_once_while_the_hash_changes() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            let field = format!("stable:{}", i);
+            backend.hset("hash".to_string(), field, RespFrame::Integer(i));
+        }
+
+        let mut cursor = 0;
+        let mut stable = Vec::new();
+        for round in 0.. {
+            let (next, page) = backend.hscan("hash", cursor, 7).unwrap();
+            let fields = page.into_iter().map(|(field, _)| field);
+            stable.extend(fields.filter(|f| f.starts_with("stable:")));
+            // fields come and go between the pages
+            let churn = format!("churn:{}", round);
+            backend.hset("hash".to_string(), churn, RespFrame::Integer(round));
+            backend
+                .db()
+                .hmap
+                .get("hash")
+                .unwrap()
+                .remove(&format!("churn:{}", round / 2));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(stable.len(), 100);
+        assert_eq!(stable.iter().collect::<HashSet<_>>().len(), 100);
+    }
+
+    #[test]
+    fn test_keys_should_time_out_on_a_tiny_budget() {
+        let backend = Backend::with_config(BackendConfig {
+            command_timeout: Some(Duration::from_nanos(1)),
+            ..Default::default()
+        });
+        for i in 0..100_000 {
+            backend.set(format!("key:{}", i), BulkString::from("value").into());
+        }
+        assert_eq!(backend.keys("*"), Err(CommandTimedOut));
+
+        let backend = Backend::new();
+        for i in 0..100_000 {
+            backend.set(format!("key:{}", i), BulkString::from("value").into());
+        }
+        assert_eq!(backend.keys("key:9999*").map(|v| v.len()), Ok(11));
+    }
+}
@@ -0,0 +1,581 @@
+use super::{AccessCounter, Backend, Db, SortedSet};
+use crate::{BulkString, RespArray, RespDecode, RespEncode, RespError, RespFrame};
+use bytes::BytesMut;
+use dashmap::{DashMap, DashSet};
+use std::{
+    collections::VecDeque,
+    fs, io,
+    sync::atomic::Ordering,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// snapshot format: every key is stored as a RESP array of "<type> <key> <value>",
+// - string: "*3\r\n$6\r\nstring\r\n$3\r\nkey\r\n<value frame>"
+// - hash: "*3\r\n$4\r\nhash\r\n$3\r\nkey\r\n*<2n>\r\n<field-1><value-1>...<field-n><value-n>"
+// - list: "*3\r\n$4\r\nlist\r\n$3\r\nkey\r\n*<n>\r\n<value-1>...<value-n>"
+// - zset: "*3\r\n$4\r\nzset\r\n$3\r\nkey\r\n*<2n>\r\n<member-1>,<score-1>...<member-n>,<score-n>"
+// - set: "*3\r\n$3\r\nset\r\n$3\r\nkey\r\n*<n>\r\n<member-1>...<member-n>"
+// - expire: "*3\r\n$6\r\nexpire\r\n$3\r\nkey\r\n:<unix time in milliseconds>", after the keys
+// keys of database 0 come first, the keys of another database follow a "select <index>" entry:
+// "*2\r\n$6\r\nselect\r\n$1\r\n1\r\n"
+impl Backend {
+    /// The snapshot of every database
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for db in self.all_dbs() {
+            let mut entries = Vec::new();
+            db.save_db(&mut entries);
+            // expired keys that weren't deleted yet are saved too, they need the SELECT as well
+            if db.db_index() > 0 && !entries.is_empty() {
+                buf.extend(select_entry(db.db_index()));
+            }
+            buf.extend(entries);
+        }
+        buf
+    }
+
+    fn save_db(&self, buf: &mut Vec<u8>) {
+        let db = self.db();
+        for v in db.map.iter() {
+            buf.extend(snapshot_entry("string", v.key(), v.value().clone()));
+        }
+        for v in db.hmap.iter() {
+            buf.extend(snapshot_entry("hash", v.key(), hash_value(v.value())));
+        }
+        for v in db.lmap.iter() {
+            buf.extend(snapshot_entry("list", v.key(), list_value(v.value())));
+        }
+        for v in db.zmap.iter() {
+            buf.extend(snapshot_entry("zset", v.key(), zset_value(v.value())));
+        }
+        for v in db.smap.iter() {
+            buf.extend(snapshot_entry("set", v.key(), set_value(v.value())));
+        }
+        for v in db.expires.iter() {
+            buf.extend(snapshot_entry(
+                "expire",
+                v.key(),
+                (*v.value() as i64).into(),
+            ));
+        }
+    }
+
+    /// The snapshot entry of a single key, None for a missing key
+    pub fn dump_entry(&self, key: &str) -> Option<Vec<u8>> {
+        let db = self.db();
+        if let Some(v) = db.map.get(key) {
+            Some(snapshot_entry("string", key, v.value().clone()))
+        } else if let Some(v) = db.hmap.get(key) {
+            Some(snapshot_entry("hash", key, hash_value(v.value())))
+        } else if let Some(v) = db.lmap.get(key) {
+            Some(snapshot_entry("list", key, list_value(v.value())))
+        } else if let Some(v) = db.zmap.get(key) {
+            Some(snapshot_entry("zset", key, zset_value(v.value())))
+        } else {
+            self.db()
+                .smap
+                .get(key)
+                .map(|v| snapshot_entry("set", key, set_value(v.value())))
+        }
+    }
+
+    /// Write the snapshot to the dump file, the old file is only replaced once the write succeeded.
+    /// Runs as a command, which keeps SWAPDB, MOVE and EXEC from changing the data meanwhile
+    pub fn save(&self) -> io::Result<()> {
+        self.write_dump(self.save_snapshot())
+    }
+
+    /// Save on a background thread, returns false if a background save is already running
+    pub fn bgsave(&self) -> bool {
+        if self.bgsave_in_progress.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        let backend = self.clone();
+        thread::spawn(move || {
+            // captured under the lock commands run under, so no SWAPDB, MOVE, EXEC or snapshot
+            // load runs halfway through it
+            let snapshot = {
+                let _guard = backend
+                    .transaction_lock
+                    .read()
+                    .unwrap_or_else(|e| e.into_inner());
+                backend.save_snapshot()
+            };
+            if let Err(e) = backend.write_dump(snapshot) {
+                tracing::warn!("background save failed: {:?}", e);
+            }
+            backend.bgsave_in_progress.store(false, Ordering::Release);
+        });
+        true
+    }
+
+    fn write_dump(&self, snapshot: Vec<u8>) -> io::Result<()> {
+        let path = &self.config.dump_path;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, snapshot)?;
+        fs::rename(&tmp, path)?;
+        self.last_save.store(unix_timestamp(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Load the dump file written by SAVE, returns the number of keys loaded. A missing file
+    /// loads nothing, like the first start of a server
+    pub fn load_dump(&self) -> anyhow::Result<usize> {
+        let data = match fs::read(&self.config.dump_path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(self.load_snapshot(&mut BytesMut::from(data.as_slice()))?)
+    }
+
+    /// Unix timestamp of the last successful save
+    pub fn last_save(&self) -> u64 {
+        self.last_save.load(Ordering::Relaxed)
+    }
+
+    /// Replace the current data with the snapshot in `buf`, returns the number of keys loaded.
+    /// The snapshot is loaded into spare databases that replace all the current ones at once,
+    /// a snapshot that fails to load leaves the current data as it was. Like SWAPDB, the caller
+    /// keeps other commands from running meanwhile, see network.rs
+    pub fn load_snapshot(&self, buf: &mut BytesMut) -> Result<usize, RespError> {
+        let mut entries = Vec::new();
+        while !buf.is_empty() {
+            entries.push(RespFrame::decode(buf)?);
+        }
+
+        let _load = self.snapshot_load.lock().unwrap_or_else(|e| e.into_inner());
+        let slots = self
+            .db_slots
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        // the spares are always empty, a failed load is cleared away and so are the databases
+        // replaced by a successful one
+        let spares = (0..self.dbs.len())
+            .filter(|slot| !slots.contains(slot))
+            .collect::<Vec<_>>();
+        let keys = match self.load_entries(entries, &spares) {
+            Ok(keys) => keys,
+            Err(e) => {
+                spares.iter().for_each(|slot| self.dbs[*slot].clear());
+                return Err(e);
+            }
+        };
+        *self.db_slots.write().unwrap_or_else(|e| e.into_inner()) = spares;
+        slots.iter().for_each(|slot| self.dbs[*slot].clear());
+        Ok(keys)
+    }
+
+    // load the entries into the databases at the `slots` of `dbs`, in database index order
+    fn load_entries(&self, entries: Vec<RespFrame>, slots: &[usize]) -> Result<usize, RespError> {
+        let mut db = &self.dbs[slots[0]];
+        let mut keys = 0;
+        for entry in entries {
+            match select_index(&entry) {
+                Some(index) => {
+                    let slot = slots.get(index).ok_or_else(|| {
+                        RespError::InvalidFrame(format!(
+                            "snapshot selects database {} out of {}",
+                            index,
+                            self.databases()
+                        ))
+                    })?;
+                    db = &self.dbs[*slot];
+                }
+                None => {
+                    if self.load_entry(db, entry)? {
+                        keys += 1;
+                    }
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    // whether the entry was a key, an expire entry only sets the deadline of one
+    fn load_entry(&self, db: &Db, entry: RespFrame) -> Result<bool, RespError> {
+        let mut args = match entry {
+            RespFrame::Array(array) if array.len() == 3 => array.0.into_iter(),
+            v => {
+                return Err(RespError::InvalidFrame(format!(
+                    "snapshot entry must be an array of 3 elements, got {:?}",
+                    v
+                )))
+            }
+        };
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(kind)), Some(RespFrame::BulkString(key)), Some(value)) => {
+                let key = String::from_utf8(key.0)?;
+                db.unmeasured.insert(key.clone());
+                match (kind.as_slice(), value) {
+                    (b"string", value) => {
+                        db.access.insert(key.clone(), AccessCounter::new());
+                        db.map.insert(key, value);
+                    }
+                    (b"hash", RespFrame::Array(fields)) => {
+                        let hmap = DashMap::new();
+                        let mut fields = fields.0.into_iter();
+                        while let (Some(RespFrame::BulkString(field)), Some(value)) =
+                            (fields.next(), fields.next())
+                        {
+                            hmap.insert(String::from_utf8(field.0)?, value);
+                        }
+                        if hmap.len() > self.config.hash_max_listpack_entries {
+                            db.large_hashes.insert(key.clone());
+                        }
+                        db.access.insert(key.clone(), AccessCounter::new());
+                        db.hmap.insert(key, hmap);
+                    }
+                    (b"list", RespFrame::Array(values)) => {
+                        db.access.insert(key.clone(), AccessCounter::new());
+                        db.lmap.insert(key, VecDeque::from(values.0));
+                    }
+                    (b"zset", RespFrame::Array(members)) => {
+                        let mut zset = SortedSet::new();
+                        let mut members = members.0.into_iter();
+                        while let (
+                            Some(RespFrame::BulkString(member)),
+                            Some(RespFrame::Double(score)),
+                        ) = (members.next(), members.next())
+                        {
+                            zset.insert(String::from_utf8(member.0)?, score);
+                        }
+                        db.access.insert(key.clone(), AccessCounter::new());
+                        db.zmap.insert(key, zset);
+                    }
+                    (b"set", RespFrame::Array(members)) => {
+                        let set = DashSet::new();
+                        for member in members.0 {
+                            if let RespFrame::BulkString(member) = member {
+                                set.insert(String::from_utf8(member.0)?);
+                            }
+                        }
+                        db.access.insert(key.clone(), AccessCounter::new());
+                        db.smap.insert(key, set);
+                    }
+                    (b"expire", RespFrame::Integer(at_ms)) => {
+                        db.expires.insert(key, at_ms.max(0) as u64);
+                        return Ok(false);
+                    }
+                    (kind, _) => {
+                        return Err(RespError::InvalidFrame(format!(
+                            "unknown snapshot entry type: {}",
+                            String::from_utf8_lossy(kind)
+                        )))
+                    }
+                }
+                Ok(true)
+            }
+            _ => Err(RespError::InvalidFrame(
+                "snapshot entry must start with type and key".to_string(),
+            )),
+        }
+    }
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn hash_value(hash: &DashMap<String, RespFrame>) -> RespFrame {
+    let fields = hash
+        .iter()
+        .flat_map(|v| [BulkString::from(v.key().as_str()).into(), v.value().clone()])
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(fields).into()
+}
+
+fn list_value(list: &VecDeque<RespFrame>) -> RespFrame {
+    RespArray::new(list.iter().cloned().collect::<Vec<_>>()).into()
+}
+
+fn zset_value(zset: &SortedSet) -> RespFrame {
+    let members = zset
+        .iter()
+        .flat_map(|(member, score)| [BulkString::from(member).into(), score.into()])
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(members).into()
+}
+
+fn set_value(set: &DashSet<String>) -> RespFrame {
+    let members = set
+        .iter()
+        .map(|member| BulkString::from(member.as_str()).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(members).into()
+}
+
+fn select_entry(index: usize) -> Vec<u8> {
+    RespArray::new([
+        BulkString::from("select").into(),
+        BulkString::from(index.to_string()).into(),
+    ])
+    .encode()
+}
+
+// the database index of a "select <index>" entry, None for a key entry
+fn select_index(entry: &RespFrame) -> Option<usize> {
+    match entry {
+        RespFrame::Array(array) if array.len() == 2 => match (&array[0], &array[1]) {
+            (RespFrame::BulkString(kind), RespFrame::BulkString(index))
+                if kind.as_slice() == b"select" =>
+            {
+                std::str::from_utf8(index).ok()?.parse().ok()
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn snapshot_entry(kind: &str, key: &str, value: RespFrame) -> Vec<u8> {
+    RespArray::new([
+        BulkString::from(kind).into(),
+        BulkString::from(key).into(),
+        value,
+    ])
+    .encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendConfig, MockClock};
+    use anyhow::Result;
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn test_snapshot_save_and_load() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("hello".to_string(), BulkString::from("world").into());
+        backend.set("number".to_string(), 42.into());
+        backend.hset(
+            "map".to_string(),
+            "foo".to_string(),
+            BulkString::from("bar").into(),
+        );
+        backend.rpush(
+            "list".to_string(),
+            [BulkString::from("a").into(), BulkString::from("b").into()],
+        );
+        backend.zadd(
+            "zset".to_string(),
+            [(1.5, "a".to_string()), (f64::INFINITY, "b".to_string())],
+        );
+        backend.sadd("set".to_string(), ["x".to_string(), "y".to_string()]);
+
+        let mut buf = BytesMut::from(backend.save_snapshot().as_slice());
+        let restored = Backend::new();
+        restored.set("stale".to_string(), BulkString::from("value").into());
+        assert_eq!(restored.load_snapshot(&mut buf)?, 6);
+        assert_eq!(
+            restored.smismember("set", &["x".to_string(), "z".to_string(), "y".to_string()]),
+            [true, false, true]
+        );
+
+        assert_eq!(restored.get("stale"), None);
+        assert_eq!(
+            restored.get("hello"),
+            Some(BulkString::from("world").into())
+        );
+        assert_eq!(restored.get("number"), Some(42.into()));
+        assert_eq!(
+            restored.hget("map", "foo"),
+            Some(BulkString::from("bar").into())
+        );
+        assert_eq!(
+            restored.db().lmap.get("list").map(|v| v.clone()),
+            Some(VecDeque::from([
+                BulkString::from("a").into(),
+                BulkString::from("b").into()
+            ]))
+        );
+        assert_eq!(
+            restored.db().zmap.get("zset").map(|v| v.clone()),
+            backend.db().zmap.get("zset").map(|v| v.clone())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_dump_should_read_the_saved_file() -> Result<()> {
+        let dump_path = std::env::temp_dir().join(format!("load-dump-{}.rdb", std::process::id()));
+        let config = BackendConfig {
+            dump_path: dump_path.clone(),
+            ..Default::default()
+        };
+        let _ = fs::remove_file(&dump_path);
+        let backend = Backend::with_config(config.clone());
+        assert_eq!(backend.load_dump()?, 0);
+
+        backend.set("key".to_string(), BulkString::from("value").into());
+        backend.save()?;
+        let restored = Backend::with_config(config);
+        assert_eq!(restored.load_dump()?, 1);
+        assert_eq!(restored.get("key"), Some(BulkString::from("value").into()));
+
+        fs::remove_file(&dump_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bgsave_should_wait_for_exclusive_commands() -> Result<()> {
+        let dump_path = std::env::temp_dir().join(format!("bgsave-{}.rdb", std::process::id()));
+        let _ = fs::remove_file(&dump_path);
+        let backend = Backend::with_config(BackendConfig {
+            dump_path: dump_path.clone(),
+            ..Default::default()
+        });
+        backend.set("key".to_string(), BulkString::from("value").into());
+
+        // like a SWAPDB in progress
+        let guard = backend.transaction_lock.write().unwrap();
+        assert!(backend.bgsave());
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!dump_path.exists());
+        drop(guard);
+        while backend.bgsave_in_progress.load(Ordering::Acquire) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let restored = Backend::new();
+        let mut buf = BytesMut::from(fs::read(&dump_path)?.as_slice());
+        assert_eq!(restored.load_snapshot(&mut buf)?, 1);
+
+        fs::remove_file(&dump_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_should_keep_ttl_deadlines() -> Result<()> {
+        let clock = Arc::new(MockClock::new(1_700_000_000_000));
+        let config = BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        };
+        let backend = Backend::with_config(config.clone());
+        backend.set("volatile".to_string(), BulkString::from("a").into());
+        backend.expire("volatile", Duration::from_secs(10));
+        backend.sadd("set".to_string(), ["x".to_string()]);
+        backend.expire("set", Duration::from_secs(20));
+        backend.set("persistent".to_string(), BulkString::from("b").into());
+        backend
+            .select(2)
+            .unwrap()
+            .set("other".to_string(), BulkString::from("c").into());
+        backend
+            .select(2)
+            .unwrap()
+            .expire("other", Duration::from_secs(30));
+
+        clock.advance(Duration::from_secs(5));
+        let mut buf = BytesMut::from(backend.save_snapshot().as_slice());
+        let restored = Backend::with_config(config);
+        assert_eq!(restored.load_snapshot(&mut buf)?, 4);
+        assert_eq!(
+            restored.expire_time("volatile"),
+            Some(Some(1_700_000_010_000))
+        );
+        assert_eq!(restored.expire_time("set"), Some(Some(1_700_000_020_000)));
+        assert_eq!(restored.expire_time("persistent"), Some(None));
+        assert_eq!(
+            restored.select(2).unwrap().expire_time("other"),
+            Some(Some(1_700_000_030_000))
+        );
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(restored.get("volatile"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_should_keep_databases_apart() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("key".to_string(), BulkString::from("db0").into());
+        let db3 = backend.select(3).unwrap();
+        db3.set("key".to_string(), BulkString::from("db3").into());
+        db3.sadd("set".to_string(), ["x".to_string()]);
+
+        let mut buf = BytesMut::from(backend.save_snapshot().as_slice());
+        let restored = Backend::new();
+        restored
+            .select(3)
+            .unwrap()
+            .set("stale".to_string(), BulkString::from("value").into());
+        assert_eq!(restored.load_snapshot(&mut buf)?, 3);
+
+        let restored3 = restored.select(3).unwrap();
+        assert_eq!(restored.get("key"), Some(BulkString::from("db0").into()));
+        assert_eq!(restored3.get("key"), Some(BulkString::from("db3").into()));
+        assert_eq!(restored3.key_type("set"), Some("set"));
+        assert_eq!(restored3.get("stale"), None);
+        assert_eq!(restored.select(1).unwrap().all_keys().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_should_select_databases_holding_only_expired_keys() -> Result<()> {
+        let clock = Arc::new(MockClock::new(1_700_000_000_000));
+        let config = BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        };
+        let backend = Backend::with_config(config.clone());
+        backend.set_active_expire(false);
+        backend.set("key".to_string(), BulkString::from("db0").into());
+        let db1 = backend.select(1).unwrap();
+        db1.set("key".to_string(), BulkString::from("db1").into());
+        db1.expire("key", Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+
+        let mut buf = BytesMut::from(backend.save_snapshot().as_slice());
+        let restored = Backend::with_config(config);
+        restored.load_snapshot(&mut buf)?;
+        assert_eq!(restored.get("key"), Some(BulkString::from("db0").into()));
+        assert_eq!(restored.expire_time("key"), Some(None));
+        assert_eq!(restored.select(1).unwrap().get("key"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_snapshot_load_should_keep_current_data() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("key".to_string(), BulkString::from("current").into());
+        let mut buf = BytesMut::from(backend.save_snapshot().as_slice());
+        buf.extend_from_slice(b"*3\r\n$7\r\nunknown\r\n$1\r\nk\r\n:1\r\n");
+
+        let other = Backend::new();
+        other.set("other".to_string(), BulkString::from("kept").into());
+        assert!(other.load_snapshot(&mut buf).is_err());
+        assert_eq!(other.get("other"), Some(BulkString::from("kept").into()));
+        assert_eq!(other.get("key"), None);
+
+        let mut buf = BytesMut::from(backend.save_snapshot().as_slice());
+        assert_eq!(other.load_snapshot(&mut buf)?, 1);
+        assert_eq!(other.get("key"), Some(BulkString::from("current").into()));
+        assert_eq!(other.get("other"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_load_should_release_the_replaced_data() -> Result<()> {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(format!("key:{:02}", i), BulkString::from("value").into());
+        }
+        let snapshot = backend.save_snapshot();
+
+        for _ in 0..2 {
+            let mut buf = BytesMut::from(&snapshot[..]);
+            assert_eq!(backend.load_snapshot(&mut buf)?, 100);
+            let stored = backend.dbs.iter().map(|db| db.map.len()).sum::<usize>();
+            assert_eq!(stored, 100);
+            assert_eq!(backend.used_memory(), backend.key_memory("key:00") * 100);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,345 @@
+use super::{normalize_range, Backend};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+};
+
+// f64 isn't Ord, scores are compared with total_cmp. NaN is rejected before it gets here
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A score interval of ZCOUNT and the score range commands, either end may be exclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreRange {
+    pub min: f64,
+    pub min_exclusive: bool,
+    pub max: f64,
+    pub max_exclusive: bool,
+}
+
+impl ScoreRange {
+    pub fn contains(&self, score: f64) -> bool {
+        let above_min = if self.min_exclusive {
+            score > self.min
+        } else {
+            score >= self.min
+        };
+        let below_max = if self.max_exclusive {
+            score < self.max
+        } else {
+            score <= self.max
+        };
+        above_min && below_max
+    }
+}
+
+/// Members ordered by (score, member). The ordered index is a BTreeSet so both ends can be
+/// popped in O(log n), the score of a member is looked up through the hash map.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortedSet {
+    scores: HashMap<String, f64>,
+    index: BTreeSet<(Score, String)>,
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the member or update its score, returns true if the member is new
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.index.remove(&(Score(old), member.clone()));
+        }
+        self.index.insert((Score(score), member));
+        old.is_none()
+    }
+
+    pub fn remove(&mut self, member: &str) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.index.remove(&(Score(score), member.to_string()));
+        Some(score)
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    pub fn pop_min(&mut self) -> Option<(String, f64)> {
+        let (score, member) = self.index.pop_first()?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    pub fn pop_max(&mut self) -> Option<(String, f64)> {
+        let (score, member) = self.index.pop_last()?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Members with their scores from the lowest to the highest score
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, f64)> {
+        self.index
+            .iter()
+            .map(|(score, member)| (member.as_str(), score.0))
+    }
+
+    /// Remove the members with a score in the range, returns how many were removed
+    pub fn remove_range_by_score(&mut self, range: ScoreRange) -> usize {
+        let members = self
+            .range_by_score(range)
+            .map(|(member, _)| member.to_string())
+            .collect::<Vec<_>>();
+        members
+            .iter()
+            .filter(|member| self.remove(member).is_some())
+            .count()
+    }
+
+    /// Remove the members from rank `start` to `stop` inclusive, 0 being the lowest score.
+    /// Negative ranks count from the highest score like redis. Returns how many were removed
+    pub fn remove_range_by_rank(&mut self, start: i64, stop: i64) -> usize {
+        let Some((start, stop)) = normalize_range(start, stop, self.len()) else {
+            return 0;
+        };
+        let members = self
+            .index
+            .iter()
+            .skip(start)
+            .take(stop - start + 1)
+            .map(|(_, member)| member.clone())
+            .collect::<Vec<_>>();
+        members
+            .iter()
+            .filter(|member| self.remove(member).is_some())
+            .count()
+    }
+
+    /// Members whose score is in the range, from the lowest score. The index is entered at the
+    /// lower end of the range instead of being walked from the start
+    pub fn range_by_score(&self, range: ScoreRange) -> impl Iterator<Item = (&str, f64)> {
+        self.index
+            .range((Score(range.min), String::new())..)
+            .map(|(score, member)| (member.as_str(), score.0))
+            .skip_while(move |(_, score)| range.min_exclusive && *score == range.min)
+            .take_while(move |(_, score)| range.contains(*score))
+    }
+}
+
+impl Backend {
+    /// Add the (score, member) pairs to the sorted set, returns the number of new members
+    pub fn zadd(&self, key: String, members: impl IntoIterator<Item = (f64, String)>) -> usize {
+        self.expire_if_needed(&key);
+        self.touch(&key);
+        let mut zset = self.db().zmap.entry(key).or_default();
+        members
+            .into_iter()
+            .filter(|(score, member)| zset.insert(member.clone(), *score))
+            .count()
+    }
+
+    /// Number of members of the sorted set, 0 for a missing key
+    pub fn zcard(&self, key: &str) -> usize {
+        self.expire_if_needed(key);
+        let zset = self.db().zmap.get(key);
+        self.touch_if(key, zset.is_some());
+        zset.map(|v| v.len()).unwrap_or(0)
+    }
+
+    /// Number of members with a score in the range, 0 for a missing key
+    pub fn zcount(&self, key: &str, range: ScoreRange) -> usize {
+        self.expire_if_needed(key);
+        let zset = self.db().zmap.get(key);
+        self.touch_if(key, zset.is_some());
+        zset.map(|v| v.range_by_score(range).count()).unwrap_or(0)
+    }
+
+    /// Members in the score range in ascending order, skipping `offset` of them and returning at
+    /// most `count` if given
+    pub fn zrangebyscore(
+        &self,
+        key: &str,
+        range: ScoreRange,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<(String, f64)> {
+        self.expire_if_needed(key);
+        let zset = self.db().zmap.get(key);
+        self.touch_if(key, zset.is_some());
+        zset.map(|v| {
+            v.range_by_score(range)
+                .skip(offset)
+                .take(count.unwrap_or(usize::MAX))
+                .map(|(member, score)| (member.to_string(), score))
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Remove up to `count` members from the lowest (or highest if `max`) end of the sorted set
+    pub fn zpop(&self, key: &str, count: usize, max: bool) -> Vec<(String, f64)> {
+        self.zremove(key, |zset| {
+            (0..count)
+                .map_while(|_| if max { zset.pop_max() } else { zset.pop_min() })
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    /// Remove the members, returns how many of them were in the sorted set
+    pub fn zrem(&self, key: &str, members: &[String]) -> usize {
+        self.zremove(key, |zset| {
+            members
+                .iter()
+                .filter(|member| zset.remove(member).is_some())
+                .count()
+        })
+        .unwrap_or(0)
+    }
+
+    pub fn zremrangebyscore(&self, key: &str, range: ScoreRange) -> usize {
+        self.zremove(key, |zset| zset.remove_range_by_score(range))
+            .unwrap_or(0)
+    }
+
+    pub fn zremrangebyrank(&self, key: &str, start: i64, stop: i64) -> usize {
+        self.zremove(key, |zset| zset.remove_range_by_rank(start, stop))
+            .unwrap_or(0)
+    }
+
+    // run a removal on the sorted set and drop the key once it's empty, None for a missing key
+    fn zremove<T>(&self, key: &str, f: impl FnOnce(&mut SortedSet) -> T) -> Option<T> {
+        let db = self.db();
+        self.expire_if_needed(key);
+        let ret = f(&mut *db.zmap.get_mut(key)?);
+        if db.zmap.remove_if(key, |_, zset| zset.is_empty()).is_some() {
+            db.access.remove(key);
+            self.mark_changed(key);
+        } else {
+            self.touch(key);
+        }
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_set_order() {
+        let mut zset = SortedSet::new();
+        assert!(zset.insert("b".to_string(), 2.0));
+        assert!(zset.insert("a".to_string(), 1.0));
+        assert!(zset.insert("c".to_string(), 1.0));
+        assert!(!zset.insert("b".to_string(), -1.0));
+
+        let members = zset
+            .iter()
+            .map(|(m, s)| (m.to_string(), s))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            members,
+            vec![
+                ("b".to_string(), -1.0),
+                ("a".to_string(), 1.0),
+                ("c".to_string(), 1.0)
+            ]
+        );
+        assert_eq!(zset.pop_max(), Some(("c".to_string(), 1.0)));
+        assert_eq!(zset.remove("b"), Some(-1.0));
+        assert_eq!(zset.len(), 1);
+    }
+
+    #[test]
+    fn test_zpop_should_remove_empty_key() {
+        let backend = Backend::new();
+        backend.zadd("zset".to_string(), [(1.0, "a".to_string())]);
+        assert_eq!(backend.zpop("zset", 5, false), vec![("a".to_string(), 1.0)]);
+        assert!(backend.db().zmap.get("zset").is_none());
+        assert!(backend.zpop("zset", 1, true).is_empty());
+    }
+
+    #[test]
+    fn test_range_by_score() {
+        let mut zset = SortedSet::new();
+        for (i, member) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            zset.insert(member.to_string(), i as f64);
+        }
+        let members = |min, min_exclusive, max, max_exclusive| {
+            let range = ScoreRange {
+                min,
+                min_exclusive,
+                max,
+                max_exclusive,
+            };
+            zset.range_by_score(range)
+                .map(|(m, _)| m.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(members(1.0, false, 3.0, false), ["b", "c", "d"]);
+        assert_eq!(members(1.0, true, 3.0, true), ["c"]);
+        assert_eq!(
+            members(f64::NEG_INFINITY, false, f64::INFINITY, false),
+            ["a", "b", "c", "d", "e"]
+        );
+        assert!(members(3.0, false, 1.0, false).is_empty());
+        assert!(members(1.0, true, 1.0, false).is_empty());
+    }
+
+    #[test]
+    fn test_remove_range_by_rank() {
+        let new_zset = || {
+            let mut zset = SortedSet::new();
+            for (i, member) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+                zset.insert(member.to_string(), i as f64);
+            }
+            zset
+        };
+        let members =
+            |zset: &SortedSet| zset.iter().map(|(m, _)| m.to_string()).collect::<Vec<_>>();
+
+        let mut zset = new_zset();
+        assert_eq!(zset.remove_range_by_rank(1, 2), 2);
+        assert_eq!(members(&zset), ["a", "d", "e"]);
+        // the score map follows the index
+        assert_eq!(zset.score("b"), None);
+        assert_eq!(zset.len(), 3);
+
+        let mut zset = new_zset();
+        assert_eq!(zset.remove_range_by_rank(-2, -1), 2);
+        assert_eq!(members(&zset), ["a", "b", "c"]);
+
+        let mut zset = new_zset();
+        assert_eq!(zset.remove_range_by_rank(-100, 100), 5);
+        assert!(zset.is_empty());
+
+        let mut zset = new_zset();
+        assert_eq!(zset.remove_range_by_rank(3, 1), 0);
+        assert_eq!(zset.remove_range_by_rank(5, 10), 0);
+        assert_eq!(zset.len(), 5);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{BulkString, RespArray, RespDecodeV2, RespEncode, RespError, RespFrame};
+use anyhow::{anyhow, Result};
+use bytes::BytesMut;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+/// A minimal client speaking RESP over TCP, one request at a time
+#[derive(Debug)]
+pub struct Client {
+    stream: TcpStream,
+    buf: BytesMut,
+}
+
+impl Client {
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Self {
+            stream,
+            buf: BytesMut::new(),
+        })
+    }
+
+    /// Send a frame and wait for its reply. Error replies are returned as `RespFrame::Error`
+    pub async fn send(&mut self, frame: RespFrame) -> Result<RespFrame> {
+        self.stream.write_all(&frame.encode()).await?;
+        self.read_frame().await
+    }
+
+    /// Send a command given as its arguments, encoded as an array of bulk strings
+    pub async fn command(&mut self, args: &[&[u8]]) -> Result<RespFrame> {
+        let frames = args
+            .iter()
+            .map(|arg| BulkString::from(*arg).into())
+            .collect::<Vec<RespFrame>>();
+        self.send(RespArray::new(frames).into()).await
+    }
+
+    pub async fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.command(&[b"get", key.as_bytes()]).await? {
+            RespFrame::BulkString(value) => Ok(Some(value.0)),
+            RespFrame::Null(_) | RespFrame::NullBulkString(_) => Ok(None),
+            reply => Err(unexpected_reply(reply)),
+        }
+    }
+
+    pub async fn set(&mut self, key: &str, value: impl AsRef<[u8]>) -> Result<()> {
+        match self
+            .command(&[b"set", key.as_bytes(), value.as_ref()])
+            .await?
+        {
+            RespFrame::SimpleString(_) => Ok(()),
+            reply => Err(unexpected_reply(reply)),
+        }
+    }
+
+    async fn read_frame(&mut self) -> Result<RespFrame> {
+        loop {
+            match RespFrame::decode(&mut self.buf) {
+                Ok(frame) => return Ok(frame),
+                Err(RespError::NotComplete) => {
+                    if self.stream.read_buf(&mut self.buf).await? == 0 {
+                        return Err(anyhow!("connection closed by server"));
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+fn unexpected_reply(reply: RespFrame) -> anyhow::Error {
+    match reply {
+        RespFrame::Error(e) => anyhow!("{}", e.0),
+        reply => anyhow!("unexpected reply: {:?}", reply),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        network::{self, NetworkConfig},
+        Backend,
+    };
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_client_set_and_get() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(network::serve(
+            listener,
+            Backend::new(),
+            NetworkConfig::default(),
+        ));
+
+        let mut client = Client::connect(addr).await?;
+        assert_eq!(client.get("hello").await?, None);
+        client.set("hello", "world").await?;
+        assert_eq!(client.get("hello").await?, Some(b"world".to_vec()));
+
+        let reply = client.command(&[b"hgetall", b"hello"]).await?;
+        assert!(matches!(reply, RespFrame::Error(_)));
+        client.set("hello", b"\x00binary\r\n").await?;
+        assert_eq!(client.get("hello").await?, Some(b"\x00binary\r\n".to_vec()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,387 @@
+use super::{BitOp, BitOperation, BitPos, BitRange, CommandExecutor, RESP_WRONGTYPE};
+use crate::{cmd::CommandError, normalize_range, Backend, BulkString, RespArray, RespFrame};
+
+impl CommandExecutor for BitOp {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let mut operands = Vec::with_capacity(self.keys.len());
+        for key in &self.keys {
+            match backend.get(key) {
+                Some(RespFrame::BulkString(s)) => operands.push(s.0),
+                Some(_) => return RESP_WRONGTYPE.clone(),
+                // only the string store was looked at, the key may still hold another type
+                None if backend.key_type(key).is_some() => return RESP_WRONGTYPE.clone(),
+                None => operands.push(vec![]),
+            }
+        }
+
+        let result = bitop(self.op, &operands);
+        let len = result.len() as i64;
+        // like redis, an empty result removes the destination key
+        if result.is_empty() {
+            backend.del(&self.dest);
+        } else {
+            backend.set(self.dest, BulkString::new(result).into());
+        }
+        len.into()
+    }
+}
+
+impl CommandExecutor for BitPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let data = match backend.get(&self.key) {
+            Some(RespFrame::BulkString(s)) => s.0,
+            Some(_) => return RESP_WRONGTYPE.clone(),
+            None if backend.key_type(&self.key).is_some() => return RESP_WRONGTYPE.clone(),
+            // a missing key is an endless run of 0 bits
+            None => return RespFrame::Integer(if self.bit { -1 } else { 0 }),
+        };
+
+        let len = if self.range.bits {
+            data.len() * 8
+        } else {
+            data.len()
+        };
+        let Some((start, end)) =
+            normalize_range(self.range.start, self.range.end.unwrap_or(-1), len)
+        else {
+            return RespFrame::Integer(-1);
+        };
+        let (first, last) = if self.range.bits {
+            (start, end)
+        } else {
+            (start * 8, end * 8 + 7)
+        };
+        match bitpos(&data, self.bit, first, last) {
+            Some(pos) => RespFrame::Integer(pos as i64),
+            // like redis, the string is padded with 0 bits on the right unless the range has an end
+            None if !self.bit && self.range.end.is_none() => RespFrame::Integer(last as i64 + 1),
+            None => RespFrame::Integer(-1),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BitOp {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 4 {
+            return Err(CommandError::InvalidArgument(
+                "bitop command must have an operation, a destination and at least one key"
+                    .to_string(),
+            ));
+        }
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let op = match args.next().map(|s| s.to_ascii_lowercase()).as_deref() {
+            Some("and") => BitOperation::And,
+            Some("or") => BitOperation::Or,
+            Some("xor") => BitOperation::Xor,
+            Some("not") => BitOperation::Not,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid bitop operation".to_string(),
+                ))
+            }
+        };
+        let dest = args.next().unwrap_or_default();
+        let keys: Vec<String> = args.collect();
+        if op == BitOperation::Not && keys.len() != 1 {
+            return Err(CommandError::InvalidArgument(
+                "BITOP NOT must be called with a single source key".to_string(),
+            ));
+        }
+
+        Ok(BitOp { op, dest, keys })
+    }
+}
+
+// BITPOS key bit [start [end [BYTE | BIT]]]
+impl TryFrom<RespArray> for BitPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if !(3..=6).contains(&value.len()) {
+            return Err(CommandError::InvalidArgument(
+                "bitpos command must have a key, a bit and an optional range".to_string(),
+            ));
+        }
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let key = args.next().unwrap_or_default();
+        let bit = match args.next().as_deref() {
+            Some("1") => true,
+            Some("0") => false,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "The bit argument must be 1 or 0.".to_string(),
+                ))
+            }
+        };
+        let index = |s: String| {
+            s.parse::<i64>().map_err(|_| {
+                CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+            })
+        };
+        let mut range = BitRange::default();
+        if let Some(start) = args.next() {
+            range.start = index(start)?;
+        }
+        if let Some(end) = args.next() {
+            range.end = Some(index(end)?);
+        }
+        if let Some(unit) = args.next() {
+            range.bits = match unit.to_ascii_lowercase().as_str() {
+                "bit" => true,
+                "byte" => false,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            };
+        }
+
+        Ok(BitPos { key, bit, range })
+    }
+}
+
+// position of the first `bit` in the bits `first..=last` of the data. Runs of bytes without a
+// match are skipped a 64-bit word at a time
+fn bitpos(data: &[u8], bit: bool, first: usize, last: usize) -> Option<usize> {
+    // a search for 0 is a search for 1 in the inverted bytes
+    let flip = if bit { 0 } else { 0xff };
+    let (first_byte, last_byte) = (first / 8, last / 8);
+    let mut i = first_byte;
+    while i <= last_byte {
+        // only whole words strictly between the first and the last byte, those two are masked
+        if i > first_byte && i + 8 <= last_byte {
+            let word = u64::from_be_bytes(data[i..i + 8].try_into().ok()?);
+            if word == u64::from_be_bytes([flip; 8]) {
+                i += 8;
+                continue;
+            }
+        }
+        let mut byte = data[i] ^ flip;
+        if i == first_byte {
+            byte &= 0xff >> (first % 8);
+        }
+        if i == last_byte {
+            byte &= 0xff << (7 - last % 8);
+        }
+        if byte != 0 {
+            return Some(i * 8 + byte.leading_zeros() as usize);
+        }
+        i += 1;
+    }
+    None
+}
+
+// shorter operands are zero-extended to the length of the longest one
+fn bitop(op: BitOperation, operands: &[Vec<u8>]) -> Vec<u8> {
+    let len = operands.iter().map(|v| v.len()).max().unwrap_or(0);
+    let byte = |v: &Vec<u8>, i: usize| v.get(i).copied().unwrap_or(0);
+
+    (0..len)
+        .map(|i| {
+            let mut bytes = operands.iter().map(|v| byte(v, i));
+            let first = bytes.next().unwrap_or(0);
+            match op {
+                BitOperation::And => bytes.fold(first, |acc, b| acc & b),
+                BitOperation::Or => bytes.fold(first, |acc, b| acc | b),
+                BitOperation::Xor => bytes.fold(first, |acc, b| acc ^ b),
+                BitOperation::Not => !first,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_bitop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nbitop\r\n$3\r\nAND\r\n$4\r\ndest\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: BitOp = frame.try_into()?;
+        assert_eq!(result.op, BitOperation::And);
+        assert_eq!(result.dest, "dest");
+        assert_eq!(result.keys, vec!["a", "b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitop_not_with_multiple_keys_should_fail() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nbitop\r\n$3\r\nnot\r\n$4\r\ndest\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<BitOp, _> = frame.try_into();
+        assert!(matches!(result, Err(CommandError::InvalidArgument(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitop_and_command() {
+        let backend = Backend::new();
+        backend.set(
+            "a".to_string(),
+            BulkString::new(vec![0b1111_0000, 0xff]).into(),
+        );
+        backend.set("b".to_string(), BulkString::new(vec![0b1010_1010]).into());
+
+        let cmd = BitOp {
+            op: BitOperation::And,
+            dest: "dest".to_string(),
+            keys: vec!["a".to_string(), "b".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(
+            backend.get("dest"),
+            Some(BulkString::new(vec![0b1010_0000, 0x00]).into())
+        );
+    }
+
+    #[test]
+    fn test_bitop_not_command() {
+        let backend = Backend::new();
+        backend.set(
+            "a".to_string(),
+            BulkString::new(vec![0b1111_0000, 0x0f]).into(),
+        );
+
+        let cmd = BitOp {
+            op: BitOperation::Not,
+            dest: "dest".to_string(),
+            keys: vec!["a".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(
+            backend.get("dest"),
+            Some(BulkString::new(vec![0b0000_1111, 0xf0]).into())
+        );
+    }
+
+    fn bitpos_of(backend: &Backend, args: &[&str]) -> Result<RespFrame> {
+        let frames = std::iter::once("bitpos")
+            .chain(args.iter().copied())
+            .map(|arg| BulkString::from(arg).into())
+            .collect::<Vec<RespFrame>>();
+        let cmd: BitPos = RespArray::new(frames).try_into()?;
+        Ok(cmd.execute(backend))
+    }
+
+    #[test]
+    fn test_bitpos_should_find_bit_mid_string() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(
+            "key".to_string(),
+            BulkString::new(b"\x00\x00\x10\xff").into(),
+        );
+        assert_eq!(bitpos_of(&backend, &["key", "1"])?, RespFrame::Integer(19));
+        assert_eq!(bitpos_of(&backend, &["key", "0"])?, RespFrame::Integer(0));
+        assert_eq!(
+            bitpos_of(&backend, &["key", "1", "3"])?,
+            RespFrame::Integer(24)
+        );
+        assert_eq!(
+            bitpos_of(&backend, &["key", "1", "-3", "-2"])?,
+            RespFrame::Integer(19)
+        );
+        assert_eq!(
+            bitpos_of(&backend, &["key", "1", "0", "1"])?,
+            RespFrame::Integer(-1)
+        );
+        assert_eq!(
+            bitpos_of(&backend, &["key", "1", "20", "-1", "BIT"])?,
+            RespFrame::Integer(24)
+        );
+        assert_eq!(
+            bitpos_of(&backend, &["key", "0", "19", "23", "bit"])?,
+            RespFrame::Integer(20)
+        );
+
+        // long runs are skipped a word at a time
+        let mut data = vec![0u8; 40];
+        data[33] = 0x01;
+        backend.set("long".to_string(), BulkString::new(data).into());
+        assert_eq!(
+            bitpos_of(&backend, &["long", "1"])?,
+            RespFrame::Integer(33 * 8 + 7)
+        );
+        assert_eq!(
+            bitpos_of(&backend, &["long", "1", "34"])?,
+            RespFrame::Integer(-1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitpos_missing_key_and_clear_bits() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(
+            bitpos_of(&backend, &["missing", "1"])?,
+            RespFrame::Integer(-1)
+        );
+        assert_eq!(
+            bitpos_of(&backend, &["missing", "0"])?,
+            RespFrame::Integer(0)
+        );
+
+        // no 0 in the string: past its end, unless the range has an end
+        backend.set("ones".to_string(), BulkString::new(vec![0xff; 10]).into());
+        assert_eq!(bitpos_of(&backend, &["ones", "0"])?, RespFrame::Integer(80));
+        assert_eq!(
+            bitpos_of(&backend, &["ones", "0", "0", "-1"])?,
+            RespFrame::Integer(-1)
+        );
+
+        assert!(bitpos_of(&backend, &["ones", "2"]).is_err());
+        backend.rpush("list".to_string(), [BulkString::from("a").into()]);
+        assert_eq!(bitpos_of(&backend, &["list", "1"])?, RESP_WRONGTYPE.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitop_or_xor_should_zero_extend_operands() {
+        let backend = Backend::new();
+        backend.set("a".to_string(), BulkString::new(vec![0x0f]).into());
+        backend.set(
+            "b".to_string(),
+            BulkString::new(vec![0xf0, 0x01, 0x02]).into(),
+        );
+
+        let bitop = |op, keys: &[&str]| BitOp {
+            op,
+            dest: "dest".to_string(),
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+        };
+        // a missing source key is all zero bytes
+        let cmd = bitop(BitOperation::Or, &["a", "b", "missing"]);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        assert_eq!(
+            backend.get("dest"),
+            Some(BulkString::new(vec![0xff, 0x01, 0x02]).into())
+        );
+
+        let cmd = bitop(BitOperation::Xor, &["b", "a"]);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        assert_eq!(
+            backend.get("dest"),
+            Some(BulkString::new(vec![0xff, 0x01, 0x02]).into())
+        );
+
+        backend.hset(
+            "hash".to_string(),
+            "f".to_string(),
+            BulkString::from("v").into(),
+        );
+        let cmd = bitop(BitOperation::And, &["a", "hash"]);
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+    }
+}
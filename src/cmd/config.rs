@@ -0,0 +1,286 @@
+use super::{help_reply, CommandExecutor, ConfigCommand, RESP_OK};
+use crate::{
+    backend::glob_match, cmd::CommandError, config::parse_memory, Backend, BulkString,
+    MaxMemoryPolicy, RespArray, RespFrame, SimpleError,
+};
+use std::path::Path;
+
+const CONFIG_HELP: &[&str] = &[
+    "GET <pattern>",
+    "    Return parameters matching the glob-like <pattern> and their values.",
+    "SET <directive> <value>",
+    "    Set the configuration <directive> to <value>.",
+];
+
+// the parameters CONFIG GET knows about, only some of them can be changed by CONFIG SET
+const PARAMETERS: &[&str] = &[
+    "maxmemory",
+    "maxmemory-policy",
+    "requirepass",
+    "appendonly",
+    "appendfilename",
+    "dbfilename",
+    "databases",
+    "hash-max-listpack-entries",
+    "busy-reply-threshold",
+    "replica-read-only",
+];
+
+impl CommandExecutor for ConfigCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self {
+            // flat name/value pairs like redis replies in RESP2
+            ConfigCommand::Get { patterns } => {
+                let pairs = PARAMETERS
+                    .iter()
+                    .filter(|name| {
+                        patterns.iter().any(|pattern| {
+                            glob_match(pattern.to_ascii_lowercase().as_bytes(), name.as_bytes())
+                        })
+                    })
+                    .flat_map(|name| {
+                        [
+                            BulkString::from(*name).into(),
+                            BulkString::from(parameter(backend, name)).into(),
+                        ]
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(pairs).into()
+            }
+            ConfigCommand::Set { parameter, value } => {
+                match set_parameter(backend, &parameter, &value) {
+                    Ok(()) => RESP_OK.clone(),
+                    Err(msg) => SimpleError::new(format!("ERR {}", msg)).into(),
+                }
+            }
+            ConfigCommand::Help => help_reply("CONFIG", CONFIG_HELP),
+        }
+    }
+}
+
+fn parameter(backend: &Backend, name: &str) -> String {
+    let config = &backend.config;
+    let file_name = |path: &Path| {
+        path.file_name()
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    match name {
+        "maxmemory" => backend.maxmemory().to_string(),
+        "maxmemory-policy" => backend.maxmemory_policy().to_string(),
+        "requirepass" => backend.requirepass().unwrap_or_default(),
+        "appendonly" => if config.appendonly { "yes" } else { "no" }.to_string(),
+        "appendfilename" => file_name(&config.aof_path),
+        "dbfilename" => file_name(&config.dump_path),
+        "databases" => backend.databases().to_string(),
+        "hash-max-listpack-entries" => config.hash_max_listpack_entries.to_string(),
+        "replica-read-only" => if backend.read_only() { "yes" } else { "no" }.to_string(),
+        "busy-reply-threshold" => config
+            .command_timeout
+            .map(|v| v.as_millis())
+            .unwrap_or(0)
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+// only the parameters the backend reads on every use can change at runtime
+fn set_parameter(backend: &Backend, name: &str, value: &str) -> Result<(), String> {
+    let invalid = || {
+        format!(
+            "CONFIG SET failed (possibly related to argument '{}') - argument couldn't be parsed into an integer",
+            name
+        )
+    };
+    match name {
+        "maxmemory" => backend.set_maxmemory(parse_memory(value).ok_or_else(invalid)?),
+        "maxmemory-policy" => {
+            backend.set_maxmemory_policy(value.parse::<MaxMemoryPolicy>().map_err(|e| {
+                format!(
+                    "CONFIG SET failed (possibly related to argument '{}') - {}",
+                    name, e
+                )
+            })?)
+        }
+        "requirepass" => backend.set_requirepass(value),
+        "replica-read-only" => backend.set_read_only(match value.to_ascii_lowercase().as_str() {
+            "yes" => true,
+            "no" => false,
+            _ => {
+                return Err(format!(
+                    "CONFIG SET failed (possibly related to argument '{}') - argument must be 'yes' or 'no'",
+                    name
+                ))
+            }
+        }),
+        name if PARAMETERS.contains(&name) => {
+            return Err(format!(
+            "CONFIG SET failed (possibly related to argument '{}') - can't set immutable config",
+            name
+        ))
+        }
+        name => {
+            return Err(format!(
+                "Unknown option or number of arguments for CONFIG SET - '{}'",
+                name
+            ))
+        }
+    }
+    Ok(())
+}
+
+impl TryFrom<RespArray> for ConfigCommand {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let subcommand = match value.get(1) {
+            Some(RespFrame::BulkString(ref cmd)) => cmd.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "CONFIG command must have a subcommand".to_string(),
+                ))
+            }
+        };
+        match subcommand.as_slice() {
+            b"get" => {
+                let patterns = value
+                    .into_strings()?
+                    .into_iter()
+                    .skip(2)
+                    .collect::<Vec<_>>();
+                if patterns.is_empty() {
+                    return Err(CommandError::WrongNumberOfArguments(
+                        "config|get".to_string(),
+                    ));
+                }
+                Ok(ConfigCommand::Get { patterns })
+            }
+            b"set" => {
+                let mut args = value.into_strings()?.into_iter().skip(2);
+                match (args.next(), args.next(), args.next()) {
+                    (Some(parameter), Some(value), None) => Ok(ConfigCommand::Set {
+                        parameter: parameter.to_ascii_lowercase(),
+                        value,
+                    }),
+                    _ => Err(CommandError::WrongNumberOfArguments(
+                        "config|set".to_string(),
+                    )),
+                }
+            }
+            b"help" => Ok(ConfigCommand::Help),
+            v => Err(CommandError::InvalidCommand(format!(
+                "Unknown CONFIG subcommand: {}",
+                String::from_utf8_lossy(v)
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendConfig, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn config(backend: &Backend, args: &[&str]) -> Result<RespFrame> {
+        let frame = RespArray::new(
+            ["config"]
+                .iter()
+                .chain(args)
+                .map(|v| BulkString::from(*v).into())
+                .collect::<Vec<RespFrame>>(),
+        );
+        let cmd: ConfigCommand = frame.try_into()?;
+        Ok(cmd.execute(backend))
+    }
+
+    fn pairs(values: &[&str]) -> RespFrame {
+        RespArray::new(
+            values
+                .iter()
+                .map(|v| BulkString::from(*v).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn test_config_help() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$6\r\nCONFIG\r\n$4\r\nHELP\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: ConfigCommand = frame.try_into()?;
+
+        let RespFrame::Array(lines) = cmd.execute(&Backend::new()) else {
+            panic!("CONFIG HELP should return an array");
+        };
+        assert!(!lines.is_empty());
+        assert!(lines
+            .iter()
+            .all(|line| matches!(line, RespFrame::SimpleString(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_get() -> Result<()> {
+        let backend = Backend::with_config(BackendConfig {
+            maxmemory: 1024,
+            ..Default::default()
+        });
+        assert_eq!(
+            config(&backend, &["get", "maxmemory"])?,
+            pairs(&["maxmemory", "1024"])
+        );
+        assert_eq!(
+            config(&backend, &["GET", "MAXMEMORY*"])?,
+            pairs(&["maxmemory", "1024", "maxmemory-policy", "noeviction"])
+        );
+        assert_eq!(config(&backend, &["get", "nosuchparam"])?, pairs(&[]));
+        assert!(config(&backend, &["get"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_set() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(
+            config(&backend, &["set", "maxmemory", "10mb"])?,
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.maxmemory(), 10 * 1024 * 1024);
+        assert_eq!(
+            config(&backend, &["get", "maxmemory"])?,
+            pairs(&["maxmemory", "10485760"])
+        );
+
+        assert_eq!(
+            config(&backend, &["set", "requirepass", "secret"])?,
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.requirepass().as_deref(), Some("secret"));
+        config(&backend, &["set", "requirepass", ""])?;
+        assert_eq!(backend.requirepass(), None);
+
+        config(&backend, &["set", "replica-read-only", "yes"])?;
+        assert!(backend.read_only());
+        assert_eq!(
+            config(&backend, &["get", "replica-read-only"])?,
+            pairs(&["replica-read-only", "yes"])
+        );
+        assert!(matches!(
+            config(&backend, &["set", "replica-read-only", "maybe"])?,
+            RespFrame::Error(_)
+        ));
+        assert!(backend.read_only());
+
+        for args in [
+            ["set", "maxmemory", "lots"],
+            ["set", "databases", "4"],
+            ["set", "nosuchparam", "1"],
+        ] {
+            assert!(matches!(config(&backend, &args)?, RespFrame::Error(_)));
+        }
+        assert_eq!(backend.maxmemory(), 10 * 1024 * 1024);
+        Ok(())
+    }
+}
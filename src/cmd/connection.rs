@@ -0,0 +1,231 @@
+use super::{
+    extract_args, validate_command, Auth, CommandExecutor, Discard, Exec, Hello, Multi, Ping, Quit,
+    Select, RESP_OK,
+};
+use crate::{cmd::CommandError, Backend, RespArray, RespFrame, SimpleError, SimpleString};
+
+// PONG, or the message back as a bulk string
+impl CommandExecutor for Ping {
+    fn execute(self, _: &Backend) -> RespFrame {
+        match self.message {
+            Some(message) => message,
+            None => SimpleString::new("PONG").into(),
+        }
+    }
+}
+
+// the connection is closed by the network layer once the reply is flushed
+impl CommandExecutor for Quit {
+    fn execute(self, _: &Backend) -> RespFrame {
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Auth {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR AUTH is only supported on a client connection").into()
+    }
+}
+
+impl CommandExecutor for Select {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR SELECT is only supported on a client connection").into()
+    }
+}
+
+impl CommandExecutor for Hello {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR HELLO is only supported on a client connection").into()
+    }
+}
+
+impl CommandExecutor for Multi {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR MULTI is only supported on a client connection").into()
+    }
+}
+
+impl CommandExecutor for Exec {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR EXEC without MULTI").into()
+    }
+}
+
+impl CommandExecutor for Discard {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR DISCARD without MULTI").into()
+    }
+}
+
+// AUTH <password> or AUTH <username> <password>
+impl TryFrom<RespArray> for Auth {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next(), args.next()) {
+            (Some(password), None, None) => Ok(Auth {
+                username: None,
+                password,
+            }),
+            (Some(username), Some(password), None) => Ok(Auth {
+                username: Some(username),
+                password,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "auth command must have a password and an optional username".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Select {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["select"], 1)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match args.next().map(|v| v.parse::<i64>()) {
+            Some(Ok(index)) => Ok(Select { index }),
+            _ => Err(CommandError::InvalidArgument(
+                "value is not an integer or out of range".to_string(),
+            )),
+        }
+    }
+}
+
+// HELLO [protover [AUTH username password]]
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let Some(protover) = args.next() else {
+            return Ok(Hello {
+                protover: None,
+                auth: None,
+            });
+        };
+        let protover = protover.parse::<i64>().map_err(|_| {
+            CommandError::InvalidArgument(
+                "Protocol version is not an integer or out of range".to_string(),
+            )
+        })?;
+        let auth = match (args.next(), args.next(), args.next(), args.next()) {
+            (None, ..) => None,
+            (Some(opt), Some(username), Some(password), None)
+                if opt.eq_ignore_ascii_case("auth") =>
+            {
+                Some(Auth {
+                    username: Some(username),
+                    password,
+                })
+            }
+            (Some(opt), ..) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Syntax error in HELLO option '{}'",
+                    opt
+                )))
+            }
+        };
+        Ok(Hello {
+            protover: Some(protover),
+            auth,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Multi {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["multi"], 0)?;
+        Ok(Multi)
+    }
+}
+
+impl TryFrom<RespArray> for Exec {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["exec"], 0)?;
+        Ok(Exec)
+    }
+}
+
+impl TryFrom<RespArray> for Discard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["discard"], 0)?;
+        Ok(Discard)
+    }
+}
+
+impl TryFrom<RespArray> for Quit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["quit"], 0)?;
+        Ok(Quit)
+    }
+}
+
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() > 2 {
+            return Err(CommandError::InvalidArgument(
+                "ping command must have at most one argument".to_string(),
+            ));
+        }
+        let message = extract_args(value, 1)?.into_iter().next();
+        Ok(Ping { message })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_hello_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n$6\r\nsecret\r\n",
+        );
+        let cmd: Hello = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.protover, Some(3));
+        let auth = cmd.auth.unwrap();
+        assert_eq!(auth.username.as_deref(), Some("default"));
+        assert_eq!(auth.password, "secret");
+
+        buf.extend_from_slice(b"*3\r\n$5\r\nhello\r\n$1\r\n3\r\n$7\r\nsetname\r\n");
+        let ret = Hello::try_from(RespArray::decode(&mut buf)?);
+        assert!(ret.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quit_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nquit\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Quit = frame.try_into()?;
+        assert_eq!(cmd.execute(&Backend::new()), RESP_OK.clone());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ping_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nPING\r\n*2\r\n$4\r\nping\r\n$5\r\nhello\r\n");
+
+        let backend = Backend::new();
+        let cmd: Ping = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), SimpleString::new("PONG").into());
+        let cmd: Ping = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), BulkString::from("hello").into());
+
+        Ok(())
+    }
+}
@@ -1,59 +1,131 @@
-use super::{extract_args, validate_command, CommandExecutor, HGet, HGetAll, HSet, RESP_OK};
-use crate::{cmd::CommandError, BulkString, RespArray, RespFrame};
+use super::{
+    extract_args, is_other_type, parse_key_scan, validate_command, CommandExecutor, HGet, HGetAll,
+    HRandField, HScan, HSet, HSetNx, RESP_OK, RESP_WRONGTYPE,
+};
+use crate::{
+    backend::glob_match, cmd::CommandError, BulkString, RespArray, RespFrame, RespMap, RespNull,
+};
 
 impl CommandExecutor for HGet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         match backend.hget(&self.key, &self.field) {
             Some(value) => value,
+            None if is_other_type(backend, &self.key, "hash") => RESP_WRONGTYPE.clone(),
             None => RespFrame::Null(crate::RespNull),
         }
     }
 }
 
+// the reply is a map of the fields to their values in field order, RESP2 connections get it
+// flattened to field/value pairs. A missing key is an empty hash, a key of another type is
+// WRONGTYPE
 impl CommandExecutor for HGetAll {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        let hmap = backend.hmap.get(&self.key);
+        let hmap = backend.hgetall(&self.key);
 
         match hmap {
             Some(hmap) => {
-                let mut data = Vec::with_capacity(hmap.len());
+                let mut map = RespMap::new();
                 for v in hmap.iter() {
-                    let key = v.key().to_owned();
-                    data.push((key, v.value().clone()));
+                    map.insert(v.key().to_owned(), v.value().clone());
                 }
-                if self.sort {
-                    data.sort_by(|a, b| a.0.cmp(&b.0));
-                }
-                let ret = data
-                    .into_iter()
-                    .flat_map(|(k, v)| vec![BulkString::from(k).into(), v])
-                    .collect::<Vec<RespFrame>>();
-
-                RespArray::new(ret).into()
+                map.into()
             }
-            None => RespArray::new([]).into(),
+            None if backend.key_type(&self.key).is_some() => RESP_WRONGTYPE.clone(),
+            None => RespMap::new().into(),
         }
     }
 }
 
 impl CommandExecutor for HSet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "hash") {
+            return RESP_WRONGTYPE.clone();
+        }
         backend.hset(self.key, self.field, self.value);
         RESP_OK.clone()
     }
 }
 
+// replies 1 when the field was created and 0 when it already existed
+impl CommandExecutor for HSetNx {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "hash") {
+            return RESP_WRONGTYPE.clone();
+        }
+        let created = backend.hsetnx(self.key, self.field, self.value);
+        (created as i64).into()
+    }
+}
+
+// the reply is [next cursor, [field, value, ...]] like SCAN, MATCH filters the page by field
+// name. A missing key is an empty hash
+impl CommandExecutor for HScan {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let (cursor, fields) = match backend.hscan(&self.key, self.cursor, self.count) {
+            Some(page) => page,
+            None if is_other_type(backend, &self.key, "hash") => return RESP_WRONGTYPE.clone(),
+            None => (0, vec![]),
+        };
+        let fields = fields
+            .into_iter()
+            .filter(|(field, _)| match &self.pattern {
+                Some(pattern) => glob_match(pattern.as_bytes(), field.as_bytes()),
+                None => true,
+            })
+            .flat_map(|(field, value)| [BulkString::from(field).into(), value])
+            .collect::<Vec<RespFrame>>();
+        RespArray::new([
+            BulkString::from(cursor.to_string()).into(),
+            RespArray::new(fields).into(),
+        ])
+        .into()
+    }
+}
+
+// without a count the reply is one field or null, with a count it's an array of fields,
+// flattened with their values for WITHVALUES
+impl CommandExecutor for HRandField {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let fields = match backend.hrandfield(&self.key, self.count.unwrap_or(1)) {
+            Ok(fields) => fields,
+            Err(e) => return CommandError::InvalidArgument(e.to_string()).into(),
+        };
+        let Some(fields) = fields else {
+            return match backend.key_type(&self.key) {
+                Some(_) => RESP_WRONGTYPE.clone(),
+                None if self.count.is_some() => RespArray::new([]).into(),
+                None => RespFrame::Null(RespNull),
+            };
+        };
+        if self.count.is_none() {
+            return match fields.into_iter().next() {
+                Some((field, _)) => BulkString::from(field).into(),
+                None => RespFrame::Null(RespNull),
+            };
+        }
+        let ret = fields
+            .into_iter()
+            .flat_map(|(field, value)| {
+                let field = BulkString::from(field).into();
+                match self.withvalues {
+                    true => vec![field, value],
+                    false => vec![field],
+                }
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
 impl TryFrom<RespArray> for HGet {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
         validate_command(&value, &["hget"], 2)?;
 
-        let mut args = extract_args(value, 1)?.into_iter();
+        let mut args = value.into_strings()?.into_iter().skip(1);
         match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => Ok(HGet {
-                key: String::from_utf8(key.0)?,
-                field: String::from_utf8(field.0)?,
-            }),
+            (Some(key), Some(field)) => Ok(HGet { key, field }),
             _ => Err(CommandError::InvalidArgument(
                 "Invalid key or field".to_string(),
             )),
@@ -66,12 +138,9 @@ impl TryFrom<RespArray> for HGetAll {
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
         validate_command(&value, &["hgetall"], 1)?;
 
-        let mut args = extract_args(value, 1)?.into_iter();
+        let mut args = value.into_strings()?.into_iter().skip(1);
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(HGetAll {
-                key: String::from_utf8(key.0)?,
-                sort: false,
-            }),
+            Some(key) => Ok(HGetAll { key }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
@@ -98,9 +167,83 @@ impl TryFrom<RespArray> for HSet {
     }
 }
 
+impl TryFrom<RespArray> for HSetNx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hsetnx"], 3)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
+                Ok(HSetNx {
+                    key: String::from_utf8(key.0)?,
+                    field: String::from_utf8(field.0)?,
+                    value,
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, field or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HRandField {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if !(2..=4).contains(&value.len()) {
+            return Err(CommandError::InvalidArgument(
+                "hrandfield command must have a key, an optional count and WITHVALUES".to_string(),
+            ));
+        }
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let key = args.next().unwrap_or_default();
+        let count = match args.next() {
+            Some(count) => Some(count.parse::<i64>().map_err(|_| {
+                CommandError::InvalidArgument("count must be an integer".to_string())
+            })?),
+            None => None,
+        };
+        let withvalues = match args.next() {
+            Some(arg) if arg.eq_ignore_ascii_case("withvalues") => true,
+            Some(arg) => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Invalid HRANDFIELD option: {}",
+                    arg
+                )))
+            }
+            None => false,
+        };
+        Ok(HRandField {
+            key,
+            count,
+            withvalues,
+        })
+    }
+}
+
+// HSCAN key cursor [MATCH pattern] [COUNT count]
+impl TryFrom<RespArray> for HScan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, cursor, pattern, count) = parse_key_scan(value, "HSCAN")?;
+        Ok(HScan {
+            key,
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::RespDecode;
+    use crate::{
+        backend::HRANDFIELD_MAX_REPEATED, BackendConfig, MockClock, RespDecode, RespEncode,
+        SimpleError,
+    };
+    use std::{sync::Arc, time::Duration};
 
     use super::*;
     use anyhow::Result;
@@ -175,17 +318,370 @@ mod tests {
 
         let cmd = HGetAll {
             key: "map".to_string(),
-            sort: true,
         };
         let result = cmd.execute(&backend);
 
-        let expected = RespArray::new([
-            BulkString::from("hello").into(),
-            BulkString::from("world").into(),
-            BulkString::from("hello1").into(),
-            BulkString::from("world1").into(),
-        ]);
+        let mut expected = RespMap::new();
+        expected.insert("hello".to_string(), BulkString::from("world").into());
+        expected.insert("hello1".to_string(), BulkString::from("world1").into());
         assert_eq!(result, expected.into());
         Ok(())
     }
+
+    #[test]
+    fn test_hgetall_missing_key_should_return_empty_map() {
+        let backend = crate::Backend::new();
+        let cmd = HGetAll {
+            key: "missing".to_string(),
+        };
+        let reply = cmd.execute(&backend);
+        assert_eq!(reply, RespMap::new().into());
+        assert_eq!(reply.into_resp2().encode(), b"*0\r\n");
+    }
+
+    #[test]
+    fn test_hgetall_on_string_key_should_return_wrongtype() {
+        let backend = crate::Backend::new();
+        backend.set("hello".to_string(), BulkString::from("world").into());
+        let cmd = HGetAll {
+            key: "hello".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+    }
+
+    #[test]
+    fn test_hscan_should_page_through_fields() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nhscan\r\n$4\r\nhash\r\n$1\r\n0\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n",
+        );
+        let cmd: HScan = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(
+            (cmd.cursor, cmd.count, cmd.pattern.as_deref()),
+            (0, 2, None)
+        );
+
+        let backend = crate::Backend::new();
+        for field in ["a", "b", "c"] {
+            backend.hset(
+                "hash".to_string(),
+                field.to_string(),
+                BulkString::from(field).into(),
+            );
+        }
+        let page = |cursor, pattern: Option<&str>| {
+            HScan {
+                key: "hash".to_string(),
+                cursor,
+                pattern: pattern.map(str::to_string),
+                count: 2,
+            }
+            .execute(&backend)
+        };
+        let reply = |cursor: &str, fields: &[&str]| -> RespFrame {
+            let fields = fields
+                .iter()
+                .flat_map(|f| [BulkString::from(*f).into(), BulkString::from(*f).into()])
+                .collect::<Vec<RespFrame>>();
+            RespArray::new([
+                BulkString::from(cursor).into(),
+                RespArray::new(fields).into(),
+            ])
+            .into()
+        };
+        // the fields come in the order of their cursor positions
+        let (next, first) = backend.hscan("hash", 0, 2).unwrap();
+        let first = first.iter().map(|(f, _)| f.as_str()).collect::<Vec<_>>();
+        let rest = ["a", "b", "c"].into_iter().filter(|f| !first.contains(f));
+        let (next, rest) = (next.to_string(), rest.collect::<Vec<_>>());
+        assert_eq!(first.len(), 2);
+        assert_eq!(page(0, None), reply(&next, &first));
+        assert_eq!(page(next.parse()?, None), reply("0", &rest));
+        // MATCH filters a page, it doesn't change the cursor
+        assert_eq!(page(0, Some(first[1])), reply(&next, &first[1..]));
+
+        let missing = HScan {
+            key: "missing".to_string(),
+            cursor: 0,
+            pattern: None,
+            count: 2,
+        };
+        assert_eq!(missing.execute(&backend), reply("0", &[]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hgetall_should_flatten_fields_in_order_for_resp2() {
+        let backend = crate::Backend::new();
+        for field in ["c", "a", "d", "b"] {
+            backend.hset(
+                "map".to_string(),
+                field.to_string(),
+                BulkString::from(field.to_uppercase()).into(),
+            );
+        }
+
+        let reply = HGetAll {
+            key: "map".to_string(),
+        }
+        .execute(&backend);
+        let expected = ["a", "b", "c", "d"]
+            .iter()
+            .flat_map(|v| {
+                [
+                    BulkString::from(*v).into(),
+                    BulkString::from(v.to_uppercase()).into(),
+                ]
+            })
+            .collect::<Vec<RespFrame>>();
+        assert_eq!(reply.into_resp2(), RespArray::new(expected).into());
+    }
+
+    #[test]
+    fn test_hsetnx_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nHSETNX\r\n$3\r\nmap\r\n$5\r\nhello\r\n$5\r\nworld\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: HSetNx = frame.try_into()?;
+        assert_eq!(cmd.key, "map");
+        assert_eq!(cmd.field, "hello");
+
+        let backend = crate::Backend::new();
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(
+            backend.hget("map", "hello"),
+            Some(BulkString::from("world").into())
+        );
+
+        let cmd = HSetNx {
+            key: "map".to_string(),
+            field: "hello".to_string(),
+            value: BulkString::from("other").into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(
+            backend.hget("map", "hello"),
+            Some(BulkString::from("world").into())
+        );
+
+        backend.set("string".to_string(), BulkString::from("value").into());
+        let cmd = HSetNx {
+            key: "string".to_string(),
+            field: "hello".to_string(),
+            value: BulkString::from("world").into(),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_hsetnx_should_create_field_once() {
+        let backend = crate::Backend::new();
+        let handles = (0..8)
+            .map(|i| {
+                let backend = backend.clone();
+                std::thread::spawn(move || {
+                    backend.hsetnx(
+                        "map".to_string(),
+                        "field".to_string(),
+                        BulkString::from(i.to_string()).into(),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        let created = handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .filter(|created| *created)
+            .count();
+        assert_eq!(created, 1);
+    }
+
+    fn hrandfield_backend() -> crate::Backend {
+        let backend = crate::Backend::new();
+        for i in 0..5 {
+            backend.hset(
+                "map".to_string(),
+                format!("field{}", i),
+                BulkString::from(format!("value{}", i)).into(),
+            );
+        }
+        backend
+    }
+
+    fn bulk_strings(frame: RespFrame) -> Vec<String> {
+        let RespFrame::Array(array) = frame else {
+            panic!("HRANDFIELD with a count should return an array");
+        };
+        array
+            .0
+            .into_iter()
+            .map(|v| match v {
+                RespFrame::BulkString(v) => String::from_utf8(v.0).unwrap(),
+                v => panic!("expect a bulk string, got {:?}", v),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hrandfield_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$10\r\nhrandfield\r\n$3\r\nmap\r\n$2\r\n-5\r\n$10\r\nWITHVALUES\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: HRandField = frame.try_into()?;
+        assert_eq!(result.key, "map");
+        assert_eq!(result.count, Some(-5));
+        assert!(result.withvalues);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hrandfield_distinct_fields() {
+        let backend = hrandfield_backend();
+        let cmd = HRandField {
+            key: "map".to_string(),
+            count: Some(3),
+            withvalues: false,
+        };
+        let mut fields = bulk_strings(cmd.execute(&backend));
+        fields.sort();
+        fields.dedup();
+        assert_eq!(fields.len(), 3);
+
+        // asking for more than the hash holds returns every field once
+        let cmd = HRandField {
+            key: "map".to_string(),
+            count: Some(10),
+            withvalues: false,
+        };
+        let mut fields = bulk_strings(cmd.execute(&backend));
+        fields.sort();
+        assert_eq!(fields, ["field0", "field1", "field2", "field3", "field4"]);
+
+        let cmd = HRandField {
+            key: "map".to_string(),
+            count: None,
+            withvalues: false,
+        };
+        assert!(
+            matches!(cmd.execute(&backend), RespFrame::BulkString(v) if v.starts_with(b"field"))
+        );
+
+        let cmd = HRandField {
+            key: "missing".to_string(),
+            count: None,
+            withvalues: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+    }
+
+    #[test]
+    fn test_hrandfield_extreme_counts() {
+        let backend = hrandfield_backend();
+        let cmd = HRandField {
+            key: "map".to_string(),
+            count: Some(i64::MAX),
+            withvalues: false,
+        };
+        let mut fields = bulk_strings(cmd.execute(&backend));
+        fields.sort();
+        assert_eq!(fields, ["field0", "field1", "field2", "field3", "field4"]);
+
+        for count in [i64::MIN, -(HRANDFIELD_MAX_REPEATED as i64) - 1] {
+            let cmd = HRandField {
+                key: "map".to_string(),
+                count: Some(count),
+                withvalues: true,
+            };
+            assert_eq!(
+                cmd.execute(&backend),
+                RespFrame::Error(SimpleError::new("ERR value is out of range"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_hrandfield_should_skip_expired_hash() {
+        let clock = Arc::new(MockClock::new(1_000_000));
+        let backend = crate::Backend::with_config(BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        backend.hset(
+            "map".to_string(),
+            "field".to_string(),
+            BulkString::from("value").into(),
+        );
+        backend.expire("map", Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(backend.hrandfield("map", 1), Ok(None));
+    }
+
+    #[test]
+    fn test_hrandfield_negative_count_allows_repeats() {
+        let backend = crate::Backend::new();
+        backend.hset(
+            "map".to_string(),
+            "only".to_string(),
+            BulkString::from("value").into(),
+        );
+        let cmd = HRandField {
+            key: "map".to_string(),
+            count: Some(-4),
+            withvalues: false,
+        };
+        assert_eq!(
+            bulk_strings(cmd.execute(&backend)),
+            ["only", "only", "only", "only"]
+        );
+    }
+
+    #[test]
+    fn test_hrandfield_withvalues() {
+        let backend = hrandfield_backend();
+        let cmd = HRandField {
+            key: "map".to_string(),
+            count: Some(-20),
+            withvalues: true,
+        };
+        let values = bulk_strings(cmd.execute(&backend));
+        assert_eq!(values.len(), 40);
+        for pair in values.chunks(2) {
+            assert_eq!(pair[1], pair[0].replace("field", "value"));
+        }
+    }
+
+    #[test]
+    fn test_hset_hget_on_string_key_should_fail() {
+        let backend = crate::Backend::new();
+        backend.set("key".to_string(), BulkString::from("value").into());
+
+        let cmd = HSet {
+            key: "key".to_string(),
+            field: "field".to_string(),
+            value: BulkString::from("value").into(),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        // no hash was created next to the string
+        assert!(backend.db().hmap.get("key").is_none());
+        assert_eq!(backend.key_type("key"), Some("string"));
+
+        let cmd = HGet {
+            key: "key".to_string(),
+            field: "field".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+
+        // SET replaces a hash instead of shadowing it
+        backend.hset(
+            "map".to_string(),
+            "field".to_string(),
+            BulkString::from("value").into(),
+        );
+        backend.set("map".to_string(), BulkString::from("value").into());
+        assert!(backend.db().hmap.get("map").is_none());
+        assert_eq!(backend.key_type("map"), Some("string"));
+    }
 }
@@ -0,0 +1,94 @@
+use super::{CommandExecutor, PfAdd, PfCount, RESP_WRONGTYPE};
+use crate::{cmd::CommandError, Backend, PfAddError, RespArray, RespFrame, SimpleError};
+
+impl CommandExecutor for PfAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfadd(&self.key, &self.elements) {
+            Ok(changed) => (changed as i64).into(),
+            Err(PfAddError::WrongType) => RESP_WRONGTYPE.clone(),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfcount(&self.keys) {
+            Ok(count) => (count as i64).into(),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PfAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::InvalidArgument(
+                "pfadd command must have a key".to_string(),
+            ));
+        }
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let key = args.next().unwrap_or_default();
+        Ok(PfAdd {
+            key,
+            elements: args.collect(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PfCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::InvalidArgument(
+                "pfcount command must have at least one key".to_string(),
+            ));
+        }
+
+        let keys = value.into_strings()?.into_iter().skip(1).collect();
+        Ok(PfCount { keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_pfadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: PfAdd = frame.try_into()?;
+        assert_eq!(result.key, "hll");
+        assert_eq!(result.elements, vec!["a", "b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pfadd_pfcount_commands() {
+        let backend = Backend::new();
+        for chunk in (0..10_000).collect::<Vec<_>>().chunks(100) {
+            let cmd = PfAdd {
+                key: "hll".to_string(),
+                elements: chunk.iter().map(|i| format!("element-{}", i)).collect(),
+            };
+            assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        }
+
+        let cmd = PfCount {
+            keys: vec!["hll".to_string(), "missing".to_string()],
+        };
+        let RespFrame::Integer(count) = cmd.execute(&backend) else {
+            panic!("PFCOUNT should return an integer");
+        };
+        assert!((count - 10_000).abs() < 300, "{}", count);
+    }
+}
@@ -0,0 +1,1063 @@
+use super::{
+    help_reply, parse_scan_options, validate_command, Command, CommandExecutor, Del, Expire,
+    ExpireAt, ExpireTime, Keys, MemoryCommand, Move, ObjectCommand, PExpireAt, PExpireTime, Scan,
+    SwapDb, Ttl, Type, RESP_OK,
+};
+use crate::{
+    backend::glob_match, cmd::CommandError, Backend, BulkString, RespArray, RespEncode, RespFrame,
+    RespNull, SimpleError, SimpleString,
+};
+use std::time::Duration;
+
+const OBJECT_HELP: &[&str] = &[
+    "ENCODING <key>",
+    "    Return the kind of internal representation used in order to store the value",
+    "    associated with a <key>.",
+    "FREQ <key>",
+    "    Return the access frequency index of the <key>. The returned integer is",
+    "    proportional to the logarithm of the recent access frequency of the key.",
+];
+
+const MEMORY_HELP: &[&str] = &[
+    "USAGE <key> [SAMPLES <count>]",
+    "    Return memory in bytes used by <key> and its value. Nested values are",
+    "    sampled up to <count> times (default: 5, 0 means sample all).",
+];
+
+impl CommandExecutor for ObjectCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self {
+            ObjectCommand::Freq { key } => match backend.freq(&key) {
+                Some(freq) => (freq as i64).into(),
+                None => RespFrame::Null(RespNull),
+            },
+            ObjectCommand::Encoding { key } => match backend.object_encoding(&key) {
+                Some(encoding) => BulkString::from(encoding).into(),
+                None => RespFrame::Null(RespNull),
+            },
+            ObjectCommand::Help => help_reply("OBJECT", OBJECT_HELP),
+        }
+    }
+}
+
+impl CommandExecutor for MemoryCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self {
+            // key_type deletes the key first when its time to live is over
+            MemoryCommand::Usage { key, samples } => match backend
+                .key_type(&key)
+                .and_then(|_| backend.size_estimate(&key, samples))
+            {
+                Some(size) => (size as i64).into(),
+                None => RespFrame::Null(RespNull),
+            },
+            MemoryCommand::Help => help_reply("MEMORY", MEMORY_HELP),
+        }
+    }
+}
+
+// the reply is [next cursor, [key, ...]], the cursor is a bulk string like redis
+impl CommandExecutor for Scan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, keys) = backend.scan(self.cursor, self.count);
+        let keys = keys
+            .into_iter()
+            .filter(|key| match &self.pattern {
+                Some(pattern) => glob_match(pattern.as_bytes(), key.as_bytes()),
+                None => true,
+            })
+            .map(|key| BulkString::from(key).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new([
+            BulkString::from(cursor.to_string()).into(),
+            RespArray::new(keys).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for Keys {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.keys(&self.pattern) {
+            Ok(keys) => {
+                let keys = keys
+                    .into_iter()
+                    .map(|key| BulkString::from(key).into())
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(keys).into()
+            }
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Del {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let deleted = self.keys.iter().filter(|key| backend.del(key)).count();
+        (deleted as i64).into()
+    }
+}
+
+// replies 1 when the key exists and got the time to live, 0 otherwise
+impl CommandExecutor for Expire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let done = match u64::try_from(self.seconds) {
+            Ok(seconds) if seconds > 0 => backend.expire(&self.key, Duration::from_secs(seconds)),
+            _ => backend.del(&self.key),
+        };
+        (done as i64).into()
+    }
+}
+
+impl CommandExecutor for ExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        PExpireAt {
+            key: self.key,
+            timestamp: self.timestamp.saturating_mul(1000),
+        }
+        .execute(backend)
+    }
+}
+
+impl CommandExecutor for PExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let at_ms = u64::try_from(self.timestamp).unwrap_or_default();
+        let done = if at_ms > backend.config.clock.now_ms() {
+            backend.expire_at(&self.key, at_ms)
+        } else {
+            backend.del(&self.key)
+        };
+        (done as i64).into()
+    }
+}
+
+// seconds left rounded like redis, -1 for a key without a time to live and -2 for a missing key
+impl CommandExecutor for Ttl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ttl(&self.key) {
+            Some(Some(ttl)) => (ttl.as_millis().div_ceil(1000) as i64).into(),
+            Some(None) => (-1).into(),
+            None => (-2).into(),
+        }
+    }
+}
+
+// unix time in seconds, -1 for a key without a time to live and -2 for a missing key
+impl CommandExecutor for ExpireTime {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.expire_time(&self.key) {
+            Some(Some(at)) => ((at / 1000) as i64).into(),
+            Some(None) => (-1).into(),
+            None => (-2).into(),
+        }
+    }
+}
+
+// same as EXPIRETIME in milliseconds
+impl CommandExecutor for PExpireTime {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.expire_time(&self.key) {
+            Some(Some(at)) => (at as i64).into(),
+            Some(None) => (-1).into(),
+            None => (-2).into(),
+        }
+    }
+}
+
+// "none" for a missing key
+impl CommandExecutor for Type {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        SimpleString::new(backend.key_type(&self.key).unwrap_or("none")).into()
+    }
+}
+
+// 1 when the key was moved, 0 when it's missing or the target database has it already
+impl CommandExecutor for Move {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.db == backend.db_index() {
+            return SimpleError::new("ERR source and destination objects are the same").into();
+        }
+        if self.db >= backend.databases() {
+            return SimpleError::new("ERR DB index is out of range").into();
+        }
+        (backend.move_key(&self.key, self.db) as i64).into()
+    }
+}
+
+impl CommandExecutor for SwapDb {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.swapdb(self.db1, self.db2) {
+            RESP_OK.clone()
+        } else {
+            SimpleError::new("ERR DB index is out of range").into()
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ObjectCommand {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let subcommand = match value.get(1) {
+            Some(RespFrame::BulkString(ref cmd)) => cmd.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "OBJECT command must have a subcommand".to_string(),
+                ))
+            }
+        };
+        match subcommand.as_slice() {
+            b"freq" => {
+                validate_command(&value, &["object", "freq"], 1)?;
+                let mut args = value.into_strings()?.into_iter().skip(2);
+                match args.next() {
+                    Some(key) => Ok(ObjectCommand::Freq { key }),
+                    _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+                }
+            }
+            b"help" => Ok(ObjectCommand::Help),
+            b"encoding" => {
+                validate_command(&value, &["object", "encoding"], 1)?;
+                let mut args = value.into_strings()?.into_iter().skip(2);
+                match args.next() {
+                    Some(key) => Ok(ObjectCommand::Encoding { key }),
+                    _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+                }
+            }
+            v => Err(CommandError::InvalidCommand(format!(
+                "Unknown OBJECT subcommand: {}",
+                String::from_utf8_lossy(v)
+            ))),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for MemoryCommand {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let subcommand = match value.get(1) {
+            Some(RespFrame::BulkString(ref cmd)) => cmd.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "MEMORY command must have a subcommand".to_string(),
+                ))
+            }
+        };
+        match subcommand.as_slice() {
+            b"help" => Ok(MemoryCommand::Help),
+            b"usage" => {
+                if value.len() != 3 && value.len() != 5 {
+                    return Err(CommandError::InvalidArgument(
+                        "MEMORY USAGE takes a key and an optional SAMPLES count".to_string(),
+                    ));
+                }
+                let mut args = value.into_strings()?.into_iter().skip(2);
+                let key = args.next().unwrap_or_default();
+                // same default as redis
+                let samples = match (args.next(), args.next()) {
+                    (Some(opt), Some(n)) if opt.eq_ignore_ascii_case("samples") => {
+                        n.parse::<usize>().map_err(|_| {
+                            CommandError::InvalidArgument("invalid SAMPLES".to_string())
+                        })?
+                    }
+                    (None, None) => 5,
+                    _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                };
+                Ok(MemoryCommand::Usage { key, samples })
+            }
+            v => Err(CommandError::InvalidCommand(format!(
+                "Unknown MEMORY subcommand: {}",
+                String::from_utf8_lossy(v)
+            ))),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::InvalidArgument(
+                "scan command must have a cursor".to_string(),
+            ));
+        }
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let cursor = args
+            .next()
+            .unwrap_or_default()
+            .parse::<usize>()
+            .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string()))?;
+        let (pattern, count) = parse_scan_options(args, "SCAN")?;
+        Ok(Scan {
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Keys {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["keys"], 1)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match args.next() {
+            Some(pattern) => Ok(Keys { pattern }),
+            _ => Err(CommandError::InvalidArgument("Invalid pattern".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Expire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["expire"], 2)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next()) {
+            (Some(key), Some(seconds)) => {
+                let seconds = seconds.parse::<i64>().map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "value is not an integer or out of range".to_string(),
+                    )
+                })?;
+                Ok(Expire { key, seconds })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or seconds".to_string(),
+            )),
+        }
+    }
+}
+
+impl Command {
+    /// EXPIRE and EXPIREAT as the PEXPIREAT of the deadline they resolve to, along with its RESP
+    /// encoding. None for other commands. The AOF records deadlines, so a replay doesn't
+    /// restart a time to live
+    pub fn absolute_expiry(&self, now_ms: u64) -> Option<(Command, Vec<u8>)> {
+        let (key, timestamp) = match self {
+            Command::Expire(cmd) => (
+                &cmd.key,
+                (now_ms as i64).saturating_add(cmd.seconds.saturating_mul(1000)),
+            ),
+            Command::ExpireAt(cmd) => (&cmd.key, cmd.timestamp.saturating_mul(1000)),
+            _ => return None,
+        };
+        let entry = RespArray::new([
+            BulkString::from("pexpireat").into(),
+            BulkString::from(key.as_str()).into(),
+            BulkString::from(timestamp.to_string()).into(),
+        ])
+        .encode();
+        let cmd = PExpireAt {
+            key: key.clone(),
+            timestamp,
+        };
+        Some((cmd.into(), entry))
+    }
+}
+
+impl TryFrom<RespArray> for PExpireAt {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pexpireat"], 2)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next()) {
+            (Some(key), Some(timestamp)) => {
+                let timestamp = timestamp.parse::<i64>().map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "value is not an integer or out of range".to_string(),
+                    )
+                })?;
+                Ok(PExpireAt { key, timestamp })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or timestamp".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ExpireAt {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["expireat"], 2)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next()) {
+            (Some(key), Some(timestamp)) => {
+                let timestamp = timestamp.parse::<i64>().map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "value is not an integer or out of range".to_string(),
+                    )
+                })?;
+                Ok(ExpireAt { key, timestamp })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or timestamp".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Ttl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ttl"], 1)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match args.next() {
+            Some(key) => Ok(Ttl { key }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ExpireTime {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["expiretime"], 1)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match args.next() {
+            Some(key) => Ok(ExpireTime { key }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PExpireTime {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pexpiretime"], 1)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match args.next() {
+            Some(key) => Ok(PExpireTime { key }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Type {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["type"], 1)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match args.next() {
+            Some(key) => Ok(Type { key }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Move {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["move"], 2)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next().map(|v| v.parse::<i64>())) {
+            (Some(key), Some(Ok(db))) => Ok(Move {
+                key,
+                db: usize::try_from(db).map_err(|_| {
+                    CommandError::InvalidArgument("DB index is out of range".to_string())
+                })?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "value is not an integer or out of range".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SwapDb {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["swapdb"], 2)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let mut index = |which: &str| match args.next().map(|v| v.parse::<i64>()) {
+            Some(Ok(index)) => usize::try_from(index)
+                .map_err(|_| CommandError::InvalidArgument("DB index is out of range".to_string())),
+            _ => Err(CommandError::InvalidArgument(format!(
+                "invalid {} DB index",
+                which
+            ))),
+        };
+        Ok(SwapDb {
+            db1: index("first")?,
+            db2: index("second")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::InvalidArgument(
+                "del command must have at least one key".to_string(),
+            ));
+        }
+
+        let keys = value.into_strings()?.into_iter().skip(1).collect();
+        Ok(Del { keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendConfig, MockClock, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_object_freq_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nobject\r\n$4\r\nFREQ\r\n$5\r\nhello\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ObjectCommand = frame.try_into()?;
+        assert!(matches!(result, ObjectCommand::Freq { key } if key == "hello"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_freq_command() {
+        let backend = Backend::new();
+        let freq = |key: &str| {
+            ObjectCommand::Freq {
+                key: key.to_string(),
+            }
+            .execute(&backend)
+        };
+        assert_eq!(freq("hello"), RespFrame::Null(RespNull));
+
+        backend.set("hello".to_string(), BulkString::from("world").into());
+        let RespFrame::Integer(before) = freq("hello") else {
+            panic!("OBJECT FREQ should return an integer");
+        };
+        for _ in 0..1000 {
+            backend.get("hello");
+        }
+        let RespFrame::Integer(after) = freq("hello") else {
+            panic!("OBJECT FREQ should return an integer");
+        };
+        assert!(after > before);
+    }
+
+    #[test]
+    fn test_scan_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\nscan\r\n$2\r\n20\r\n$5\r\nCOUNT\r\n$1\r\n5\r\n$5\r\nmatch\r\n$5\r\nuser:\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Scan = frame.try_into()?;
+        assert_eq!(result.cursor, 20);
+        assert_eq!(result.pattern, Some("user:".to_string()));
+        assert_eq!(result.count, 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_command() {
+        let backend = Backend::new();
+        backend.set("a".to_string(), BulkString::from("1").into());
+        backend.set("b".to_string(), BulkString::from("2").into());
+        backend.set("c".to_string(), BulkString::from("3").into());
+
+        let cmd = Scan {
+            cursor: 0,
+            pattern: None,
+            count: 2,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("2").into(),
+                RespArray::new([BulkString::from("a").into(), BulkString::from("b").into()]).into(),
+            ])
+            .into()
+        );
+
+        let cmd = Scan {
+            cursor: 2,
+            pattern: None,
+            count: 2,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("0").into(),
+                RespArray::new([BulkString::from("c").into()]).into(),
+            ])
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_scan_match_command() {
+        let backend = Backend::new();
+        for key in ["user:1", "user:2", "order:1"] {
+            backend.set(key.to_string(), BulkString::from("value").into());
+        }
+
+        let cmd = Scan {
+            cursor: 0,
+            pattern: Some("user:*".to_string()),
+            count: 10,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("0").into(),
+                RespArray::new([
+                    BulkString::from("user:1").into(),
+                    BulkString::from("user:2").into()
+                ])
+                .into(),
+            ])
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_keys_command_timeout() {
+        let backend = Backend::with_config(BackendConfig {
+            command_timeout: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        for i in 0..10_000 {
+            backend.set(format!("key:{}", i), BulkString::from("value").into());
+        }
+
+        let cmd = Keys {
+            pattern: "*".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR command timed out").into()
+        );
+    }
+
+    #[test]
+    fn test_del_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$3\r\ndel\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Del = frame.try_into()?;
+        assert_eq!(cmd.keys, vec!["a", "b", "c"]);
+
+        let backend = Backend::new();
+        backend.set("a".to_string(), BulkString::from("1").into());
+        backend.rpush("b".to_string(), [BulkString::from("2").into()]);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.key_type("a"), None);
+        assert_eq!(backend.key_type("b"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_encoding_hash_transition() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nOBJECT\r\n$8\r\nencoding\r\n$4\r\nuser\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ObjectCommand = frame.try_into()?;
+        assert!(matches!(result, ObjectCommand::Encoding { ref key } if key == "user"));
+
+        let backend = Backend::with_config(BackendConfig {
+            hash_max_listpack_entries: 4,
+            ..Default::default()
+        });
+        let encoding = || {
+            ObjectCommand::Encoding {
+                key: "user".to_string(),
+            }
+            .execute(&backend)
+        };
+        assert_eq!(encoding(), RespFrame::Null(RespNull));
+
+        for i in 0..4 {
+            backend.hset(
+                "user".to_string(),
+                format!("field{}", i),
+                BulkString::from("value").into(),
+            );
+        }
+        assert_eq!(encoding(), BulkString::from("listpack").into());
+
+        backend.hset(
+            "user".to_string(),
+            "field4".to_string(),
+            BulkString::from("value").into(),
+        );
+        assert_eq!(encoding(), BulkString::from("hashtable").into());
+
+        // overwriting existing fields doesn't convert it back
+        backend.hset(
+            "user".to_string(),
+            "field0".to_string(),
+            BulkString::from("other").into(),
+        );
+        assert_eq!(encoding(), BulkString::from("hashtable").into());
+
+        // a new hash under the same key starts small again
+        backend.del("user");
+        backend.hset(
+            "user".to_string(),
+            "field0".to_string(),
+            BulkString::from("value").into(),
+        );
+        assert_eq!(encoding(), BulkString::from("listpack").into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_usage_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nMEMORY\r\n$5\r\nusage\r\n$3\r\nkey\r\n$7\r\nSAMPLES\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: MemoryCommand = frame.try_into()?;
+        assert!(matches!(result, MemoryCommand::Usage { key, samples: 0 } if key == "key"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_usage_command() {
+        let backend = Backend::new();
+        let usage = |key: &str, samples: usize| {
+            let cmd = MemoryCommand::Usage {
+                key: key.to_string(),
+                samples,
+            };
+            match cmd.execute(&backend) {
+                RespFrame::Integer(size) => Some(size as usize),
+                _ => None,
+            }
+        };
+        assert_eq!(usage("missing", 5), None);
+
+        backend.set("expired".to_string(), BulkString::from("x").into());
+        backend.expire_at("expired", 1);
+        assert_eq!(usage("expired", 5), None);
+
+        backend.set(
+            "string".to_string(),
+            BulkString::new(vec![b'x'; 100]).into(),
+        );
+        let size = usage("string", 5).unwrap();
+        assert!(size > 100 && size < 1000);
+
+        for i in 0..100 {
+            backend.hset(
+                "hash".to_string(),
+                format!("field{:03}", i),
+                BulkString::new(vec![b'x'; 100]).into(),
+            );
+        }
+        // every field is the same size, sampling a few of them extrapolates to the exact total
+        let size = usage("hash", 0).unwrap();
+        assert!(size > 100 * 100 && size < 100 * 1000);
+        assert_eq!(usage("hash", 5), Some(size));
+    }
+
+    #[test]
+    fn test_expiretime_commands() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$11\r\npexpiretime\r\n$3\r\nkey\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let pexpiretime: PExpireTime = frame.try_into()?;
+        assert_eq!(pexpiretime.key, "key");
+
+        let clock = Arc::new(MockClock::new(1_700_000_000_500));
+        let backend = Backend::with_config(BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        let times = || {
+            let key = "key".to_string();
+            (
+                ExpireTime { key: key.clone() }.execute(&backend),
+                PExpireTime { key }.execute(&backend),
+            )
+        };
+        assert_eq!(times(), (RespFrame::Integer(-2), RespFrame::Integer(-2)));
+
+        backend.set("key".to_string(), BulkString::from("value").into());
+        assert_eq!(times(), (RespFrame::Integer(-1), RespFrame::Integer(-1)));
+
+        backend.expire("key", Duration::from_secs(10));
+        assert_eq!(
+            times(),
+            (
+                RespFrame::Integer(1_700_000_010),
+                RespFrame::Integer(1_700_000_010_500)
+            )
+        );
+        // the deadline is absolute, it doesn't move with the clock
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(times().1, RespFrame::Integer(1_700_000_010_500));
+        Ok(())
+    }
+
+    #[test]
+    fn test_expireat_then_expiretime_should_round_trip() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$8\r\nexpireat\r\n$3\r\nkey\r\n$10\r\n1700000100\r\n");
+        let expireat: ExpireAt = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(expireat.timestamp, 1_700_000_100);
+
+        let clock = Arc::new(MockClock::new(1_700_000_000_000));
+        let backend = Backend::with_config(BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        let expiretime = |key: &str| {
+            ExpireTime {
+                key: key.to_string(),
+            }
+            .execute(&backend)
+        };
+        backend.set("key".to_string(), BulkString::from("value").into());
+        backend.set("other".to_string(), BulkString::from("value").into());
+        assert_eq!(expiretime("other"), RespFrame::Integer(-1));
+
+        assert_eq!(expireat.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(expiretime("key"), RespFrame::Integer(1_700_000_100));
+        assert_eq!(
+            PExpireTime {
+                key: "key".to_string()
+            }
+            .execute(&backend),
+            RespFrame::Integer(1_700_000_100_000)
+        );
+
+        // a timestamp in the past deletes the key
+        let expireat = ExpireAt {
+            key: "other".to_string(),
+            timestamp: 1_600_000_000,
+        };
+        assert_eq!(expireat.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(expiretime("other"), RespFrame::Integer(-2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_should_resolve_to_an_absolute_pexpireat() -> Result<()> {
+        let frame = |args: &[&str]| {
+            RespArray::new(
+                args.iter()
+                    .map(|v| BulkString::from(*v).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+        let expire = Command::try_from(frame(&["expire", "key", "10"]))?;
+        let (cmd, entry) = expire.absolute_expiry(1_700_000_000_000).unwrap();
+        assert!(matches!(
+            cmd,
+            Command::PExpireAt(PExpireAt { ref key, timestamp: 1_700_000_010_000 }) if key == "key"
+        ));
+        assert_eq!(
+            entry,
+            frame(&["pexpireat", "key", "1700000010000"]).encode()
+        );
+
+        let expireat = Command::try_from(frame(&["expireat", "key", "1700000100"]))?;
+        let (_, entry) = expireat.absolute_expiry(0).unwrap();
+        assert_eq!(
+            entry,
+            frame(&["pexpireat", "key", "1700000100000"]).encode()
+        );
+
+        let set = Command::try_from(frame(&["set", "key", "value"]))?;
+        assert!(set.absolute_expiry(0).is_none());
+
+        let pexpireat: PExpireAt = frame(&["pexpireat", "key", "1700000000500"]).try_into()?;
+        let clock = Arc::new(MockClock::new(1_700_000_000_000));
+        let backend = Backend::with_config(BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        backend.set("key".to_string(), BulkString::from("value").into());
+        assert_eq!(pexpireat.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.expire_time("key"), Some(Some(1_700_000_000_500)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_and_ttl_commands() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nexpire\r\n$3\r\nkey\r\n$2\r\n10\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let expire: Expire = frame.try_into()?;
+        assert_eq!(expire.key, "key");
+        assert_eq!(expire.seconds, 10);
+
+        let clock = Arc::new(MockClock::new(0));
+        let backend = Backend::with_config(BackendConfig {
+            clock: clock.clone(),
+            ..Default::default()
+        });
+        let ttl = || {
+            Ttl {
+                key: "key".to_string(),
+            }
+            .execute(&backend)
+        };
+        assert_eq!(expire.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(ttl(), RespFrame::Integer(-2));
+
+        backend.set("key".to_string(), BulkString::from("value").into());
+        assert_eq!(ttl(), RespFrame::Integer(-1));
+        let expire = Expire {
+            key: "key".to_string(),
+            seconds: 10,
+        };
+        assert_eq!(expire.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(ttl(), RespFrame::Integer(10));
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(ttl(), RespFrame::Integer(10));
+        clock.advance(Duration::from_millis(9500));
+        assert_eq!(ttl(), RespFrame::Integer(-2));
+
+        // a time to live that's already over deletes the key
+        backend.set("key".to_string(), BulkString::from("value").into());
+        let expire = Expire {
+            key: "key".to_string(),
+            seconds: -1,
+        };
+        assert_eq!(expire.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.get("key"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_help() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$6\r\nobject\r\n$4\r\nhelp\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: ObjectCommand = frame.try_into()?;
+
+        let RespFrame::Array(lines) = cmd.execute(&Backend::new()) else {
+            panic!("OBJECT HELP should return an array");
+        };
+        assert_eq!(lines.len(), OBJECT_HELP.len() + 3);
+        assert_eq!(
+            lines[0],
+            RespFrame::from("OBJECT <subcommand> [<arg> [value] [opt] ...]. Subcommands are:")
+        );
+        assert_eq!(lines[1], RespFrame::from("ENCODING <key>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nmove\r\n$3\r\nkey\r\n$1\r\n2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Move = frame.try_into()?;
+        assert_eq!((cmd.key.as_str(), cmd.db), ("key", 2));
+
+        let backend = Backend::new();
+        backend.hset(
+            "key".to_string(),
+            "field".to_string(),
+            BulkString::from("value").into(),
+        );
+        backend.expire("key", Duration::from_secs(100));
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let db2 = backend.select(2).unwrap();
+        assert_eq!(backend.key_type("key"), None);
+        assert_eq!(
+            db2.hget("key", "field"),
+            Some(BulkString::from("value").into())
+        );
+        assert!(matches!(db2.ttl("key"), Some(Some(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_command_should_not_overwrite() {
+        let backend = Backend::new();
+        let db1 = backend.select(1).unwrap();
+        backend.set("key".to_string(), BulkString::from("db0").into());
+        db1.set("key".to_string(), BulkString::from("db1").into());
+
+        let cmd = |key: &str, db| {
+            Move {
+                key: key.to_string(),
+                db,
+            }
+            .execute(&backend)
+        };
+        assert_eq!(cmd("key", 1), RespFrame::Integer(0));
+        assert_eq!(backend.get("key"), Some(BulkString::from("db0").into()));
+        assert_eq!(db1.get("key"), Some(BulkString::from("db1").into()));
+        assert_eq!(cmd("missing", 1), RespFrame::Integer(0));
+        assert!(matches!(cmd("key", 0), RespFrame::Error(_)));
+        assert!(matches!(cmd("key", 16), RespFrame::Error(_)));
+
+        // a key of another type in the target is a conflict too, the value stays in place
+        db1.del("key");
+        db1.hset(
+            "key".to_string(),
+            "field".to_string(),
+            RespFrame::Integer(1),
+        );
+        backend.expire("key", std::time::Duration::from_secs(10));
+        assert_eq!(cmd("key", 1), RespFrame::Integer(0));
+        assert_eq!(backend.get("key"), Some(BulkString::from("db0").into()));
+        assert!(backend.ttl("key").is_some_and(|ttl| ttl.is_some()));
+        assert_eq!(db1.key_type("key"), Some("hash"));
+    }
+
+    #[test]
+    fn test_concurrent_moves_should_move_the_key_once() {
+        let backend = Backend::new();
+        for _ in 0..20 {
+            backend.flushall();
+            let sources = (1..16)
+                .map(|i| {
+                    let db = backend.select(i).unwrap();
+                    db.set("key".to_string(), BulkString::from(i.to_string()).into());
+                    db
+                })
+                .collect::<Vec<_>>();
+            let moved = std::thread::scope(|s| {
+                let handles = sources
+                    .iter()
+                    .map(|db| s.spawn(|| db.move_key("key", 0)))
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .filter_map(|h| h.join().ok())
+                    .filter(|moved| *moved)
+                    .count()
+            });
+            assert_eq!(moved, 1);
+            // no value was lost, 14 sources kept theirs and the target holds the moved one
+            let kept = sources.iter().filter(|db| db.get("key").is_some()).count();
+            assert_eq!(kept, 14);
+            assert!(backend.get("key").is_some());
+        }
+    }
+}
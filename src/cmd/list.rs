@@ -0,0 +1,518 @@
+use super::{
+    extract_args, is_other_type, validate_command, CommandExecutor, LInsert, LMove, LPush, LPushX,
+    RPop, RPopLPush, RPush, RPushX, RESP_WRONGTYPE,
+};
+use crate::{cmd::CommandError, Backend, ListSide, RespArray, RespFrame, RespNull};
+
+impl CommandExecutor for LMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        // checked up front, the element must not be popped when it can't be pushed
+        match backend.lmove(&self.src, &self.dst, self.src_side, self.dst_side) {
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(_) => RESP_WRONGTYPE.clone(),
+        }
+    }
+}
+
+impl CommandExecutor for RPopLPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lmove(&self.src, &self.dst, ListSide::Right, ListSide::Left) {
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(_) => RESP_WRONGTYPE.clone(),
+        }
+    }
+}
+
+impl CommandExecutor for RPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "list") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.rpush(self.key, self.values) as i64).into()
+    }
+}
+
+impl CommandExecutor for LPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "list") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.lpush(self.key, self.values) as i64).into()
+    }
+}
+
+impl CommandExecutor for LPushX {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "list") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.pushx(&self.key, ListSide::Left, self.values) as i64).into()
+    }
+}
+
+impl CommandExecutor for RPushX {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "list") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.pushx(&self.key, ListSide::Right, self.values) as i64).into()
+    }
+}
+
+impl CommandExecutor for RPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "list") {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.pop(&self.key, ListSide::Right) {
+            Some(value) => value,
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+// the new length of the list, 0 for a missing key and -1 when the pivot isn't found
+impl CommandExecutor for LInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "list") {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.linsert(&self.key, self.before, &self.pivot, self.value) {
+            Some(len) => (len as i64).into(),
+            None => (-1).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lmove"], 4)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next(), args.next(), args.next()) {
+            (Some(src), Some(dst), Some(src_side), Some(dst_side)) => Ok(LMove {
+                src,
+                dst,
+                src_side: parse_side(&src_side)?,
+                dst_side: parse_side(&dst_side)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid source, destination or side".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for RPopLPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["rpoplpush"], 2)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next()) {
+            (Some(src), Some(dst)) => Ok(RPopLPush { src, dst }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid source or destination".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for RPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value, "rpush")?;
+        Ok(RPush { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value, "lpush")?;
+        Ok(LPush { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPushX {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value, "lpushx")?;
+        Ok(LPushX { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for RPushX {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value, "rpushx")?;
+        Ok(RPushX { key, values })
+    }
+}
+
+// <command> key value [value ...]
+fn parse_push(value: RespArray, command: &str) -> Result<(String, Vec<RespFrame>), CommandError> {
+    if value.len() < 3 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have a key and at least one value",
+            command
+        )));
+    }
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    match args.next() {
+        Some(RespFrame::BulkString(key)) => Ok((String::from_utf8(key.0)?, args.collect())),
+        _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+impl TryFrom<RespArray> for RPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["rpop"], 1)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match args.next() {
+            Some(key) => Ok(RPop { key }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+// LINSERT key BEFORE|AFTER pivot element
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(RespFrame::BulkString(position)),
+                Some(pivot),
+                Some(value),
+            ) => {
+                let before = match position.to_ascii_lowercase().as_slice() {
+                    b"before" => true,
+                    b"after" => false,
+                    _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                };
+                Ok(LInsert {
+                    key: String::from_utf8(key.0)?,
+                    before,
+                    pivot,
+                    value,
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, position, pivot or element".to_string(),
+            )),
+        }
+    }
+}
+
+fn parse_side(side: &str) -> Result<ListSide, CommandError> {
+    match side.to_ascii_lowercase().as_str() {
+        "left" => Ok(ListSide::Left),
+        "right" => Ok(ListSide::Right),
+        _ => Err(CommandError::InvalidArgument(format!(
+            "Invalid side: {}, expect LEFT or RIGHT",
+            side
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, BulkString, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn list(backend: &Backend, key: &str) -> Vec<RespFrame> {
+        backend
+            .db()
+            .lmap
+            .get(key)
+            .map(|v| v.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_linsert_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$7\r\nlinsert\r\n$4\r\nlist\r\n$6\r\nBEFORE\r\n$1\r\nb\r\n$1\r\nx\r\n",
+        );
+        let cmd: LInsert = RespArray::decode(&mut buf)?.try_into()?;
+        assert!(cmd.before);
+
+        let backend = Backend::new();
+        let values = |v: &[&str]| {
+            v.iter()
+                .map(|v| BulkString::from(*v).into())
+                .collect::<Vec<RespFrame>>()
+        };
+        backend.rpush("list".to_string(), values(&["a", "b", "b"]));
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(4));
+        assert_eq!(list(&backend, "list"), values(&["a", "x", "b", "b"]));
+
+        let linsert = |key: &str, before, pivot: &str| {
+            LInsert {
+                key: key.to_string(),
+                before,
+                pivot: BulkString::from(pivot).into(),
+                value: BulkString::from("y").into(),
+            }
+            .execute(&backend)
+        };
+        // next to the first occurrence only
+        assert_eq!(linsert("list", false, "b"), RespFrame::Integer(5));
+        assert_eq!(list(&backend, "list"), values(&["a", "x", "b", "y", "b"]));
+        assert_eq!(linsert("list", true, "missing"), RespFrame::Integer(-1));
+        assert_eq!(linsert("missing", true, "a"), RespFrame::Integer(0));
+        assert!(list(&backend, "missing").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nlmove\r\n$3\r\nsrc\r\n$3\r\ndst\r\n$4\r\nLEFT\r\n$5\r\nright\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: LMove = frame.try_into()?;
+        assert_eq!(result.src, "src");
+        assert_eq!(result.dst, "dst");
+        assert_eq!(result.src_side, ListSide::Left);
+        assert_eq!(result.dst_side, ListSide::Right);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove_rotate_single_list() {
+        let backend = Backend::new();
+        backend.rpush(
+            "list".to_string(),
+            ["a", "b", "c"].map(|v| BulkString::from(v).into()),
+        );
+
+        let cmd = LMove {
+            src: "list".to_string(),
+            dst: "list".to_string(),
+            src_side: ListSide::Left,
+            dst_side: ListSide::Right,
+        };
+        let result = cmd.execute(&backend);
+        assert_eq!(result, BulkString::from("a").into());
+        assert_eq!(
+            list(&backend, "list"),
+            ["b", "c", "a"].map(|v| BulkString::from(v).into())
+        );
+    }
+
+    #[test]
+    fn test_rpoplpush_command() {
+        let backend = Backend::new();
+        backend.rpush("src".to_string(), [BulkString::from("a").into()]);
+        backend.rpush("dst".to_string(), [BulkString::from("b").into()]);
+
+        let cmd = RPopLPush {
+            src: "src".to_string(),
+            dst: "dst".to_string(),
+        };
+        let result = cmd.execute(&backend);
+        assert_eq!(result, BulkString::from("a").into());
+        assert!(!backend.db().lmap.contains_key("src"));
+        assert_eq!(
+            list(&backend, "dst"),
+            ["a", "b"].map(|v| BulkString::from(v).into())
+        );
+
+        let cmd = RPopLPush {
+            src: "src".to_string(),
+            dst: "dst".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+    }
+
+    #[test]
+    fn test_rpush_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nrpush\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: RPush = frame.try_into()?;
+        let backend = Backend::new();
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(
+            list(&backend, "list"),
+            vec![BulkString::from("a").into(), BulkString::from("b").into()]
+        );
+
+        backend.set("string".to_string(), BulkString::from("x").into());
+        let cmd = RPush {
+            key: "string".to_string(),
+            values: vec![BulkString::from("a").into()],
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        assert_eq!(backend.key_type("string"), Some("string"));
+        assert_eq!(backend.list_len("string"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove_to_other_type_should_keep_element() {
+        let backend = Backend::new();
+        backend.rpush("src".to_string(), [BulkString::from("a").into()]);
+        backend.set("string".to_string(), BulkString::from("v").into());
+
+        let cmd = RPopLPush {
+            src: "src".to_string(),
+            dst: "string".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        assert_eq!(list(&backend, "src"), [BulkString::from("a").into()]);
+        assert!(!backend.db().lmap.contains_key("string"));
+
+        let cmd = LMove {
+            src: "string".to_string(),
+            dst: "src".to_string(),
+            src_side: ListSide::Left,
+            dst_side: ListSide::Left,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+    }
+
+    #[test]
+    fn test_concurrent_lmoves_should_keep_every_element() {
+        let backend = Backend::new();
+        let keys = (0..8).map(|i| format!("list:{}", i)).collect::<Vec<_>>();
+        for key in &keys {
+            let values = (0..100).map(|i| BulkString::from(i.to_string()).into());
+            backend.rpush(key.clone(), values.collect::<Vec<RespFrame>>());
+        }
+        // every pair of lists, in both directions, so moves cross each other's locks
+        std::thread::scope(|s| {
+            for (i, src) in keys.iter().enumerate() {
+                for dst in keys.iter().skip(i) {
+                    let backend = backend.clone();
+                    s.spawn(move || {
+                        for _ in 0..200 {
+                            backend
+                                .lmove(src, dst, ListSide::Left, ListSide::Right)
+                                .unwrap();
+                            backend
+                                .lmove(dst, src, ListSide::Right, ListSide::Left)
+                                .unwrap();
+                        }
+                    });
+                }
+            }
+        });
+        let total = keys.iter().map(|key| backend.list_len(key)).sum::<usize>();
+        assert_eq!(total, 800);
+    }
+
+    #[test]
+    fn test_lpush_and_rpop_commands() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nlpush\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: LPush = frame.try_into()?;
+
+        let backend = Backend::new();
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.list_len("list"), 2);
+        assert_eq!(
+            list(&backend, "list"),
+            [BulkString::from("b").into(), BulkString::from("a").into()]
+        );
+
+        let rpop = || {
+            RPop {
+                key: "list".to_string(),
+            }
+            .execute(&backend)
+        };
+        assert_eq!(rpop(), BulkString::from("a").into());
+        assert_eq!(backend.list_len("list"), 1);
+        assert_eq!(rpop(), BulkString::from("b").into());
+        // popping the last element deletes the key
+        assert_eq!(backend.list_len("list"), 0);
+        assert_eq!(backend.key_type("list"), None);
+        assert_eq!(rpop(), RespFrame::Null(RespNull));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pushx_should_only_push_to_existing_lists() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nrpushx\r\n$4\r\nlist\r\n$1\r\nb\r\n$1\r\nc\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: RPushX = frame.try_into()?;
+
+        let backend = Backend::new();
+        let value = |v: &str| RespFrame::from(BulkString::from(v));
+        backend.rpush("list".to_string(), [value("a")]);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+
+        let cmd = LPushX {
+            key: "list".to_string(),
+            values: vec![value("y"), value("z")],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(5));
+        assert_eq!(
+            list(&backend, "list"),
+            [value("z"), value("y"), value("a"), value("b"), value("c")]
+        );
+
+        for cmd in [
+            Command::from(LPushX {
+                key: "missing".to_string(),
+                values: vec![value("a")],
+            }),
+            Command::from(RPushX {
+                key: "missing".to_string(),
+                values: vec![value("a")],
+            }),
+        ] {
+            assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        }
+        assert_eq!(backend.key_type("missing"), None);
+
+        backend.set("string".to_string(), value("v"));
+        let cmd = RPushX {
+            key: "string".to_string(),
+            values: vec![value("a")],
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_len_after_push_and_pop_sequence() {
+        let backend = Backend::new();
+        let value = |v: &str| RespFrame::from(BulkString::from(v));
+        backend.lpush("list".to_string(), [value("a"), value("b"), value("c")]);
+        backend.rpush("list".to_string(), [value("d")]);
+        assert_eq!(backend.list_len("list"), 4);
+        assert_eq!(backend.pop("list", ListSide::Right), Some(value("d")));
+        assert_eq!(backend.pop("list", ListSide::Left), Some(value("c")));
+        assert_eq!(backend.list_len("list"), 2);
+        assert_eq!(backend.list_len("missing"), 0);
+
+        backend.set("string".to_string(), value("x"));
+        let cmd = LPush {
+            key: "string".to_string(),
+            values: vec![value("a")],
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        assert_eq!(backend.list_len("string"), 0);
+    }
+}
@@ -1,9 +1,16 @@
-use super::{extract_args, validate_command, CommandExecutor, Set, RESP_OK};
+use super::{
+    extract_args, is_other_type, validate_command, Append, CommandExecutor, DecrBy, IncrBy, Lcs,
+    MSet, Set, RESP_OK, RESP_WRONGTYPE,
+};
 use crate::{
+    backend::{AppendError, IncrError},
     cmd::{CommandError, Get},
-    RespArray, RespFrame, RespNull,
+    Backend, BulkString, RespArray, RespFrame, RespNull, SimpleError,
 };
 
+// the DP table of LCS takes 4 bytes per cell, refuse inputs that would need more than 256MB
+const LCS_MAX_CELLS: usize = 64 * 1024 * 1024;
+
 impl CommandExecutor for Get {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         match backend.get(&self.key) {
@@ -15,21 +22,192 @@ impl CommandExecutor for Get {
 
 impl CommandExecutor for Set {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if exceeds_max_string_size(backend, &self.value) {
+            return string_too_large();
+        }
         backend.set(self.key, self.value);
         RESP_OK.clone()
     }
 }
 
+impl CommandExecutor for MSet {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        // all or nothing, like redis checks every value before setting any
+        if self
+            .pairs
+            .iter()
+            .any(|(_, value)| exceeds_max_string_size(backend, value))
+        {
+            return string_too_large();
+        }
+        for (key, value) in self.pairs {
+            backend.set(key, value);
+        }
+        RESP_OK.clone()
+    }
+}
+
+// a missing key is created as an empty string first, a key of another type is WRONGTYPE
+impl CommandExecutor for Append {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.append(self.key, &self.value) {
+            Ok(len) => (len as i64).into(),
+            Err(AppendError::WrongType) => RESP_WRONGTYPE.clone(),
+            Err(e) => CommandError::InvalidArgument(e.to_string()).into(),
+        }
+    }
+}
+
+fn exceeds_max_string_size(backend: &Backend, value: &RespFrame) -> bool {
+    matches!(value, RespFrame::BulkString(s) if s.len() > backend.config.max_string_size)
+}
+
+fn string_too_large() -> RespFrame {
+    CommandError::InvalidArgument(AppendError::TooLarge.to_string()).into()
+}
+
+impl CommandExecutor for IncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        incr_by(backend, self.key, self.delta)
+    }
+}
+
+impl CommandExecutor for DecrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.delta.checked_neg() {
+            Some(delta) => incr_by(backend, self.key, delta),
+            None => CommandError::InvalidArgument("decrement would overflow".to_string()).into(),
+        }
+    }
+}
+
+fn incr_by(backend: &Backend, key: String, delta: i64) -> RespFrame {
+    match backend.incr_by(key, delta) {
+        Ok(value) => value.into(),
+        Err(IncrError::WrongType) => RESP_WRONGTYPE.clone(),
+        Err(e) => CommandError::InvalidArgument(e.to_string()).into(),
+    }
+}
+
+impl CommandExecutor for Lcs {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (a, b) = match (
+            lcs_value(backend, &self.key1),
+            lcs_value(backend, &self.key2),
+        ) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return RESP_WRONGTYPE.clone(),
+        };
+
+        if self.len_only && !self.with_matches {
+            return (lcs_len(&a, &b) as i64).into();
+        }
+        if (a.len() + 1).saturating_mul(b.len() + 1) > LCS_MAX_CELLS {
+            return SimpleError::new("ERR Insufficient memory, strings are too long for LCS")
+                .into();
+        }
+
+        let (lcs, matches) = lcs_matches(&a, &b);
+        if !self.with_matches {
+            return BulkString::new(lcs).into();
+        }
+        // ["matches", [[[a_start, a_end], [b_start, b_end]], ...], "len", <len>]
+        let range = |(start, end): (usize, usize)| {
+            RespArray::new([(start as i64).into(), (end as i64).into()]).into()
+        };
+        let matches = matches
+            .into_iter()
+            .map(|(a, b)| RespArray::new([range(a), range(b)]).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new([
+            BulkString::from("matches").into(),
+            RespArray::new(matches).into(),
+            BulkString::from("len").into(),
+            (lcs.len() as i64).into(),
+        ])
+        .into()
+    }
+}
+
+// a missing key is an empty string, None for a value that isn't a string
+fn lcs_value(backend: &Backend, key: &str) -> Option<Vec<u8>> {
+    if is_other_type(backend, key, "string") {
+        return None;
+    }
+    match backend.get(key) {
+        Some(RespFrame::BulkString(s)) => Some(s.0),
+        Some(_) => None,
+        None => Some(vec![]),
+    }
+}
+
+// only the length is needed, two rows of the table are enough
+fn lcs_len(a: &[u8], b: &[u8]) -> u32 {
+    let mut prev = vec![0u32; b.len() + 1];
+    let mut cur = vec![0u32; b.len() + 1];
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            cur[j] = if a[i - 1] == b[j - 1] {
+                prev[j - 1] + 1
+            } else {
+                prev[j].max(cur[j - 1])
+            };
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+type LcsRange = (usize, usize);
+
+// the LCS and the inclusive ranges of the matches in both strings, from the last match to the first
+fn lcs_matches(a: &[u8], b: &[u8]) -> (Vec<u8>, Vec<(LcsRange, LcsRange)>) {
+    let cols = b.len() + 1;
+    let mut table = vec![0u32; (a.len() + 1) * cols];
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            table[i * cols + j] = if a[i - 1] == b[j - 1] {
+                table[(i - 1) * cols + j - 1] + 1
+            } else {
+                table[(i - 1) * cols + j].max(table[i * cols + j - 1])
+            };
+        }
+    }
+
+    let mut lcs = Vec::with_capacity(table[a.len() * cols + b.len()] as usize);
+    let mut matches: Vec<(LcsRange, LcsRange)> = Vec::new();
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 && j > 0 {
+        if a[i - 1] == b[j - 1] {
+            lcs.push(a[i - 1]);
+            match matches.last_mut() {
+                // extend the current range while the match is contiguous in both strings
+                Some((ra, rb)) if ra.0 == i && rb.0 == j => {
+                    ra.0 -= 1;
+                    rb.0 -= 1;
+                }
+                _ => matches.push(((i - 1, i - 1), (j - 1, j - 1))),
+            }
+            i -= 1;
+            j -= 1;
+        } else if table[(i - 1) * cols + j] > table[i * cols + j - 1] {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    lcs.reverse();
+    (lcs, matches)
+}
+
 impl TryFrom<RespArray> for Get {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
         validate_command(&value, &["get"], 1)?;
 
-        let mut args = extract_args(value, 1)?.into_iter();
+        let mut args = value.into_strings()?.into_iter().skip(1);
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(Get {
-                key: String::from_utf8(key.0)?,
-            }),
+            Some(key) => Ok(Get { key }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
@@ -53,10 +231,118 @@ impl TryFrom<RespArray> for Set {
     }
 }
 
+impl TryFrom<RespArray> for Append {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["append"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(value))) => Ok(Append {
+                key: String::from_utf8(key.0)?,
+                value: value.0,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for IncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["incrby"], 2)?;
+        let (key, delta) = parse_delta(value)?;
+        Ok(IncrBy { key, delta })
+    }
+}
+
+impl TryFrom<RespArray> for DecrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["decrby"], 2)?;
+        let (key, delta) = parse_delta(value)?;
+        Ok(DecrBy { key, delta })
+    }
+}
+
+// <key> <delta>
+fn parse_delta(value: RespArray) -> Result<(String, i64), CommandError> {
+    let mut args = value.into_strings()?.into_iter().skip(1);
+    match (args.next(), args.next()) {
+        (Some(key), Some(delta)) => {
+            let delta = delta.parse::<i64>().map_err(|_| {
+                CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+            })?;
+            Ok((key, delta))
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid key or delta".to_string(),
+        )),
+    }
+}
+
+impl TryFrom<RespArray> for MSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // every pair is checked before MSET runs, a malformed one never sets the pairs before it
+        if value.len() < 3 || value.len().is_multiple_of(2) {
+            return Err(CommandError::WrongNumberOfArguments("mset".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut pairs = Vec::new();
+        while let (Some(key), Some(value)) = (args.next(), args.next()) {
+            match key {
+                RespFrame::BulkString(key) => pairs.push((String::from_utf8(key.0)?, value)),
+                _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+            }
+        }
+        Ok(MSet { pairs })
+    }
+}
+
+impl TryFrom<RespArray> for Lcs {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 3 {
+            return Err(CommandError::InvalidArgument(
+                "lcs command must have two keys".to_string(),
+            ));
+        }
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let (key1, key2) = (
+            args.next().unwrap_or_default(),
+            args.next().unwrap_or_default(),
+        );
+        let mut cmd = Lcs {
+            key1,
+            key2,
+            len_only: false,
+            with_matches: false,
+        };
+        for arg in args {
+            match arg.to_ascii_lowercase().as_str() {
+                "len" => cmd.len_only = true,
+                "idx" => cmd.with_matches = true,
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Invalid lcs option: {}",
+                        arg
+                    )))
+                }
+            }
+        }
+        Ok(cmd)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Backend, RespDecode};
+    use crate::{Backend, BackendConfig, RespDecode};
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -105,4 +391,312 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_mset_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*5\r\n$4\r\nmset\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: MSet = frame.try_into()?;
+        let backend = Backend::new();
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.get("a"), Some(RespFrame::BulkString(b"1".into())));
+        assert_eq!(backend.get("b"), Some(RespFrame::BulkString(b"2".into())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mset_odd_arguments_should_write_nothing() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nmset\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+
+        let backend = Backend::new();
+        let reply = match crate::cmd::Command::try_from(frame) {
+            Ok(cmd) => cmd.execute(&backend),
+            Err(e) => e.into(),
+        };
+        assert_eq!(
+            reply,
+            SimpleError::new("ERR wrong number of arguments for 'mset' command").into()
+        );
+        assert_eq!(backend.get("a"), None);
+        assert_eq!(backend.all_keys().count(), 0);
+
+        // the arity in the command table is checked before the arguments are parsed
+        let frame = RespArray::new([
+            BulkString::from("GET").into(),
+            BulkString::from("a").into(),
+            BulkString::from("b").into(),
+        ]);
+        assert!(matches!(
+            crate::cmd::Command::try_from(frame),
+            Err(CommandError::WrongNumberOfArguments(name)) if name == "get"
+        ));
+        Ok(())
+    }
+
+    fn lcs_backend() -> Backend {
+        let backend = Backend::new();
+        backend.set("key1".to_string(), BulkString::from("ohmytext").into());
+        backend.set("key2".to_string(), BulkString::from("mynewtext").into());
+        backend
+    }
+
+    #[test]
+    fn test_lcs_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\nlcs\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Lcs = frame.try_into()?;
+        assert_eq!(
+            cmd.execute(&lcs_backend()),
+            BulkString::from("mytext").into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcs_len_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$3\r\nLCS\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n$3\r\nLEN\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Lcs = frame.try_into()?;
+        assert_eq!(cmd.execute(&lcs_backend()), RespFrame::Integer(6));
+
+        let cmd = Lcs {
+            key1: "key1".to_string(),
+            key2: "missing".to_string(),
+            len_only: true,
+            with_matches: false,
+        };
+        assert_eq!(cmd.execute(&lcs_backend()), RespFrame::Integer(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcs_with_other_type_should_fail() {
+        let backend = lcs_backend();
+        backend.rpush("list".to_string(), [BulkString::from("mytext").into()]);
+        let cmd = Lcs {
+            key1: "key1".to_string(),
+            key2: "list".to_string(),
+            len_only: true,
+            with_matches: false,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+    }
+
+    #[test]
+    fn test_lcs_idx_command() {
+        let cmd = Lcs {
+            key1: "key1".to_string(),
+            key2: "key2".to_string(),
+            len_only: false,
+            with_matches: true,
+        };
+        let range = |a: i64, b: i64| RespFrame::from(RespArray::new([a.into(), b.into()]));
+        // same reply as the example in the redis docs
+        let expected = RespArray::new([
+            BulkString::from("matches").into(),
+            RespArray::new([
+                RespArray::new([range(4, 7), range(5, 8)]).into(),
+                RespArray::new([range(2, 3), range(0, 1)]).into(),
+            ])
+            .into(),
+            BulkString::from("len").into(),
+            6.into(),
+        ]);
+        assert_eq!(cmd.execute(&lcs_backend()), expected.into());
+    }
+
+    #[test]
+    fn test_append_to_missing_key_should_create_it() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nappend\r\n$3\r\nkey\r\n$5\r\nhello\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Append = frame.try_into()?;
+        let backend = Backend::new();
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(5));
+        assert_eq!(backend.get("key"), Some(BulkString::from("hello").into()));
+
+        let cmd = Append {
+            key: "key".to_string(),
+            value: b" world".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(11));
+        assert_eq!(
+            backend.get("key"),
+            Some(BulkString::from("hello world").into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_writes_should_respect_max_string_size() {
+        let backend = Backend::with_config(BackendConfig {
+            max_string_size: 8,
+            ..Default::default()
+        });
+        let too_large =
+            RespFrame::Error(SimpleError::new("ERR string exceeds maximum allowed size"));
+
+        backend.set("key".to_string(), BulkString::from("hello").into());
+        let append = |value: &[u8]| Append {
+            key: "key".to_string(),
+            value: value.to_vec(),
+        };
+        assert_eq!(append(b" world").execute(&backend), too_large);
+        assert_eq!(backend.get("key"), Some(BulkString::from("hello").into()));
+        assert_eq!(append(b"!!!").execute(&backend), RespFrame::Integer(8));
+
+        let cmd = Set {
+            key: "other".to_string(),
+            value: BulkString::from("123456789").into(),
+        };
+        assert_eq!(cmd.execute(&backend), too_large);
+        assert_eq!(backend.get("other"), None);
+        let cmd = Append {
+            key: "other".to_string(),
+            value: b"123456789".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), too_large);
+        assert_eq!(backend.key_type("other"), None);
+
+        let cmd = MSet {
+            pairs: vec![
+                ("a".to_string(), BulkString::from("1").into()),
+                ("b".to_string(), BulkString::from("123456789").into()),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), too_large);
+        assert_eq!(backend.get("a"), None);
+    }
+
+    #[test]
+    fn test_append_to_other_type_should_fail() {
+        let backend = Backend::new();
+        backend.hset(
+            "hash".to_string(),
+            "field".to_string(),
+            BulkString::from("value").into(),
+        );
+        backend.rpush("list".to_string(), [BulkString::from("a").into()]);
+        backend.set("number".to_string(), 42.into());
+
+        for key in ["hash", "list", "number"] {
+            let cmd = Append {
+                key: key.to_string(),
+                value: b"x".to_vec(),
+            };
+            assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        }
+        assert_eq!(backend.get("hash"), None);
+        assert_eq!(backend.key_type("hash"), Some("hash"));
+        assert_eq!(backend.get("number"), Some(42.into()));
+    }
+
+    #[test]
+    fn test_string_writes_to_other_type_should_fail_in_the_backend() {
+        let backend = Backend::new();
+        backend.sadd("set".to_string(), ["a".to_string()]);
+        assert_eq!(
+            backend.append("set".to_string(), b"x"),
+            Err(AppendError::WrongType)
+        );
+        assert_eq!(
+            backend.incr_by("set".to_string(), 1),
+            Err(IncrError::WrongType)
+        );
+        assert_eq!(backend.get("set"), None);
+        assert_eq!(backend.key_type("set"), Some("set"));
+
+        let cmd = IncrBy {
+            key: "set".to_string(),
+            delta: 1,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+    }
+
+    #[test]
+    fn test_incrby_and_decrby_commands() -> Result<()> {
+        let backend = Backend::new();
+        let frame = RespArray::new([
+            BulkString::from("incrby").into(),
+            BulkString::from("counter").into(),
+            BulkString::from("10").into(),
+        ]);
+        let cmd: IncrBy = frame.try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(10));
+
+        let frame = RespArray::new([
+            BulkString::from("decrby").into(),
+            BulkString::from("counter").into(),
+            BulkString::from("-5").into(),
+        ]);
+        let cmd: DecrBy = frame.try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(15));
+        let cmd = DecrBy {
+            key: "counter".to_string(),
+            delta: 20,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-5));
+        assert_eq!(backend.get("counter"), Some(BulkString::from("-5").into()));
+
+        backend.set("text".to_string(), BulkString::from("abc").into());
+        let cmd = IncrBy {
+            key: "text".to_string(),
+            delta: 1,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR value is not an integer or out of range").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_incrby_overflow_should_fail() {
+        let backend = Backend::new();
+        backend.set(
+            "n".to_string(),
+            BulkString::from((i64::MAX - 1).to_string()).into(),
+        );
+        let cmd = IncrBy {
+            key: "n".to_string(),
+            delta: 1,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(i64::MAX));
+
+        let cmd = IncrBy {
+            key: "n".to_string(),
+            delta: 1,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR increment or decrement would overflow").into()
+        );
+        // the value is left untouched
+        assert_eq!(
+            backend.get("n"),
+            Some(BulkString::from(i64::MAX.to_string()).into())
+        );
+
+        let cmd = DecrBy {
+            key: "n".to_string(),
+            delta: i64::MIN,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR decrement would overflow").into()
+        );
+    }
 }
@@ -1,14 +1,32 @@
+mod bitmap;
+mod config;
+mod connection;
 mod hmap;
+mod hyperloglog;
+mod keyspace;
+mod list;
 mod map;
+mod pubsub;
+mod server;
+mod set;
+mod spec;
+mod zset;
 
-use crate::{Backend, RespArray, RespError, RespFrame, SimpleString};
+pub use self::spec::{lookup_command, CommandSpec, COMMAND_TABLE};
+
+use crate::{
+    Backend, BulkString, ListSide, RespArray, RespError, RespFrame, ScoreRange, SimpleError,
+    SimpleString,
+};
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
+use std::{collections::HashMap, time::Duration};
 use thiserror::Error;
 
 // you could also use once_cell instead of lazy_static
 lazy_static! {
     static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
+    static ref RESP_WRONGTYPE: RespFrame = CommandError::WrongType.into();
 }
 
 #[derive(Error, Debug)]
@@ -17,6 +35,10 @@ pub enum CommandError {
     InvalidCommand(String),
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("wrong number of arguments for '{0}' command")]
+    WrongNumberOfArguments(String),
 
     #[error("{0}")]
     RespError(#[from] RespError),
@@ -24,6 +46,26 @@ pub enum CommandError {
     Utf8Error(#[from] std::string::FromUtf8Error),
 }
 
+// the error reply for the client, prefixed with the error code like redis: ERR for the generic
+// errors, WRONGTYPE for a key of another type
+impl From<CommandError> for RespFrame {
+    fn from(e: CommandError) -> Self {
+        match e {
+            CommandError::InvalidCommand(msg) | CommandError::InvalidArgument(msg) => {
+                SimpleError::new(format!("ERR {}", msg)).into()
+            }
+            CommandError::WrongType => SimpleError::new(e.to_string()).into(),
+            CommandError::WrongNumberOfArguments(_) => {
+                SimpleError::new(format!("ERR {}", e)).into()
+            }
+            CommandError::RespError(e) => e.into(),
+            CommandError::Utf8Error(e) => {
+                SimpleError::new(format!("ERR invalid UTF-8 argument: {}", e)).into()
+            }
+        }
+    }
+}
+
 #[enum_dispatch]
 pub trait CommandExecutor {
     fn execute(self, backend: &Backend) -> RespFrame;
@@ -34,9 +76,82 @@ pub trait CommandExecutor {
 pub enum Command {
     Get(Get),
     Set(Set),
+    MSet(MSet),
+    Append(Append),
+    Lcs(Lcs),
+    IncrBy(IncrBy),
+    DecrBy(DecrBy),
     HGet(HGet),
     HSet(HSet),
+    HSetNx(HSetNx),
+    HRandField(HRandField),
     HGetAll(HGetAll),
+    HScan(HScan),
+    SScan(SScan),
+    SInterCard(SInterCard),
+    ZScan(ZScan),
+    LMove(LMove),
+    RPopLPush(RPopLPush),
+    LPush(LPush),
+    LPushX(LPushX),
+    RPushX(RPushX),
+    RPop(RPop),
+    LInsert(LInsert),
+    RPush(RPush),
+    Ping(Ping),
+    Quit(Quit),
+    Auth(Auth),
+    Select(Select),
+    Hello(Hello),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Debug(DebugCommand),
+    Role(Role),
+    Object(ObjectCommand),
+    Config(ConfigCommand),
+    Memory(MemoryCommand),
+    Scan(Scan),
+    Keys(Keys),
+    Del(Del),
+    Expire(Expire),
+    ExpireAt(ExpireAt),
+    Ttl(Ttl),
+    ExpireTime(ExpireTime),
+    PExpireTime(PExpireTime),
+    PExpireAt(PExpireAt),
+    Type(Type),
+    SwapDb(SwapDb),
+    Move(Move),
+    BitOp(BitOp),
+    BitPos(BitPos),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    Publish(Publish),
+    PubSub(PubSubCommand),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    CommandInfo(CommandInfo),
+    Time(Time),
+    Info(Info),
+    Save(Save),
+    FlushAll(FlushAll),
+    BgSave(BgSave),
+    LastSave(LastSave),
+    BgRewriteAof(BgRewriteAof),
+    ZAdd(ZAdd),
+    ZPopMin(ZPopMin),
+    ZPopMax(ZPopMax),
+    ZCard(ZCard),
+    ZCount(ZCount),
+    ZRem(ZRem),
+    ZRemRangeByScore(ZRemRangeByScore),
+    ZRemRangeByRank(ZRemRangeByRank),
+    ZRangeByScore(ZRangeByScore),
+    SAdd(SAdd),
+    SMIsMember(SMIsMember),
 
     // unrecognized command
     Unrecognized(Unrecognized),
@@ -53,6 +168,39 @@ pub struct Set {
     value: RespFrame,
 }
 
+#[derive(Debug)]
+pub struct MSet {
+    pairs: Vec<(String, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct Append {
+    key: String,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Lcs {
+    key1: String,
+    key2: String,
+    // LEN: reply only the length of the LCS
+    len_only: bool,
+    // IDX: reply the ranges of the matches
+    with_matches: bool,
+}
+
+#[derive(Debug)]
+pub struct IncrBy {
+    key: String,
+    delta: i64,
+}
+
+#[derive(Debug)]
+pub struct DecrBy {
+    key: String,
+    delta: i64,
+}
+
 #[derive(Debug)]
 pub struct HGet {
     key: String,
@@ -66,14 +214,464 @@ pub struct HSet {
     value: RespFrame,
 }
 
+// set the field only when the hash doesn't have it yet
+#[derive(Debug)]
+pub struct HSetNx {
+    key: String,
+    field: String,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct HRandField {
+    key: String,
+    // None replies a single field, Some an array, a negative count allows repeated fields
+    count: Option<i64>,
+    // reply the value after each field
+    withvalues: bool,
+}
+
+// iterates the fields of a hash page by page like SCAN
+#[derive(Debug)]
+pub struct HScan {
+    key: String,
+    cursor: usize,
+    // MATCH: only reply fields matching the glob pattern
+    pattern: Option<String>,
+    count: usize,
+}
+
+// iterates the members of a set page by page like SCAN
+#[derive(Debug)]
+pub struct SScan {
+    key: String,
+    cursor: usize,
+    pattern: Option<String>,
+    count: usize,
+}
+
+// iterates the members of a sorted set with their scores page by page like SCAN
+#[derive(Debug)]
+pub struct ZScan {
+    key: String,
+    cursor: usize,
+    pattern: Option<String>,
+    count: usize,
+}
+
 #[derive(Debug)]
 pub struct HGetAll {
     key: String,
-    sort: bool,
 }
 
 #[derive(Debug)]
-pub struct Unrecognized;
+pub struct LMove {
+    src: String,
+    dst: String,
+    src_side: ListSide,
+    dst_side: ListSide,
+}
+
+#[derive(Debug)]
+pub struct RPopLPush {
+    src: String,
+    dst: String,
+}
+
+#[derive(Debug)]
+pub struct RPush {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct LPush {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+// like LPUSH and RPUSH, but a missing key is left alone instead of being created
+#[derive(Debug)]
+pub struct LPushX {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct RPushX {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+// insert the value next to the first element equal to the pivot
+#[derive(Debug)]
+pub struct LInsert {
+    key: String,
+    before: bool,
+    pivot: RespFrame,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct RPop {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Ping {
+    message: Option<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct Quit;
+
+// switches the database of the connection, handled by the network layer
+#[derive(Debug)]
+pub struct Select {
+    pub index: i64,
+}
+
+// authenticates the connection, checked by the network layer
+#[derive(Debug)]
+pub struct Auth {
+    // only the default user exists
+    pub username: Option<String>,
+    pub password: String,
+}
+
+// switches the protocol of the connection and optionally authenticates it, handled by the
+// network layer
+#[derive(Debug)]
+pub struct Hello {
+    pub protover: Option<i64>,
+    pub auth: Option<Auth>,
+}
+
+// MULTI, EXEC and DISCARD work on the transaction state of the connection, see network.rs
+#[derive(Debug)]
+pub struct Multi;
+
+#[derive(Debug)]
+pub struct Exec;
+
+#[derive(Debug)]
+pub struct Discard;
+
+#[derive(Debug)]
+pub enum DebugCommand {
+    // save a snapshot and load it back right away
+    Reload,
+    // give the server a new random run id
+    ChangeReplId,
+    // match `string` against the glob `pattern`, replies 1 or 0
+    StringMatchLen { pattern: Vec<u8>, string: Vec<u8> },
+    // turn the periodic deletion of expired keys on or off
+    SetActiveExpire { enabled: bool },
+    // panic while executing, to exercise the panic handling of the network layer
+    Panic,
+    // low level details of the value at the key
+    Object { key: String },
+    // hold up the connection for a while, on the blocking pool when `blocking`
+    Sleep { duration: Duration, blocking: bool },
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Role;
+
+#[derive(Debug)]
+pub struct Expire {
+    key: String,
+    // a time to live of 0 or less deletes the key right away
+    seconds: i64,
+}
+
+#[derive(Debug)]
+pub struct ExpireAt {
+    key: String,
+    // unix time in seconds, a time in the past deletes the key right away
+    timestamp: i64,
+}
+
+#[derive(Debug)]
+pub struct Ttl {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct ExpireTime {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct PExpireAt {
+    key: String,
+    // unix time in milliseconds, a time in the past deletes the key right away
+    timestamp: i64,
+}
+
+#[derive(Debug)]
+pub struct PExpireTime {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Type {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct Move {
+    key: String,
+    db: usize,
+}
+
+#[derive(Debug)]
+pub struct SwapDb {
+    db1: usize,
+    db2: usize,
+}
+
+#[derive(Debug)]
+pub enum MemoryCommand {
+    // estimated bytes of the key and its value, containers are measured on `samples` elements
+    Usage { key: String, samples: usize },
+    Help,
+}
+
+#[derive(Debug)]
+pub enum ObjectCommand {
+    // the logarithmic access frequency counter of the key
+    Freq { key: String },
+    // the internal representation of the value, e.g. listpack or hashtable for hashes
+    Encoding { key: String },
+    Help,
+}
+
+#[derive(Debug)]
+pub enum ConfigCommand {
+    // the parameters matching any of the glob patterns
+    Get { patterns: Vec<String> },
+    Set { parameter: String, value: String },
+    Help,
+}
+
+#[derive(Debug)]
+pub enum PubSubCommand {
+    // active channels, only the ones matching the glob pattern when given
+    Channels { pattern: Option<String> },
+    NumSub { channels: Vec<String> },
+    NumPat,
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Scan {
+    cursor: usize,
+    // MATCH: only reply keys matching the glob pattern
+    pattern: Option<String>,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct Keys {
+    pattern: String,
+}
+
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+#[derive(Debug)]
+pub struct BitOp {
+    op: BitOperation,
+    dest: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct BitPos {
+    key: String,
+    bit: bool,
+    range: BitRange,
+}
+
+// inclusive range of BITPOS, negative indexes count from the end of the string
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BitRange {
+    start: i64,
+    // None runs to the end of the string
+    end: Option<i64>,
+    // the indexes are bits instead of bytes
+    bits: bool,
+}
+
+// SUBSCRIBE and UNSUBSCRIBE change the connection state, they are handled by the network layer
+#[derive(Debug)]
+pub struct Subscribe {
+    pub channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Unsubscribe {
+    // unsubscribe from every channel when empty
+    pub channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PSubscribe {
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PUnsubscribe {
+    // unsubscribe from every pattern when empty
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: String,
+    message: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct PfAdd {
+    key: String,
+    elements: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PfCount {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum CommandInfo {
+    // every command in the table
+    All,
+    Info { names: Vec<String> },
+    // the key arguments of the full command in `args`
+    GetKeys { args: Vec<String> },
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Time;
+
+#[derive(Debug)]
+pub struct Info {
+    // every section when None
+    section: Option<String>,
+}
+
+// remove every key, the ASYNC and SYNC options are accepted and ignored
+#[derive(Debug)]
+pub struct FlushAll;
+
+#[derive(Debug)]
+pub struct Save;
+
+#[derive(Debug)]
+pub struct BgSave;
+
+#[derive(Debug)]
+pub struct LastSave;
+
+#[derive(Debug)]
+pub struct BgRewriteAof;
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: String,
+    // (score, member) in argument order
+    members: Vec<(f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct ZPopMin {
+    key: String,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct ZPopMax {
+    key: String,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: String,
+    range: ScoreRange,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByScore {
+    key: String,
+    range: ScoreRange,
+}
+
+// ranks are inclusive and count from the highest score when negative
+#[derive(Debug)]
+pub struct ZRemRangeByRank {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+// LIMIT is (offset, count), a negative count means all the remaining members
+#[derive(Debug)]
+pub struct ZRangeByScore {
+    key: String,
+    range: ScoreRange,
+    withscores: bool,
+    limit: Option<(i64, i64)>,
+}
+
+#[derive(Debug)]
+pub struct SAdd {
+    key: String,
+    members: Vec<String>,
+}
+
+// SINTERCARD numkeys key [key ...] [LIMIT limit], a limit of 0 counts everything
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<String>,
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Unrecognized {
+    name: String,
+}
 
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
@@ -90,14 +688,97 @@ impl TryFrom<RespFrame> for Command {
 impl TryFrom<RespArray> for Command {
     type Error = CommandError;
     fn try_from(v: RespArray) -> Result<Self, Self::Error> {
+        // the arity is checked against the command table before any argument is parsed
+        if let Some(RespFrame::BulkString(ref cmd)) = v.first() {
+            let name = String::from_utf8_lossy(cmd).to_ascii_lowercase();
+            if lookup_command(&name).is_some_and(|spec| !spec.check_arity(v.len())) {
+                return Err(CommandError::WrongNumberOfArguments(name));
+            }
+        }
         match v.first() {
-            Some(RespFrame::BulkString(ref cmd)) => match cmd.as_ref() {
+            Some(RespFrame::BulkString(ref cmd)) => match cmd.to_ascii_lowercase().as_slice() {
                 b"get" => Ok(Get::try_from(v)?.into()),
                 b"set" => Ok(Set::try_from(v)?.into()),
+                b"mset" => Ok(MSet::try_from(v)?.into()),
+                b"append" => Ok(Append::try_from(v)?.into()),
+                b"lcs" => Ok(Lcs::try_from(v)?.into()),
+                b"incrby" => Ok(IncrBy::try_from(v)?.into()),
+                b"decrby" => Ok(DecrBy::try_from(v)?.into()),
                 b"hget" => Ok(HGet::try_from(v)?.into()),
                 b"hset" => Ok(HSet::try_from(v)?.into()),
+                b"hsetnx" => Ok(HSetNx::try_from(v)?.into()),
+                b"hrandfield" => Ok(HRandField::try_from(v)?.into()),
                 b"hgetall" => Ok(HGetAll::try_from(v)?.into()),
-                _ => Ok(Unrecognized.into()),
+                b"hscan" => Ok(HScan::try_from(v)?.into()),
+                b"sscan" => Ok(SScan::try_from(v)?.into()),
+                b"sintercard" => Ok(SInterCard::try_from(v)?.into()),
+                b"zscan" => Ok(ZScan::try_from(v)?.into()),
+                b"lmove" => Ok(LMove::try_from(v)?.into()),
+                b"rpoplpush" => Ok(RPopLPush::try_from(v)?.into()),
+                b"rpush" => Ok(RPush::try_from(v)?.into()),
+                b"lpush" => Ok(LPush::try_from(v)?.into()),
+                b"lpushx" => Ok(LPushX::try_from(v)?.into()),
+                b"rpushx" => Ok(RPushX::try_from(v)?.into()),
+                b"rpop" => Ok(RPop::try_from(v)?.into()),
+                b"linsert" => Ok(LInsert::try_from(v)?.into()),
+                b"ping" => Ok(Ping::try_from(v)?.into()),
+                b"quit" => Ok(Quit::try_from(v)?.into()),
+                b"auth" => Ok(Auth::try_from(v)?.into()),
+                b"select" => Ok(Select::try_from(v)?.into()),
+                b"hello" => Ok(Hello::try_from(v)?.into()),
+                b"multi" => Ok(Multi::try_from(v)?.into()),
+                b"exec" => Ok(Exec::try_from(v)?.into()),
+                b"discard" => Ok(Discard::try_from(v)?.into()),
+                b"debug" => Ok(DebugCommand::try_from(v)?.into()),
+                b"role" => Ok(Role::try_from(v)?.into()),
+                b"object" => Ok(ObjectCommand::try_from(v)?.into()),
+                b"config" => Ok(ConfigCommand::try_from(v)?.into()),
+                b"memory" => Ok(MemoryCommand::try_from(v)?.into()),
+                b"scan" => Ok(Scan::try_from(v)?.into()),
+                b"keys" => Ok(Keys::try_from(v)?.into()),
+                b"del" => Ok(Del::try_from(v)?.into()),
+                b"expire" => Ok(Expire::try_from(v)?.into()),
+                b"expireat" => Ok(ExpireAt::try_from(v)?.into()),
+                b"ttl" => Ok(Ttl::try_from(v)?.into()),
+                b"expiretime" => Ok(ExpireTime::try_from(v)?.into()),
+                b"pexpiretime" => Ok(PExpireTime::try_from(v)?.into()),
+                b"pexpireat" => Ok(PExpireAt::try_from(v)?.into()),
+                b"type" => Ok(Type::try_from(v)?.into()),
+                b"swapdb" => Ok(SwapDb::try_from(v)?.into()),
+                b"move" => Ok(Move::try_from(v)?.into()),
+                b"bitop" => Ok(BitOp::try_from(v)?.into()),
+                b"bitpos" => Ok(BitPos::try_from(v)?.into()),
+                b"subscribe" => Ok(Subscribe::try_from(v)?.into()),
+                b"unsubscribe" => Ok(Unsubscribe::try_from(v)?.into()),
+                b"psubscribe" => Ok(PSubscribe::try_from(v)?.into()),
+                b"punsubscribe" => Ok(PUnsubscribe::try_from(v)?.into()),
+                b"publish" => Ok(Publish::try_from(v)?.into()),
+                b"pubsub" => Ok(PubSubCommand::try_from(v)?.into()),
+                b"pfadd" => Ok(PfAdd::try_from(v)?.into()),
+                b"pfcount" => Ok(PfCount::try_from(v)?.into()),
+                b"command" => Ok(CommandInfo::try_from(v)?.into()),
+                b"info" => Ok(Info::try_from(v)?.into()),
+                b"time" => Ok(Time::try_from(v)?.into()),
+                b"save" => Ok(Save::try_from(v)?.into()),
+                b"flushall" => Ok(FlushAll::try_from(v)?.into()),
+                b"bgsave" => Ok(BgSave::try_from(v)?.into()),
+                b"lastsave" => Ok(LastSave::try_from(v)?.into()),
+                b"bgrewriteaof" => Ok(BgRewriteAof::try_from(v)?.into()),
+                b"zadd" => Ok(ZAdd::try_from(v)?.into()),
+                b"zpopmin" => Ok(ZPopMin::try_from(v)?.into()),
+                b"zpopmax" => Ok(ZPopMax::try_from(v)?.into()),
+                b"zcard" => Ok(ZCard::try_from(v)?.into()),
+                b"zcount" => Ok(ZCount::try_from(v)?.into()),
+                b"zrem" => Ok(ZRem::try_from(v)?.into()),
+                b"zremrangebyscore" => Ok(ZRemRangeByScore::try_from(v)?.into()),
+                b"zremrangebyrank" => Ok(ZRemRangeByRank::try_from(v)?.into()),
+                b"zrangebyscore" => Ok(ZRangeByScore::try_from(v)?.into()),
+                b"sadd" => Ok(SAdd::try_from(v)?.into()),
+                b"smismember" => Ok(SMIsMember::try_from(v)?.into()),
+                _ => Ok(Unrecognized {
+                    name: String::from_utf8_lossy(cmd).into_owned(),
+                }
+                .into()),
             },
             _ => Err(CommandError::InvalidCommand(
                 "Command must have a BulkString as the first argument".to_string(),
@@ -106,9 +787,10 @@ impl TryFrom<RespArray> for Command {
     }
 }
 
+// answered with an error so a pipeline carries on with the next command
 impl CommandExecutor for Unrecognized {
     fn execute(self, _: &Backend) -> RespFrame {
-        RESP_OK.clone()
+        CommandError::InvalidCommand(format!("unknown command '{}'", self.name)).into()
     }
 }
 
@@ -146,6 +828,119 @@ fn validate_command(
     Ok(())
 }
 
+impl RespArray {
+    /// Convert every element into a `String`, only BulkString and SimpleString are accepted
+    pub fn into_strings(self) -> Result<Vec<String>, CommandError> {
+        self.0
+            .into_iter()
+            .map(|frame| match frame {
+                RespFrame::BulkString(s) => Ok(String::from_utf8(s.0)?),
+                RespFrame::SimpleString(s) => Ok(s.0),
+                v => Err(CommandError::InvalidArgument(format!(
+                    "Expect BulkString or SimpleString, got {:?}",
+                    v
+                ))),
+            })
+            .collect()
+    }
+}
+
+// the key holds a value of another type than `ty`
+fn is_other_type(backend: &Backend, key: &str, ty: &str) -> bool {
+    matches!(backend.key_type(key), Some(t) if t != ty)
+}
+
+// the reply of `<CMD> HELP`: the usage line, a pair of lines per subcommand (the syntax and an
+// indented description) and the HELP entry itself, each line a simple string like redis
+fn help_reply(command: &str, lines: &[&str]) -> RespFrame {
+    let header = format!(
+        "{} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+        command
+    );
+    let frames = std::iter::once(header)
+        .chain(lines.iter().map(|line| line.to_string()))
+        .chain(["HELP".to_string(), "    Print this help.".to_string()])
+        .map(|line| SimpleString::new(line).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(frames).into()
+}
+
+/// Resolve the configured command renames on a request: an alias is replaced by the name of
+/// the command it stands for, a command called by its original name after being renamed or
+/// disabled is unknown
+pub fn rename_command(
+    frame: RespFrame,
+    renames: &HashMap<String, String>,
+) -> Result<RespFrame, CommandError> {
+    let RespFrame::Array(mut array) = frame else {
+        return Ok(frame);
+    };
+    let Some(RespFrame::BulkString(name)) = array.0.first() else {
+        return Ok(array.into());
+    };
+    let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+    if let Some((original, _)) = renames
+        .iter()
+        .find(|(_, alias)| !alias.is_empty() && alias.eq_ignore_ascii_case(&name))
+    {
+        array.0[0] = BulkString::from(original.as_str()).into();
+    } else if renames.contains_key(&name) {
+        return Err(CommandError::InvalidCommand(format!(
+            "unknown command '{}'",
+            name
+        )));
+    }
+    Ok(array.into())
+}
+
+// the [MATCH pattern] [COUNT count] options shared by SCAN and the per-key scans, COUNT
+// defaults to 10
+fn parse_scan_options(
+    mut args: impl Iterator<Item = String>,
+    command: &str,
+) -> Result<(Option<String>, usize), CommandError> {
+    let mut pattern = None;
+    let mut count = 10;
+    while let Some(arg) = args.next() {
+        match (arg.to_ascii_lowercase().as_str(), args.next()) {
+            ("match", Some(p)) => pattern = Some(p),
+            ("count", Some(n)) => {
+                count = match n.parse::<usize>() {
+                    Ok(n) if n > 0 => n,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT must be a positive integer".to_string(),
+                        ))
+                    }
+                }
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Invalid {} option: {}",
+                    command, arg
+                )))
+            }
+        }
+    }
+    Ok((pattern, count))
+}
+
+// <key> <cursor> [MATCH pattern] [COUNT count] of HSCAN, SSCAN and ZSCAN
+fn parse_key_scan(
+    value: RespArray,
+    command: &str,
+) -> Result<(String, usize, Option<String>, usize), CommandError> {
+    let mut args = value.into_strings()?.into_iter().skip(1);
+    let key = args.next().unwrap_or_default();
+    let cursor = args
+        .next()
+        .unwrap_or_default()
+        .parse::<usize>()
+        .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string()))?;
+    let (pattern, count) = parse_scan_options(args, command)?;
+    Ok((key, cursor, pattern, count))
+}
+
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
@@ -153,7 +948,7 @@ fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, Comman
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{RespDecode, RespNull};
+    use crate::{BulkString, RespDecode, RespNull, SimpleString};
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -173,4 +968,67 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_command_name_is_case_insensitive() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Command = frame.try_into()?;
+        assert!(matches!(cmd, Command::Get(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resp_array_into_strings() -> Result<()> {
+        let array = RespArray::new([
+            BulkString::from("hset").into(),
+            SimpleString::new("map").into(),
+            BulkString::from("hello").into(),
+        ]);
+        assert_eq!(array.into_strings()?, vec!["hset", "map", "hello"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resp_array_into_strings_with_integer_should_fail() {
+        let array = RespArray::new([BulkString::from("get").into(), 123.into()]);
+        let ret = array.into_strings();
+        assert!(matches!(ret, Err(CommandError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_command_error_into_frame() {
+        let error = |e: CommandError| RespFrame::from(e);
+        assert_eq!(
+            error(CommandError::InvalidCommand(
+                "unknown command 'foo'".to_string()
+            )),
+            SimpleError::new("ERR unknown command 'foo'").into()
+        );
+        assert_eq!(
+            error(CommandError::InvalidArgument("Invalid key".to_string())),
+            SimpleError::new("ERR Invalid key").into()
+        );
+        assert_eq!(
+            error(CommandError::WrongType),
+            SimpleError::new("WRONGTYPE Operation against a key holding the wrong kind of value")
+                .into()
+        );
+        assert_eq!(error(CommandError::WrongType), RESP_WRONGTYPE.clone());
+        assert_eq!(
+            error(RespError::NotComplete.into()),
+            SimpleError::new("ERR Protocol error: incomplete frame").into()
+        );
+        let e = String::from_utf8(vec![0xff]).unwrap_err();
+        assert_eq!(
+            error(CommandError::Utf8Error(e)),
+            SimpleError::new(
+                "ERR invalid UTF-8 argument: invalid utf-8 sequence of 1 bytes from index 0"
+            )
+            .into()
+        );
+    }
 }
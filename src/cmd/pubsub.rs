@@ -0,0 +1,311 @@
+use super::{
+    extract_args, help_reply, validate_command, CommandExecutor, PSubscribe, PUnsubscribe,
+    PubSubCommand, Publish, Subscribe, Unsubscribe,
+};
+use crate::{cmd::CommandError, Backend, BulkString, RespArray, RespFrame, SimpleError};
+
+impl CommandExecutor for Subscribe {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR SUBSCRIBE is only supported on a client connection").into()
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR UNSUBSCRIBE is only supported on a client connection").into()
+    }
+}
+
+impl CommandExecutor for PSubscribe {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR PSUBSCRIBE is only supported on a client connection").into()
+    }
+}
+
+impl CommandExecutor for PUnsubscribe {
+    fn execute(self, _: &Backend) -> RespFrame {
+        SimpleError::new("ERR PUNSUBSCRIBE is only supported on a client connection").into()
+    }
+}
+
+impl CommandExecutor for Publish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.publish(&self.channel, self.message) as i64).into()
+    }
+}
+
+const PUBSUB_HELP: &[&str] = &[
+    "CHANNELS [<pattern>]",
+    "    Return the currently active channels matching a <pattern> (default: '*').",
+    "NUMPAT",
+    "    Return number of subscriptions to patterns.",
+    "NUMSUB [<channel> ...]",
+    "    Return the number of subscribers for the specified channels, excluding",
+    "    pattern subscriptions(default: no channels).",
+];
+
+impl CommandExecutor for PubSubCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self {
+            PubSubCommand::Channels { pattern } => RespArray::new(
+                backend
+                    .pubsub_channels(pattern.as_deref())
+                    .into_iter()
+                    .map(|channel| BulkString::from(channel).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            // flat channel/count pairs in the order the channels were given
+            PubSubCommand::NumSub { channels } => RespArray::new(
+                channels
+                    .into_iter()
+                    .flat_map(|channel| {
+                        let count = backend.pubsub_numsub(&channel) as i64;
+                        [BulkString::from(channel).into(), count.into()]
+                    })
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            PubSubCommand::NumPat => (backend.pubsub_numpat() as i64).into(),
+            PubSubCommand::Help => help_reply("PUBSUB", PUBSUB_HELP),
+        }
+    }
+}
+
+impl Subscribe {
+    /// Reply sent for every channel subscribed, `count` is the number of subscriptions afterwards
+    pub fn reply(channel: &str, count: usize) -> RespFrame {
+        subscription_reply("subscribe", Some(channel), count)
+    }
+}
+
+impl Unsubscribe {
+    /// Reply sent for every channel unsubscribed, `count` is the number of subscriptions left
+    pub fn reply(channel: Option<&str>, count: usize) -> RespFrame {
+        subscription_reply("unsubscribe", channel, count)
+    }
+}
+
+impl PSubscribe {
+    /// Reply sent for every pattern subscribed, `count` is the number of subscriptions afterwards
+    pub fn reply(pattern: &str, count: usize) -> RespFrame {
+        subscription_reply("psubscribe", Some(pattern), count)
+    }
+}
+
+impl PUnsubscribe {
+    /// Reply sent for every pattern unsubscribed, `count` is the number of subscriptions left
+    pub fn reply(pattern: Option<&str>, count: usize) -> RespFrame {
+        subscription_reply("punsubscribe", pattern, count)
+    }
+}
+
+// [kind, channel or pattern (null when there was nothing to unsubscribe), count]
+fn subscription_reply(kind: &str, name: Option<&str>, count: usize) -> RespFrame {
+    let name = match name {
+        Some(name) => BulkString::from(name).into(),
+        None => RespFrame::Null(crate::RespNull),
+    };
+    RespArray::new([BulkString::from(kind).into(), name, (count as i64).into()]).into()
+}
+
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::InvalidArgument(
+                "subscribe command must have at least one channel".to_string(),
+            ));
+        }
+        let channels = value.into_strings()?.into_iter().skip(1).collect();
+        Ok(Subscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let channels = value.into_strings()?.into_iter().skip(1).collect();
+        Ok(Unsubscribe { channels })
+    }
+}
+
+impl TryFrom<RespArray> for PSubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 2 {
+            return Err(CommandError::InvalidArgument(
+                "psubscribe command must have at least one pattern".to_string(),
+            ));
+        }
+        let patterns = value.into_strings()?.into_iter().skip(1).collect();
+        Ok(PSubscribe { patterns })
+    }
+}
+
+impl TryFrom<RespArray> for PUnsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let patterns = value.into_strings()?.into_iter().skip(1).collect();
+        Ok(PUnsubscribe { patterns })
+    }
+}
+
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["publish"], 2)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(channel)), Some(message)) => Ok(Publish {
+                channel: String::from_utf8(channel.0)?,
+                message,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid channel or message".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PubSubCommand {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let subcommand = match value.get(1) {
+            Some(RespFrame::BulkString(ref cmd)) => cmd.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "PUBSUB command must have a subcommand".to_string(),
+                ))
+            }
+        };
+        let args = value
+            .into_strings()?
+            .into_iter()
+            .skip(2)
+            .collect::<Vec<_>>();
+        match subcommand.as_slice() {
+            b"channels" if args.len() <= 1 => Ok(PubSubCommand::Channels {
+                pattern: args.into_iter().next(),
+            }),
+            b"numsub" => Ok(PubSubCommand::NumSub { channels: args }),
+            b"numpat" if args.is_empty() => Ok(PubSubCommand::NumPat),
+            b"help" => Ok(PubSubCommand::Help),
+            b"channels" | b"numpat" => Err(CommandError::WrongNumberOfArguments(format!(
+                "pubsub|{}",
+                String::from_utf8_lossy(&subcommand)
+            ))),
+            v => Err(CommandError::InvalidCommand(format!(
+                "Unknown PUBSUB subcommand: {}",
+                String::from_utf8_lossy(v)
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespDecode, Subscriber};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_subscribe_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Subscribe = frame.try_into()?;
+        assert_eq!(result.channels, vec!["a", "b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_psubscribe_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$10\r\npsubscribe\r\n$6\r\nnews.*\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: PSubscribe = frame.try_into()?;
+        assert_eq!(result.patterns, vec!["news.*"]);
+        assert_eq!(
+            PSubscribe::reply("news.*", 1),
+            RespArray::new([
+                BulkString::from("psubscribe").into(),
+                BulkString::from("news.*").into(),
+                1.into(),
+            ])
+            .into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pubsub_channels_and_numsub() -> Result<()> {
+        let backend = Backend::new();
+        let (tx, _rx) = mpsc::channel(16);
+        let subscriber = Subscriber::new(tx);
+        backend.subscribe("news", 1, subscriber.clone());
+        backend.subscribe("news", 2, subscriber.clone());
+        backend.subscribe("sports", 2, subscriber.clone());
+        backend.psubscribe("n*", 1, subscriber);
+
+        let pubsub = |args: &[&str]| -> Result<RespFrame> {
+            let frame = RespArray::new(
+                ["pubsub"]
+                    .iter()
+                    .chain(args)
+                    .map(|v| BulkString::from(*v).into())
+                    .collect::<Vec<RespFrame>>(),
+            );
+            let cmd: PubSubCommand = frame.try_into()?;
+            Ok(cmd.execute(&backend))
+        };
+        let bulk = |v: &str| -> RespFrame { BulkString::from(v).into() };
+
+        assert_eq!(
+            pubsub(&["channels"])?,
+            RespArray::new([bulk("news"), bulk("sports")]).into()
+        );
+        assert_eq!(
+            pubsub(&["CHANNELS", "s*"])?,
+            RespArray::new([bulk("sports")]).into()
+        );
+        assert_eq!(
+            pubsub(&["numsub", "news", "sports", "weather"])?,
+            RespArray::new([
+                bulk("news"),
+                2.into(),
+                bulk("sports"),
+                1.into(),
+                bulk("weather"),
+                0.into(),
+            ])
+            .into()
+        );
+        assert_eq!(pubsub(&["numsub"])?, RespArray::new(vec![]).into());
+        assert_eq!(pubsub(&["numpat"])?, RespFrame::Integer(1));
+        assert!(pubsub(&["numpat", "extra"]).is_err());
+        assert!(pubsub(&["nosuchsubcommand"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_publish_command() {
+        let backend = Backend::new();
+        let (tx, mut rx) = mpsc::channel(16);
+        backend.subscribe("news", 1, Subscriber::new(tx));
+
+        let cmd = Publish {
+            channel: "news".to_string(),
+            message: BulkString::from("hello").into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(rx.try_recv().is_ok());
+    }
+}
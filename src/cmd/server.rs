@@ -0,0 +1,882 @@
+use super::{
+    extract_args, help_reply, lookup_command, validate_command, BgRewriteAof, BgSave,
+    CommandExecutor, CommandInfo, DebugCommand, FlushAll, Info, LastSave, Role, Save, Time,
+    COMMAND_TABLE, RESP_OK,
+};
+use crate::{
+    backend::glob_match, cmd::CommandError, Backend, BulkString, RespArray, RespFrame,
+    RespNullArray, SimpleError, SimpleString,
+};
+use bytes::BytesMut;
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const DEBUG_HELP: &[&str] = &[
+    "CHANGE-REPL-ID",
+    "    Change the replication IDs of the instance.",
+    "OBJECT <key>",
+    "    Show low level info about the key and associated value.",
+    "PANIC",
+    "    Panic in the command, the client gets an internal error reply.",
+    "RELOAD",
+    "    Save the dataset and load it back.",
+    "SLEEP <seconds>",
+    "    Stop replying to the client for <seconds>, fractions like 0.0005 are fine.",
+    "SLEEP-BLOCKING <seconds>",
+    "    Like SLEEP, but the wait ties up a thread of the blocking pool.",
+    "SET-ACTIVE-EXPIRE <0|1>",
+    "    Setting it to 0 disables expiring keys in background when they are not accessed.",
+    "STRINGMATCH-LEN <pattern> <string>",
+    "    Run the glob matcher of KEYS and SCAN on <string>.",
+];
+
+const COMMAND_HELP: &[&str] = &[
+    "(no subcommand)",
+    "    Return details about all commands.",
+    "INFO [<command-name> ...]",
+    "    Return details about multiple commands.",
+    "GETKEYS <full-command>",
+    "    Return the keys from a full command.",
+];
+
+impl CommandExecutor for DebugCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self {
+            DebugCommand::Reload => {
+                let mut buf = BytesMut::from(backend.save_snapshot().as_slice());
+                match backend.load_snapshot(&mut buf) {
+                    Ok(_) => RESP_OK.clone(),
+                    Err(e) => {
+                        SimpleError::new(format!("ERR Error trying to load the snapshot: {}", e))
+                            .into()
+                    }
+                }
+            }
+            DebugCommand::ChangeReplId => {
+                backend.change_run_id();
+                RESP_OK.clone()
+            }
+            DebugCommand::StringMatchLen { pattern, string } => {
+                (glob_match(&pattern, &string) as i64).into()
+            }
+            DebugCommand::SetActiveExpire { enabled } => {
+                backend.set_active_expire(enabled);
+                RESP_OK.clone()
+            }
+            DebugCommand::Panic => panic!("DEBUG PANIC"),
+            DebugCommand::Object { key } => match backend.debug_object(&key) {
+                Some(line) => SimpleString::new(line).into(),
+                None => SimpleError::new("ERR no such key").into(),
+            },
+            // the network layer waits without blocking its worker and refuses to queue the
+            // command in a transaction, sleeping here would block the thread running it
+            DebugCommand::Sleep { .. } => {
+                SimpleError::new("ERR DEBUG SLEEP is not allowed in MULTI").into()
+            }
+            DebugCommand::Help => help_reply("DEBUG", DEBUG_HELP),
+        }
+    }
+}
+
+// this server is always a standalone master: ["master", <replication offset>, <replicas>]
+impl CommandExecutor for Role {
+    fn execute(self, _: &Backend) -> RespFrame {
+        RespArray::new([
+            BulkString::from("master").into(),
+            0.into(),
+            RespArray::new([]).into(),
+        ])
+        .into()
+    }
+}
+
+// [unix seconds, microseconds within the second], both as bulk strings
+impl CommandExecutor for Time {
+    fn execute(self, _: &Backend) -> RespFrame {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        RespArray::new([
+            BulkString::from(now.as_secs().to_string()).into(),
+            BulkString::from(now.subsec_micros().to_string()).into(),
+        ])
+        .into()
+    }
+}
+
+// the reply is a bulk string of "# Section" headers followed by "field:value" lines,
+// sections are separated by an empty line
+impl CommandExecutor for Info {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let flag = |v: bool| if v { "1" } else { "0" }.to_string();
+        let sections = [
+            (
+                "Server",
+                vec![
+                    ("redis_version", "7.2.0".to_string()),
+                    ("redis_mode", "standalone".to_string()),
+                    ("process_id", std::process::id().to_string()),
+                    ("run_id", backend.run_id()),
+                    ("uptime_in_seconds", backend.uptime().to_string()),
+                ],
+            ),
+            (
+                "Clients",
+                vec![("connected_clients", backend.connected_clients().to_string())],
+            ),
+            (
+                "Persistence",
+                vec![
+                    (
+                        "rdb_bgsave_in_progress",
+                        flag(backend.bgsave_in_progress.load(Ordering::Acquire)),
+                    ),
+                    ("rdb_last_save_time", backend.last_save().to_string()),
+                    ("aof_enabled", flag(backend.aof_enabled())),
+                    (
+                        "aof_rewrite_in_progress",
+                        flag(backend.aof_rewrite_in_progress.load(Ordering::Acquire)),
+                    ),
+                ],
+            ),
+        ];
+
+        let all = match self.section.as_deref() {
+            None => true,
+            Some(section) => ["all", "default", "everything"].contains(&section),
+        };
+        let info = sections
+            .into_iter()
+            .filter(|(name, _)| all || self.section.as_deref() == Some(&name.to_lowercase()))
+            .map(|(name, fields)| {
+                let fields = fields
+                    .into_iter()
+                    .map(|(field, value)| format!("{}:{}\r\n", field, value))
+                    .collect::<String>();
+                format!("# {}\r\n{}", name, fields)
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        BulkString::from(info).into()
+    }
+}
+
+impl CommandExecutor for FlushAll {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.flushall();
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Save {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.save() {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(format!("ERR Error saving the snapshot: {}", e)).into(),
+        }
+    }
+}
+
+impl CommandExecutor for BgSave {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.bgsave() {
+            SimpleString::new("Background saving started").into()
+        } else {
+            SimpleError::new("ERR Background save already in progress").into()
+        }
+    }
+}
+
+impl CommandExecutor for BgRewriteAof {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.bg_rewrite_aof() {
+            SimpleString::new("Background append only file rewriting started").into()
+        } else {
+            SimpleError::new("ERR Background append only file rewriting already in progress").into()
+        }
+    }
+}
+
+impl CommandExecutor for LastSave {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.last_save() as i64).into()
+    }
+}
+
+impl CommandExecutor for CommandInfo {
+    fn execute(self, _: &Backend) -> RespFrame {
+        let specs = match self {
+            CommandInfo::All => COMMAND_TABLE.iter().map(|spec| spec.into()).collect(),
+            // unknown commands are reported as a null element
+            CommandInfo::Info { names } => names
+                .iter()
+                .map(|name| match lookup_command(name) {
+                    Some(spec) => spec.into(),
+                    None => RespNullArray.into(),
+                })
+                .collect::<Vec<RespFrame>>(),
+            CommandInfo::GetKeys { args } => {
+                let Some(spec) = args.first().and_then(|name| lookup_command(name)) else {
+                    return SimpleError::new("ERR Invalid command specified").into();
+                };
+                if !spec.check_arity(args.len()) {
+                    return SimpleError::new(
+                        "ERR Invalid number of arguments specified for command",
+                    )
+                    .into();
+                }
+                let keys = spec.keys(&args);
+                if keys.is_empty() {
+                    return SimpleError::new("ERR The command has no key arguments").into();
+                }
+                keys.into_iter()
+                    .map(|key| BulkString::from(key).into())
+                    .collect()
+            }
+            CommandInfo::Help => return help_reply("COMMAND", COMMAND_HELP),
+        };
+        RespArray::new(specs).into()
+    }
+}
+
+impl TryFrom<RespArray> for CommandInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let subcommand = match value.get(1) {
+            None => return Ok(CommandInfo::All),
+            Some(RespFrame::BulkString(ref cmd)) => cmd.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "COMMAND subcommand must be a BulkString".to_string(),
+                ))
+            }
+        };
+        match subcommand.as_slice() {
+            b"help" => Ok(CommandInfo::Help),
+            b"info" => {
+                let names = value.into_strings()?.into_iter().skip(2).collect();
+                Ok(CommandInfo::Info { names })
+            }
+            b"getkeys" => {
+                if value.len() < 3 {
+                    return Err(CommandError::InvalidArgument(
+                        "COMMAND GETKEYS needs a full command".to_string(),
+                    ));
+                }
+                let args = value.into_strings()?.into_iter().skip(2).collect();
+                Ok(CommandInfo::GetKeys { args })
+            }
+            v => Err(CommandError::InvalidCommand(format!(
+                "Unknown COMMAND subcommand: {}",
+                String::from_utf8_lossy(v)
+            ))),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Info {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() > 2 {
+            return Err(CommandError::InvalidArgument(
+                "info command must have at most one section".to_string(),
+            ));
+        }
+        let section = value
+            .into_strings()?
+            .into_iter()
+            .nth(1)
+            .map(|v| v.to_lowercase());
+        Ok(Info { section })
+    }
+}
+
+impl TryFrom<RespArray> for Time {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["time"], 0)?;
+        Ok(Time)
+    }
+}
+
+impl TryFrom<RespArray> for FlushAll {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        match value.len() {
+            1 => Ok(FlushAll),
+            2 => match value.get(1) {
+                Some(RespFrame::BulkString(v))
+                    if v.eq_ignore_ascii_case(b"async") || v.eq_ignore_ascii_case(b"sync") =>
+                {
+                    Ok(FlushAll)
+                }
+                _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Save {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["save"], 0)?;
+        Ok(Save)
+    }
+}
+
+impl TryFrom<RespArray> for BgSave {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bgsave"], 0)?;
+        Ok(BgSave)
+    }
+}
+
+impl TryFrom<RespArray> for LastSave {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lastsave"], 0)?;
+        Ok(LastSave)
+    }
+}
+
+impl TryFrom<RespArray> for BgRewriteAof {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bgrewriteaof"], 0)?;
+        Ok(BgRewriteAof)
+    }
+}
+
+impl TryFrom<RespArray> for Role {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["role"], 0)?;
+        Ok(Role)
+    }
+}
+
+impl TryFrom<RespArray> for DebugCommand {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let subcommand = match value.get(1) {
+            Some(RespFrame::BulkString(ref cmd)) => cmd.to_ascii_lowercase(),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "DEBUG command must have a subcommand".to_string(),
+                ))
+            }
+        };
+        match subcommand.as_slice() {
+            b"help" => Ok(DebugCommand::Help),
+            b"reload" => {
+                validate_command(&value, &["debug", "reload"], 0)?;
+                Ok(DebugCommand::Reload)
+            }
+            b"object" => {
+                validate_command(&value, &["debug", "object"], 1)?;
+                let key = value.into_strings()?.into_iter().nth(2).unwrap_or_default();
+                Ok(DebugCommand::Object { key })
+            }
+            b"panic" => {
+                validate_command(&value, &["debug", "panic"], 0)?;
+                Ok(DebugCommand::Panic)
+            }
+            b"change-repl-id" => {
+                validate_command(&value, &["debug", "change-repl-id"], 0)?;
+                Ok(DebugCommand::ChangeReplId)
+            }
+            b"stringmatch-len" => {
+                validate_command(&value, &["debug", "stringmatch-len"], 2)?;
+                let mut args = extract_args(value, 2)?.into_iter();
+                match (args.next(), args.next()) {
+                    (Some(RespFrame::BulkString(pattern)), Some(RespFrame::BulkString(string))) => {
+                        Ok(DebugCommand::StringMatchLen {
+                            pattern: pattern.0,
+                            string: string.0,
+                        })
+                    }
+                    _ => Err(CommandError::InvalidArgument(
+                        "Invalid pattern or string".to_string(),
+                    )),
+                }
+            }
+            b"sleep" | b"sleep-blocking" => {
+                let blocking = subcommand.as_slice() == b"sleep-blocking";
+                let name = if blocking { "sleep-blocking" } else { "sleep" };
+                validate_command(&value, &["debug", name], 1)?;
+                let seconds = value.into_strings()?.into_iter().nth(2).unwrap_or_default();
+                let duration = seconds
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|v| Duration::try_from_secs_f64(v).ok())
+                    .ok_or_else(|| {
+                        CommandError::InvalidArgument("value is not a valid float".to_string())
+                    })?;
+                Ok(DebugCommand::Sleep { duration, blocking })
+            }
+            b"set-active-expire" => {
+                validate_command(&value, &["debug", "set-active-expire"], 1)?;
+                let mut args = value.into_strings()?.into_iter().skip(2);
+                match args.next().as_deref() {
+                    Some("0") => Ok(DebugCommand::SetActiveExpire { enabled: false }),
+                    Some("1") => Ok(DebugCommand::SetActiveExpire { enabled: true }),
+                    _ => Err(CommandError::InvalidArgument(
+                        "SET-ACTIVE-EXPIRE takes 0 or 1".to_string(),
+                    )),
+                }
+            }
+            v => Err(CommandError::InvalidCommand(format!(
+                "Unknown DEBUG subcommand: {}",
+                String::from_utf8_lossy(v)
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{unix_timestamp, BackendConfig, RespDecode};
+    use anyhow::Result;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn test_debug_reload_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\ndebug\r\n$6\r\nRELOAD\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: DebugCommand = frame.try_into()?;
+        assert!(matches!(result, DebugCommand::Reload));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_sleep_from_resp_array() -> Result<()> {
+        let debug = |args: &[&str]| -> Result<DebugCommand, CommandError> {
+            RespArray::new(
+                args.iter()
+                    .map(|v| BulkString::from(*v).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .try_into()
+        };
+        assert!(matches!(
+            debug(&["debug", "sleep", "0.0005"])?,
+            DebugCommand::Sleep { duration, blocking: false } if duration == Duration::from_micros(500)
+        ));
+        assert!(matches!(
+            debug(&["debug", "SLEEP-BLOCKING", "2"])?,
+            DebugCommand::Sleep { duration, blocking: true } if duration == Duration::from_secs(2)
+        ));
+        for args in [
+            &["debug", "sleep", "-1"][..],
+            &["debug", "sleep", "soon"],
+            &["debug", "sleep"],
+        ] {
+            assert!(debug(args).is_err(), "{:?}", args);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_reload_command() {
+        let backend = Backend::new();
+        backend.set("hello".to_string(), BulkString::from("world").into());
+        backend.hset(
+            "map".to_string(),
+            "foo".to_string(),
+            BulkString::from("bar").into(),
+        );
+        backend.rpush("list".to_string(), [BulkString::from("a").into()]);
+
+        let result = DebugCommand::Reload.execute(&backend);
+        assert_eq!(result, RESP_OK.clone());
+
+        assert_eq!(backend.get("hello"), Some(BulkString::from("world").into()));
+        assert_eq!(
+            backend.hget("map", "foo"),
+            Some(BulkString::from("bar").into())
+        );
+        assert_eq!(backend.db().lmap.get("list").map(|v| v.len()), Some(1));
+    }
+
+    #[test]
+    fn test_debug_reload_should_keep_every_type() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("string".to_string(), BulkString::from("a\r\nb").into());
+        backend.set("integer".to_string(), (-42).into());
+        backend.set("double".to_string(), 1.5.into());
+        backend.set("boolean".to_string(), true.into());
+        backend.set(
+            "array".to_string(),
+            RespArray::new([BulkString::from("x").into(), 1.into()]).into(),
+        );
+        for (field, value) in [("f1", "v1"), ("f2", ""), ("f3", "v3")] {
+            backend.hset(
+                "hash".to_string(),
+                field.to_string(),
+                BulkString::from(value).into(),
+            );
+        }
+        backend.rpush(
+            "list".to_string(),
+            ["a", "b", "c"].map(|v| BulkString::from(v).into()),
+        );
+        backend.zadd(
+            "zset".to_string(),
+            [(-1.5, "m1".to_string()), (f64::INFINITY, "m2".to_string())],
+        );
+        backend.pfadd("hll", &["a".to_string(), "b".to_string()])?;
+
+        let strings = |b: &Backend| {
+            let mut v = b
+                .db()
+                .map
+                .iter()
+                .map(|v| (v.key().clone(), v.value().clone()))
+                .collect::<Vec<_>>();
+            v.sort_by(|a, b| a.0.cmp(&b.0));
+            v
+        };
+        let before = strings(&backend);
+        let hash = backend.hgetall("hash");
+        let list = backend.db().lmap.get("list").map(|v| v.clone());
+        let zset = backend.db().zmap.get("zset").map(|v| v.clone());
+
+        assert_eq!(DebugCommand::Reload.execute(&backend), RESP_OK.clone());
+
+        assert_eq!(strings(&backend), before);
+        let hash_fields = |h: Option<dashmap::DashMap<String, RespFrame>>| {
+            let mut v = h
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<Vec<(String, RespFrame)>>();
+            v.sort_by(|a, b| a.0.cmp(&b.0));
+            v
+        };
+        assert_eq!(hash_fields(backend.hgetall("hash")), hash_fields(hash));
+        assert_eq!(backend.db().lmap.get("list").map(|v| v.clone()), list);
+        assert_eq!(backend.db().zmap.get("zset").map(|v| v.clone()), zset);
+        assert_eq!(backend.pfcount(&["hll".to_string()])?, 2);
+        assert_eq!(backend.all_keys().count(), 9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_info_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$7\r\ncommand\r\n$4\r\nINFO\r\n$3\r\nget\r\n$3\r\nSET\r\n$4\r\nnope\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: CommandInfo = frame.try_into()?;
+        let RespFrame::Array(result) = cmd.execute(&Backend::new()) else {
+            panic!("COMMAND INFO should return an array");
+        };
+        assert_eq!(result.len(), 3);
+        assert_eq!(
+            result[0],
+            RespArray::new([
+                BulkString::from("get").into(),
+                2.into(),
+                RespArray::new([
+                    SimpleString::new("readonly").into(),
+                    SimpleString::new("fast").into(),
+                ])
+                .into(),
+                1.into(),
+                1.into(),
+                1.into(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            result[1],
+            RespArray::new([
+                BulkString::from("set").into(),
+                3.into(),
+                RespArray::new([
+                    SimpleString::new("write").into(),
+                    SimpleString::new("denyoom").into(),
+                ])
+                .into(),
+                1.into(),
+                1.into(),
+                1.into(),
+            ])
+            .into()
+        );
+        assert_eq!(result[2], RespNullArray.into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_getkeys_command() -> Result<()> {
+        let getkeys = |args: &[&str]| {
+            let args = args.iter().map(|v| BulkString::from(*v).into());
+            let frame = RespArray::new(
+                [
+                    BulkString::from("command").into(),
+                    BulkString::from("GETKEYS").into(),
+                ]
+                .into_iter()
+                .chain(args)
+                .collect::<Vec<RespFrame>>(),
+            );
+            CommandInfo::try_from(frame).map(|cmd| cmd.execute(&Backend::new()))
+        };
+
+        assert_eq!(
+            getkeys(&["SET", "foo", "bar"])?,
+            RespArray::new([BulkString::from("foo").into()]).into()
+        );
+        assert_eq!(
+            getkeys(&["mset", "a", "1", "b", "2"])?,
+            RespArray::new([BulkString::from("a").into(), BulkString::from("b").into()]).into()
+        );
+        assert_eq!(
+            getkeys(&["time"])?,
+            SimpleError::new("ERR The command has no key arguments").into()
+        );
+        assert_eq!(
+            getkeys(&["get", "a", "b"])?,
+            SimpleError::new("ERR Invalid number of arguments specified for command").into()
+        );
+        assert_eq!(
+            getkeys(&["nope", "a"])?,
+            SimpleError::new("ERR Invalid command specified").into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\ntime\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Time = frame.try_into()?;
+        let RespFrame::Array(result) = cmd.execute(&Backend::new()) else {
+            panic!("TIME should return an array");
+        };
+        let parts = RespArray::new(result.0).into_strings()?;
+        assert_eq!(parts.len(), 2);
+
+        let secs: u64 = parts[0].parse()?;
+        let micros: u32 = parts[1].parse()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        assert!(now.abs_diff(secs) <= 1);
+        assert!(micros < 1_000_000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_with_arguments_should_fail() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$4\r\ntime\r\n$3\r\nnow\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let ret: Result<Time, _> = frame.try_into();
+        assert!(matches!(ret, Err(CommandError::InvalidArgument(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_should_update_lastsave() -> Result<()> {
+        let dump_path =
+            std::env::temp_dir().join(format!("simple-redis-{}.rdb", std::process::id()));
+        let backend = Backend::with_config(BackendConfig {
+            dump_path: dump_path.clone(),
+            ..Default::default()
+        });
+        backend.set("hello".to_string(), BulkString::from("world").into());
+        backend.last_save.store(0, Ordering::Relaxed);
+
+        let before = unix_timestamp();
+        assert_eq!(Save.execute(&backend), RESP_OK.clone());
+        let RespFrame::Integer(last_save) = LastSave.execute(&backend) else {
+            panic!("LASTSAVE should return an integer");
+        };
+        assert!(last_save as u64 >= before);
+
+        let mut buf = BytesMut::from(std::fs::read(&dump_path)?.as_slice());
+        std::fs::remove_file(&dump_path)?;
+        assert_eq!(Backend::new().load_snapshot(&mut buf)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_role_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nrole\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: Role = frame.try_into()?;
+        let result = cmd.execute(&Backend::new());
+
+        let RespFrame::Array(result) = result else {
+            panic!("ROLE should return an array");
+        };
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], BulkString::from("master").into());
+        assert_eq!(result[1], 0.into());
+        assert_eq!(result[2], RespArray::new([]).into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_stringmatch_len_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$5\r\ndebug\r\n$15\r\nSTRINGMATCH-LEN\r\n$4\r\n*a*b\r\n$5\r\nxaxxb\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: DebugCommand = frame.try_into()?;
+        let backend = Backend::new();
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = DebugCommand::StringMatchLen {
+            pattern: b"h[^e]llo".to_vec(),
+            string: b"hello".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        Ok(())
+    }
+
+    fn info_field(backend: &Backend, section: &str, field: &str) -> Option<String> {
+        let cmd = Info {
+            section: Some(section.to_string()),
+        };
+        let RespFrame::BulkString(info) = cmd.execute(backend) else {
+            panic!("INFO should return a bulk string");
+        };
+        String::from_utf8(info.0)
+            .ok()?
+            .split("\r\n")
+            .find_map(|line| line.strip_prefix(&format!("{}:", field)).map(String::from))
+    }
+
+    #[test]
+    fn test_info_server_run_id_should_change() -> Result<()> {
+        let backend = Backend::new();
+        let run_id = info_field(&backend, "server", "run_id").unwrap();
+        assert_eq!(run_id.len(), 40);
+        assert!(run_id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(
+            info_field(&backend, "server", "run_id"),
+            Some(run_id.clone())
+        );
+        // only the requested section is returned
+        assert_eq!(info_field(&backend, "clients", "run_id"), None);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\ndebug\r\n$14\r\nCHANGE-REPL-ID\r\n");
+        let cmd: DebugCommand = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let changed = info_field(&backend, "server", "run_id").unwrap();
+        assert_eq!(changed.len(), 40);
+        assert_ne!(changed, run_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_all_sections() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nINFO\r\n");
+        let cmd: Info = RespArray::decode(&mut buf)?.try_into()?;
+        let RespFrame::BulkString(info) = cmd.execute(&Backend::new()) else {
+            panic!("INFO should return a bulk string");
+        };
+        let info = String::from_utf8(info.0)?;
+        assert!(info.starts_with("# Server\r\n"));
+        assert!(info.contains("\r\n\r\n# Clients\r\nconnected_clients:0\r\n"));
+        assert!(info.contains("aof_enabled:0\r\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flushall_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$8\r\nflushall\r\n$5\r\nASYNC\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: FlushAll = frame.try_into()?;
+
+        let backend = Backend::new();
+        backend.set("key".to_string(), BulkString::from("value").into());
+        backend.hset(
+            "map".to_string(),
+            "field".to_string(),
+            BulkString::from("value").into(),
+        );
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.all_keys().count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_object_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.hset(
+            "map".to_string(),
+            "a".to_string(),
+            BulkString::from("1").into(),
+        );
+        backend.hset(
+            "map".to_string(),
+            "b".to_string(),
+            BulkString::from("2").into(),
+        );
+
+        let frame = RespArray::new([
+            BulkString::from("debug").into(),
+            BulkString::from("object").into(),
+            BulkString::from("map").into(),
+        ]);
+        let cmd: DebugCommand = frame.try_into()?;
+        let RespFrame::SimpleString(line) = cmd.execute(&backend) else {
+            panic!("DEBUG OBJECT should reply a simple string");
+        };
+        let serialized = backend.dump_entry("map").map(|v| v.len()).unwrap_or(0);
+        assert!(line.contains("encoding:listpack"));
+        assert!(line.contains(&format!("serializedlength:{}", serialized)));
+        assert!(line.contains("fields:2"));
+
+        backend.rpush("list".to_string(), [BulkString::from("x").into()]);
+        let cmd = DebugCommand::Object {
+            key: "list".to_string(),
+        };
+        let RespFrame::SimpleString(line) = cmd.execute(&backend) else {
+            panic!("DEBUG OBJECT should reply a simple string");
+        };
+        assert!(line.contains("encoding:quicklist") && line.contains("length:1"));
+
+        let cmd = DebugCommand::Object {
+            key: "missing".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR no such key").into()
+        );
+        Ok(())
+    }
+}
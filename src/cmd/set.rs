@@ -0,0 +1,273 @@
+use super::{
+    is_other_type, parse_key_scan, CommandExecutor, SAdd, SInterCard, SMIsMember, SScan,
+    RESP_WRONGTYPE,
+};
+use crate::{backend::glob_match, cmd::CommandError, Backend, BulkString, RespArray, RespFrame};
+
+impl CommandExecutor for SAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "set") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.sadd(self.key, self.members) as i64).into()
+    }
+}
+
+// 1 or 0 per member in the order asked, a missing key is an empty set
+impl CommandExecutor for SMIsMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "set") {
+            return RESP_WRONGTYPE.clone();
+        }
+        let replies = backend
+            .smismember(&self.key, &self.members)
+            .into_iter()
+            .map(|found| RespFrame::Integer(found as i64))
+            .collect::<Vec<_>>();
+        RespArray::new(replies).into()
+    }
+}
+
+// [next cursor, [member, ...]] like SCAN, a missing key is an empty set
+impl CommandExecutor for SScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, members) = match backend.sscan(&self.key, self.cursor, self.count) {
+            Some(page) => page,
+            None if is_other_type(backend, &self.key, "set") => return RESP_WRONGTYPE.clone(),
+            None => (0, vec![]),
+        };
+        let members = members
+            .into_iter()
+            .filter(|member| match &self.pattern {
+                Some(pattern) => glob_match(pattern.as_bytes(), member.as_bytes()),
+                None => true,
+            })
+            .map(|member| BulkString::from(member).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new([
+            BulkString::from(cursor.to_string()).into(),
+            RespArray::new(members).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self
+            .keys
+            .iter()
+            .any(|key| is_other_type(backend, key, "set"))
+        {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.sintercard(&self.keys, self.limit) as i64).into()
+    }
+}
+
+impl TryFrom<RespArray> for SAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = key_and_members(value, "sadd")?;
+        Ok(SAdd { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = key_and_members(value, "smismember")?;
+        Ok(SMIsMember { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SScan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, cursor, pattern, count) = parse_key_scan(value, "SSCAN")?;
+        Ok(SScan {
+            key,
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let numkeys = match args.next().map(|n| n.parse::<usize>()) {
+            Some(Ok(n)) if n > 0 => n,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "numkeys should be greater than 0".to_string(),
+                ))
+            }
+        };
+        let keys = args.by_ref().take(numkeys).collect::<Vec<_>>();
+        if keys.len() < numkeys {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let limit = match (args.next(), args.next(), args.next()) {
+            (None, ..) => 0,
+            (Some(opt), Some(limit), None) if opt.eq_ignore_ascii_case("limit") => {
+                limit.parse::<usize>().map_err(|_| {
+                    CommandError::InvalidArgument("LIMIT can't be negative".to_string())
+                })?
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+// <key> <member> [member ...]
+fn key_and_members(value: RespArray, name: &str) -> Result<(String, Vec<String>), CommandError> {
+    if value.len() < 3 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have a key and at least one member",
+            name
+        )));
+    }
+
+    let mut args = value.into_strings()?.into_iter().skip(1);
+    let key = args.next().unwrap_or_default();
+    Ok((key, args.collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_smismember_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = SAdd {
+            key: "set".to_string(),
+            members: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$10\r\nsmismember\r\n$3\r\nset\r\n$1\r\nb\r\n$1\r\nx\r\n$1\r\na\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: SMIsMember = frame.try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([1.into(), 0.into(), 1.into()]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sscan_should_page_through_members() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nsscan\r\n$3\r\nset\r\n$1\r\n2\r\n$5\r\nmatch\r\n$2\r\na*\r\n",
+        );
+        let cmd: SScan = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!((cmd.cursor, cmd.pattern.as_deref()), (2, Some("a*")));
+
+        let backend = Backend::new();
+        backend.sadd("set".to_string(), ["c", "a", "b"].map(str::to_string));
+        let page = |cursor| {
+            SScan {
+                key: "set".to_string(),
+                cursor,
+                pattern: None,
+                count: 2,
+            }
+            .execute(&backend)
+        };
+        let reply = |cursor: &str, members: &[&str]| -> RespFrame {
+            let members = members
+                .iter()
+                .map(|m| BulkString::from(*m).into())
+                .collect::<Vec<RespFrame>>();
+            RespArray::new([
+                BulkString::from(cursor).into(),
+                RespArray::new(members).into(),
+            ])
+            .into()
+        };
+        let (next, first) = backend.sscan("set", 0, 2).unwrap();
+        let first = first.iter().map(String::as_str).collect::<Vec<_>>();
+        let rest = ["a", "b", "c"].into_iter().filter(|m| !first.contains(m));
+        let (next, rest) = (next.to_string(), rest.collect::<Vec<_>>());
+        assert_eq!(first.len(), 2);
+        assert_eq!(page(0), reply(&next, &first));
+        assert_eq!(page(next.parse()?), reply("0", &rest));
+
+        backend.set("string".to_string(), BulkString::from("a").into());
+        let cmd = SScan {
+            key: "string".to_string(),
+            cursor: 0,
+            pattern: None,
+            count: 2,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sintercard_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("a".to_string(), ["1", "2", "3", "4"].map(str::to_string));
+        backend.sadd("b".to_string(), ["2", "3", "4", "5"].map(str::to_string));
+        backend.sadd("c".to_string(), ["3", "4", "6"].map(str::to_string));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$10\r\nsintercard\r\n$1\r\n3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n",
+        );
+        let cmd: SInterCard = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.limit, 0);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let intercard = |keys: &[&str], limit| {
+            SInterCard {
+                keys: keys.iter().map(|k| k.to_string()).collect(),
+                limit,
+            }
+            .execute(&backend)
+        };
+        assert_eq!(intercard(&["a", "b"], 0), RespFrame::Integer(3));
+        // counting stops at the limit
+        assert_eq!(intercard(&["a", "b"], 2), RespFrame::Integer(2));
+        assert_eq!(intercard(&["a", "b"], 10), RespFrame::Integer(3));
+        assert_eq!(intercard(&["a", "missing"], 0), RespFrame::Integer(0));
+
+        buf.extend_from_slice(b"*4\r\n$10\r\nsintercard\r\n$1\r\n3\r\n$1\r\na\r\n$1\r\nb\r\n");
+        assert!(SInterCard::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_smismember_missing_key() {
+        let backend = Backend::new();
+        let cmd = SMIsMember {
+            key: "missing".to_string(),
+            members: vec!["a".to_string(), "b".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([0.into(), 0.into()]).into()
+        );
+
+        backend.set("string".to_string(), BulkString::from("a").into());
+        let cmd = SMIsMember {
+            key: "string".to_string(),
+            members: vec!["a".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{BulkString, RespArray, RespFrame, SimpleString};
+
+/// Static metadata of a command, the same shape redis reports in COMMAND INFO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    // number of arguments including the command name, negative means "at least"
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    // position of the first and last key argument, last key -1 means "until the end"
+    pub first_key: i64,
+    pub last_key: i64,
+    pub key_step: i64,
+}
+
+const fn spec(
+    name: &'static str,
+    arity: i64,
+    flags: &'static [&'static str],
+    first_key: i64,
+    last_key: i64,
+    key_step: i64,
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        arity,
+        flags,
+        first_key,
+        last_key,
+        key_step,
+    }
+}
+
+const READONLY: &[&str] = &["readonly", "fast"];
+const WRITE: &[&str] = &["write", "denyoom"];
+const PUBSUB: &[&str] = &["pubsub", "loading", "stale"];
+const ADMIN: &[&str] = &["admin", "noscript"];
+
+pub static COMMAND_TABLE: &[CommandSpec] = &[
+    spec("get", 2, READONLY, 1, 1, 1),
+    spec("set", 3, WRITE, 1, 1, 1),
+    spec("mset", -3, WRITE, 1, -1, 2),
+    spec("append", 3, WRITE, 1, 1, 1),
+    spec("lcs", -3, &["readonly"], 1, 2, 1),
+    spec("incrby", 3, &["write", "denyoom", "fast"], 1, 1, 1),
+    spec("decrby", 3, &["write", "denyoom", "fast"], 1, 1, 1),
+    spec("hget", 3, READONLY, 1, 1, 1),
+    spec("hset", 4, WRITE, 1, 1, 1),
+    spec("hsetnx", 4, WRITE, 1, 1, 1),
+    spec("hrandfield", -2, READONLY, 1, 1, 1),
+    spec("hgetall", 2, &["readonly"], 1, 1, 1),
+    spec("hscan", -3, &["readonly"], 1, 1, 1),
+    spec("lmove", 5, WRITE, 1, 2, 1),
+    spec("rpoplpush", 3, WRITE, 1, 2, 1),
+    spec("rpush", -3, WRITE, 1, 1, 1),
+    spec("lpush", -3, &["write", "denyoom", "fast"], 1, 1, 1),
+    spec("lpushx", -3, &["write", "denyoom", "fast"], 1, 1, 1),
+    spec("rpushx", -3, &["write", "denyoom", "fast"], 1, 1, 1),
+    spec("rpop", 2, &["write", "fast"], 1, 1, 1),
+    spec("linsert", 5, WRITE, 1, 1, 1),
+    spec("ping", -1, &["fast", "stale"], 0, 0, 0),
+    spec("quit", 1, &["fast", "loading", "stale"], 0, 0, 0),
+    spec(
+        "auth",
+        -2,
+        &["noscript", "loading", "stale", "fast"],
+        0,
+        0,
+        0,
+    ),
+    spec(
+        "multi",
+        1,
+        &["noscript", "fast", "loading", "stale"],
+        0,
+        0,
+        0,
+    ),
+    spec("select", 2, &["loading", "stale", "fast"], 0, 0, 0),
+    spec(
+        "hello",
+        -1,
+        &["noscript", "loading", "stale", "fast"],
+        0,
+        0,
+        0,
+    ),
+    spec("swapdb", 3, &["write", "fast"], 0, 0, 0),
+    spec("move", 3, &["write", "fast"], 1, 1, 1),
+    spec("exec", 1, &["noscript", "loading", "stale"], 0, 0, 0),
+    spec(
+        "discard",
+        1,
+        &["noscript", "fast", "loading", "stale"],
+        0,
+        0,
+        0,
+    ),
+    spec("debug", -2, ADMIN, 0, 0, 0),
+    spec(
+        "role",
+        1,
+        &["noscript", "loading", "stale", "fast"],
+        0,
+        0,
+        0,
+    ),
+    spec("object", -2, &["readonly"], 2, 2, 1),
+    spec("memory", -2, &["readonly"], 2, 2, 1),
+    spec("config", -2, ADMIN, 0, 0, 0),
+    spec("scan", -2, &["readonly"], 0, 0, 0),
+    spec("keys", 2, &["readonly"], 0, 0, 0),
+    spec("del", -2, &["write"], 1, -1, 1),
+    spec("expire", 3, &["write", "fast"], 1, 1, 1),
+    spec("expireat", 3, &["write", "fast"], 1, 1, 1),
+    spec("pexpireat", 3, &["write", "fast"], 1, 1, 1),
+    spec("ttl", 2, READONLY, 1, 1, 1),
+    spec("expiretime", 2, READONLY, 1, 1, 1),
+    spec("pexpiretime", 2, READONLY, 1, 1, 1),
+    spec("type", 2, READONLY, 1, 1, 1),
+    spec("bitop", -4, WRITE, 2, -1, 1),
+    spec("bitpos", -3, READONLY, 1, 1, 1),
+    spec("subscribe", -2, PUBSUB, 0, 0, 0),
+    spec("unsubscribe", -1, PUBSUB, 0, 0, 0),
+    spec("psubscribe", -2, PUBSUB, 0, 0, 0),
+    spec("punsubscribe", -1, PUBSUB, 0, 0, 0),
+    spec(
+        "publish",
+        3,
+        &["pubsub", "loading", "stale", "fast"],
+        0,
+        0,
+        0,
+    ),
+    spec("pubsub", -2, PUBSUB, 0, 0, 0),
+    spec("pfadd", -2, WRITE, 1, 1, 1),
+    spec("pfcount", -2, &["readonly"], 1, -1, 1),
+    spec("command", -1, &["loading", "stale"], 0, 0, 0),
+    spec("info", -1, &["loading", "stale"], 0, 0, 0),
+    spec("time", 1, &["loading", "stale", "fast"], 0, 0, 0),
+    spec("save", 1, ADMIN, 0, 0, 0),
+    spec("flushall", -1, &["write"], 0, 0, 0),
+    spec("bgsave", 1, ADMIN, 0, 0, 0),
+    spec("lastsave", 1, &["loading", "stale", "fast"], 0, 0, 0),
+    spec("bgrewriteaof", 1, ADMIN, 0, 0, 0),
+    spec("zadd", -4, &["write", "denyoom", "fast"], 1, 1, 1),
+    spec("zpopmin", -2, &["write", "fast"], 1, 1, 1),
+    spec("zpopmax", -2, &["write", "fast"], 1, 1, 1),
+    spec("sadd", -3, &["write", "denyoom", "fast"], 1, 1, 1),
+    spec("smismember", -3, READONLY, 1, 1, 1),
+    spec("sscan", -3, &["readonly"], 1, 1, 1),
+    spec("sintercard", -3, &["readonly", "movablekeys"], 0, 0, 0),
+    spec("zcard", 2, READONLY, 1, 1, 1),
+    spec("zcount", 4, READONLY, 1, 1, 1),
+    spec("zrem", -3, &["write", "fast"], 1, 1, 1),
+    spec("zremrangebyscore", 4, &["write"], 1, 1, 1),
+    spec("zremrangebyrank", 4, &["write"], 1, 1, 1),
+    spec("zrangebyscore", -4, &["readonly"], 1, 1, 1),
+    spec("zscan", -3, &["readonly"], 1, 1, 1),
+];
+
+impl CommandSpec {
+    pub fn is_write(&self) -> bool {
+        self.flags.contains(&"write")
+    }
+
+    /// Whether the command may grow the dataset, it is rejected when out of memory
+    pub fn is_denyoom(&self) -> bool {
+        self.flags.contains(&"denyoom")
+    }
+
+    /// Whether `n` arguments (including the command name) satisfy the arity
+    pub fn check_arity(&self, n: usize) -> bool {
+        if self.arity >= 0 {
+            n as i64 == self.arity
+        } else {
+            n as i64 >= -self.arity
+        }
+    }
+
+    /// The key arguments of a full command line, `args[0]` is the command name
+    pub fn keys<'a>(&self, args: &'a [String]) -> Vec<&'a str> {
+        if self.first_key <= 0 || self.key_step <= 0 {
+            return vec![];
+        }
+        let last = if self.last_key < 0 {
+            args.len() as i64 + self.last_key
+        } else {
+            self.last_key.min(args.len() as i64 - 1)
+        };
+        (self.first_key..=last)
+            .step_by(self.key_step as usize)
+            .filter_map(|i| args.get(i as usize).map(|v| v.as_str()))
+            .collect()
+    }
+}
+
+/// Look up the metadata of a command, the name is case insensitive
+pub fn lookup_command(name: &str) -> Option<&'static CommandSpec> {
+    COMMAND_TABLE
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(name))
+}
+
+// [name, arity, [flags...], first_key, last_key, key_step]
+impl From<&CommandSpec> for RespFrame {
+    fn from(spec: &CommandSpec) -> Self {
+        let flags = spec
+            .flags
+            .iter()
+            .map(|flag| SimpleString::new(*flag).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new([
+            BulkString::from(spec.name).into(),
+            spec.arity.into(),
+            RespArray::new(flags).into(),
+            spec.first_key.into(),
+            spec.last_key.into(),
+            spec.key_step.into(),
+        ])
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_command() {
+        let spec = lookup_command("GET").unwrap();
+        assert_eq!(spec.name, "get");
+        assert_eq!(spec.arity, 2);
+        assert!(lookup_command("nosuchcommand").is_none());
+    }
+
+    #[test]
+    fn test_command_spec_keys() {
+        let args = |v: &[&str]| v.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        let set = lookup_command("set").unwrap();
+        assert_eq!(set.keys(&args(&["set", "foo", "bar"])), vec!["foo"]);
+
+        let mset = lookup_command("mset").unwrap();
+        assert_eq!(
+            mset.keys(&args(&["mset", "a", "1", "b", "2"])),
+            vec!["a", "b"]
+        );
+
+        let bitop = lookup_command("bitop").unwrap();
+        assert_eq!(
+            bitop.keys(&args(&["bitop", "and", "dest", "a", "b"])),
+            vec!["dest", "a", "b"]
+        );
+
+        let time = lookup_command("time").unwrap();
+        assert!(time.keys(&args(&["time"])).is_empty());
+    }
+
+    #[test]
+    fn test_command_spec_check_arity() {
+        let get = lookup_command("get").unwrap();
+        assert!(get.check_arity(2));
+        assert!(!get.check_arity(3));
+
+        let mset = lookup_command("mset").unwrap();
+        assert!(!mset.check_arity(2));
+        assert!(mset.check_arity(5));
+    }
+}
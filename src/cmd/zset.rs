@@ -0,0 +1,812 @@
+use super::{
+    is_other_type, parse_key_scan, validate_command, CommandExecutor, ZAdd, ZCard, ZCount, ZPopMax,
+    ZPopMin, ZRangeByScore, ZRem, ZRemRangeByRank, ZRemRangeByScore, ZScan, RESP_WRONGTYPE,
+};
+use crate::{
+    backend::glob_match, cmd::CommandError, Backend, BulkString, RespArray, RespFrame, ScoreRange,
+};
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "zset") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.zadd(self.key, self.members) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZPopMin {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        zpop(backend, &self.key, self.count, false)
+    }
+}
+
+impl CommandExecutor for ZPopMax {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        zpop(backend, &self.key, self.count, true)
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "zset") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.zcard(&self.key) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "zset") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.zcount(&self.key, self.range) as i64).into()
+    }
+}
+
+// the ZREM commands reply the number of removed members
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "zset") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.zrem(&self.key, &self.members) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZRemRangeByScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "zset") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.zremrangebyscore(&self.key, self.range) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZRemRangeByRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "zset") {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.zremrangebyrank(&self.key, self.start, self.stop) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZRangeByScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if is_other_type(backend, &self.key, "zset") {
+            return RESP_WRONGTYPE.clone();
+        }
+        let (offset, count) = match self.limit {
+            // like redis a negative offset matches nothing
+            Some((offset, _)) if offset < 0 => return RespArray::new([]).into(),
+            Some((offset, count)) => (offset as usize, usize::try_from(count).ok()),
+            None => (0, None),
+        };
+        let ret = backend
+            .zrangebyscore(&self.key, self.range, offset, count)
+            .into_iter()
+            .flat_map(|(member, score)| {
+                let member = BulkString::from(member).into();
+                let score = self
+                    .withscores
+                    .then(|| BulkString::from(score.to_string()).into());
+                std::iter::once(member).chain(score)
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+// [next cursor, [member, score, ...]] like SCAN in score order, MATCH filters by member. A
+// missing key is an empty sorted set
+impl CommandExecutor for ZScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, members) = match backend.zscan(&self.key, self.cursor, self.count) {
+            Some(page) => page,
+            None if is_other_type(backend, &self.key, "zset") => return RESP_WRONGTYPE.clone(),
+            None => (0, vec![]),
+        };
+        let members = members
+            .into_iter()
+            .filter(|(member, _)| match &self.pattern {
+                Some(pattern) => glob_match(pattern.as_bytes(), member.as_bytes()),
+                None => true,
+            })
+            .flat_map(|(member, score)| {
+                [
+                    BulkString::from(member).into(),
+                    BulkString::from(score.to_string()).into(),
+                ]
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new([
+            BulkString::from(cursor.to_string()).into(),
+            RespArray::new(members).into(),
+        ])
+        .into()
+    }
+}
+
+// the reply is the flattened member/score pairs in pop order, a missing key replies an empty array
+fn zpop(backend: &Backend, key: &str, count: usize, max: bool) -> RespFrame {
+    if is_other_type(backend, key, "zset") {
+        return RESP_WRONGTYPE.clone();
+    }
+    let ret = backend
+        .zpop(key, count, max)
+        .into_iter()
+        .flat_map(|(member, score)| {
+            [
+                BulkString::from(member).into(),
+                BulkString::from(score.to_string()).into(),
+            ]
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(ret).into()
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 4 || !value.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "zadd command must have a key and score/member pairs".to_string(),
+            ));
+        }
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let key = args.next().unwrap_or_default();
+        let mut members = Vec::new();
+        while let (Some(score), Some(member)) = (args.next(), args.next()) {
+            members.push((parse_score(&score)?, member));
+        }
+        Ok(ZAdd { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMin {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_zpop(value, "zpopmin")?;
+        Ok(ZPopMin { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMax {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_zpop(value, "zpopmax")?;
+        Ok(ZPopMax { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZScan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, cursor, pattern, count) = parse_key_scan(value, "ZSCAN")?;
+        Ok(ZScan {
+            key,
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcard"], 1)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match args.next() {
+            Some(key) => Ok(ZCard { key }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcount"], 3)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(min), Some(max)) => Ok(ZCount {
+                key,
+                range: parse_score_range(&min, &max)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, min or max".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() < 3 {
+            return Err(CommandError::InvalidArgument(
+                "zrem command must have a key and at least one member".to_string(),
+            ));
+        }
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let key = args.next().unwrap_or_default();
+        Ok(ZRem {
+            key,
+            members: args.collect(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zremrangebyscore"], 3)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(min), Some(max)) => Ok(ZRemRangeByScore {
+                key,
+                range: parse_score_range(&min, &max)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, min or max".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByRank {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zremrangebyrank"], 3)?;
+
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(start), Some(stop)) => {
+                let rank = |s: String| {
+                    s.parse::<i64>().map_err(|_| {
+                        CommandError::InvalidArgument(
+                            "value is not an integer or out of range".to_string(),
+                        )
+                    })
+                };
+                Ok(ZRemRangeByRank {
+                    key,
+                    start: rank(start)?,
+                    stop: rank(stop)?,
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, start or stop".to_string(),
+            )),
+        }
+    }
+}
+
+// <key> <min> <max> [WITHSCORES] [LIMIT offset count]
+impl TryFrom<RespArray> for ZRangeByScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = value.into_strings()?.into_iter().skip(1);
+        let (Some(key), Some(min), Some(max)) = (args.next(), args.next(), args.next()) else {
+            return Err(CommandError::InvalidArgument(
+                "zrangebyscore command must have a key, min and max".to_string(),
+            ));
+        };
+        let mut cmd = ZRangeByScore {
+            key,
+            range: parse_score_range(score_shorthand(&min), score_shorthand(&max))?,
+            withscores: false,
+            limit: None,
+        };
+        while let Some(arg) = args.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "withscores" => cmd.withscores = true,
+                "limit" => {
+                    let mut int = || {
+                        args.next()
+                            .and_then(|v| v.parse::<i64>().ok())
+                            .ok_or_else(|| {
+                                CommandError::InvalidArgument(
+                                    "value is not an integer or out of range".to_string(),
+                                )
+                            })
+                    };
+                    cmd.limit = Some((int()?, int()?));
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+// <key> [count], count defaults to 1
+fn parse_zpop(value: RespArray, name: &str) -> Result<(String, usize), CommandError> {
+    if value.len() < 2 || value.len() > 3 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have a key and an optional count",
+            name
+        )));
+    }
+
+    let mut args = value.into_strings()?.into_iter().skip(1);
+    let key = args.next().unwrap_or_default();
+    let count = match args.next() {
+        Some(count) => count.parse::<usize>().map_err(|_| {
+            CommandError::InvalidArgument("value is out of range, must be positive".to_string())
+        })?,
+        None => 1,
+    };
+    Ok((key, count))
+}
+
+// min and max of a score range: a float, -inf or +inf, a leading ( makes the end exclusive
+fn parse_score_range(min: &str, max: &str) -> Result<ScoreRange, CommandError> {
+    let (min, min_exclusive) = parse_score_bound(min)?;
+    let (max, max_exclusive) = parse_score_bound(max)?;
+    Ok(ScoreRange {
+        min,
+        min_exclusive,
+        max,
+        max_exclusive,
+    })
+}
+
+fn parse_score_bound(s: &str) -> Result<(f64, bool), CommandError> {
+    let (s, exclusive) = match s.strip_prefix('(') {
+        Some(s) => (s, true),
+        None => (s, false),
+    };
+    match s.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok((score, exclusive)),
+        _ => Err(CommandError::InvalidArgument(
+            "min or max is not a float".to_string(),
+        )),
+    }
+}
+
+// ZRANGEBYSCORE also takes a bare - or + for -inf and +inf, the other score ranges don't
+fn score_shorthand(s: &str) -> &str {
+    match s {
+        "-" => "-inf",
+        "+" => "+inf",
+        s => s,
+    }
+}
+
+fn parse_score(s: &str) -> Result<f64, CommandError> {
+    match s.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok(score),
+        _ => Err(CommandError::InvalidArgument(
+            "value is not a valid float".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_zadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\nzadd\r\n$4\r\nzset\r\n$1\r\n1\r\n$1\r\na\r\n$4\r\n-inf\r\n$1\r\nb\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZAdd = frame.try_into()?;
+        assert_eq!(result.key, "zset");
+        assert_eq!(
+            result.members,
+            vec![(1.0, "a".to_string()), (f64::NEG_INFINITY, "b".to_string())]
+        );
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nzadd\r\n$4\r\nzset\r\n$3\r\nnan\r\n$1\r\na\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let ret: Result<ZAdd, _> = frame.try_into();
+        assert!(matches!(ret, Err(CommandError::InvalidArgument(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zpopmin_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\nzpopmin\r\n$4\r\nzset\r\n$1\r\n2\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ZPopMin = frame.try_into()?;
+        assert_eq!(result.key, "zset");
+        assert_eq!(result.count, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zscan_should_page_through_members() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nzscan\r\n$4\r\nzset\r\n$1\r\n0\r\n$5\r\ncount\r\n$1\r\n2\r\n",
+        );
+        let cmd: ZScan = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!((cmd.key.as_str(), cmd.cursor, cmd.count), ("zset", 0, 2));
+
+        let backend = Backend::new();
+        backend.zadd(
+            "zset".to_string(),
+            [(3.0, "c"), (1.0, "a"), (2.5, "b")].map(|(s, m)| (s, m.to_string())),
+        );
+        let page = |cursor, pattern: Option<&str>| {
+            ZScan {
+                key: "zset".to_string(),
+                cursor,
+                pattern: pattern.map(str::to_string),
+                count: 2,
+            }
+            .execute(&backend)
+        };
+        let reply = |cursor: &str, members: &[(&str, &str)]| -> RespFrame {
+            let members = members
+                .iter()
+                .flat_map(|(m, s)| [BulkString::from(*m).into(), BulkString::from(*s).into()])
+                .collect::<Vec<RespFrame>>();
+            RespArray::new([
+                BulkString::from(cursor).into(),
+                RespArray::new(members).into(),
+            ])
+            .into()
+        };
+        let members = [("a", "1"), ("b", "2.5"), ("c", "3")];
+        let (next, first) = backend.zscan("zset", 0, 2).unwrap();
+        let (first, rest): (Vec<_>, Vec<_>) = members
+            .into_iter()
+            .partition(|(m, _)| first.iter().any(|(f, _)| f == m));
+        let next = next.to_string();
+        assert_eq!(first.len(), 2);
+        assert_eq!(page(0, None), reply(&next, &first));
+        assert_eq!(page(next.parse()?, None), reply("0", &rest));
+        assert_eq!(page(0, Some(first[1].0)), reply(&next, &first[1..]));
+        assert_eq!(
+            ZScan {
+                key: "missing".to_string(),
+                cursor: 0,
+                pattern: None,
+                count: 2,
+            }
+            .execute(&backend),
+            reply("0", &[])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zpop_two_members() {
+        let backend = Backend::new();
+        let cmd = ZAdd {
+            key: "zset".to_string(),
+            members: vec![
+                (3.0, "c".to_string()),
+                (1.0, "a".to_string()),
+                (2.5, "b".to_string()),
+                (4.0, "d".to_string()),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(4));
+
+        let cmd = ZPopMin {
+            key: "zset".to_string(),
+            count: 2,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("a").into(),
+                BulkString::from("1").into(),
+                BulkString::from("b").into(),
+                BulkString::from("2.5").into(),
+            ])
+            .into()
+        );
+
+        let cmd = ZPopMax {
+            key: "zset".to_string(),
+            count: 2,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("d").into(),
+                BulkString::from("4").into(),
+                BulkString::from("c").into(),
+                BulkString::from("3").into(),
+            ])
+            .into()
+        );
+        assert_eq!(backend.key_type("zset"), None);
+    }
+
+    #[test]
+    fn test_zpop_missing_key_should_return_empty_array() {
+        let backend = Backend::new();
+        let cmd = ZPopMax {
+            key: "zset".to_string(),
+            count: 1,
+        };
+        assert_eq!(cmd.execute(&backend), RespArray::new([]).into());
+
+        backend.set("string".to_string(), BulkString::from("value").into());
+        let cmd = ZPopMin {
+            key: "string".to_string(),
+            count: 1,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+    }
+
+    fn zcount_backend() -> Backend {
+        let backend = Backend::new();
+        backend.zadd(
+            "zset".to_string(),
+            [
+                (1.0, "a".to_string()),
+                (2.0, "b".to_string()),
+                (3.0, "c".to_string()),
+                (4.0, "d".to_string()),
+            ],
+        );
+        backend
+    }
+
+    fn zcount(backend: &Backend, min: &str, max: &str) -> Result<RespFrame> {
+        let frame = RespArray::new([
+            BulkString::from("zcount").into(),
+            BulkString::from("zset").into(),
+            BulkString::from(min).into(),
+            BulkString::from(max).into(),
+        ]);
+        let cmd: ZCount = frame.try_into()?;
+        Ok(cmd.execute(backend))
+    }
+
+    #[test]
+    fn test_zcard_command() {
+        let backend = zcount_backend();
+        let zcard = |key: &str| {
+            ZCard {
+                key: key.to_string(),
+            }
+            .execute(&backend)
+        };
+        assert_eq!(zcard("zset"), RespFrame::Integer(4));
+        assert_eq!(zcard("missing"), RespFrame::Integer(0));
+    }
+
+    #[test]
+    fn test_zcount_inclusive_range() -> Result<()> {
+        let backend = zcount_backend();
+        assert_eq!(zcount(&backend, "2", "3")?, RespFrame::Integer(2));
+        assert_eq!(zcount(&backend, "-inf", "+inf")?, RespFrame::Integer(4));
+        assert_eq!(zcount(&backend, "3", "2")?, RespFrame::Integer(0));
+        assert_eq!(
+            ZCount {
+                key: "missing".to_string(),
+                range: parse_score_range("-inf", "+inf")?,
+            }
+            .execute(&backend),
+            RespFrame::Integer(0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zcount_exclusive_range() -> Result<()> {
+        let backend = zcount_backend();
+        assert_eq!(zcount(&backend, "(1", "4")?, RespFrame::Integer(3));
+        assert_eq!(zcount(&backend, "(1", "(4")?, RespFrame::Integer(2));
+        assert_eq!(zcount(&backend, "(2", "(3")?, RespFrame::Integer(0));
+        assert!(zcount(&backend, "(x", "4").is_err());
+        Ok(())
+    }
+
+    fn members(backend: &Backend, key: &str) -> Vec<String> {
+        backend
+            .db()
+            .zmap
+            .get(key)
+            .map(|zset| zset.iter().map(|(m, _)| m.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_zrem_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nzrem\r\n$4\r\nzset\r\n$1\r\na\r\n$1\r\nx\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: ZRem = frame.try_into()?;
+        assert_eq!(cmd.members, ["a", "x"]);
+
+        let backend = zcount_backend();
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(members(&backend, "zset"), ["b", "c", "d"]);
+        assert_eq!(
+            backend.db().zmap.get("zset").map(|v| v.score("a")),
+            Some(None)
+        );
+
+        // removing the last members deletes the key
+        let cmd = ZRem {
+            key: "zset".to_string(),
+            members: vec!["b".to_string(), "c".to_string(), "d".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        assert_eq!(backend.key_type("zset"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrangebyrank_command() -> Result<()> {
+        let frame = RespArray::new([
+            BulkString::from("zremrangebyrank").into(),
+            BulkString::from("zset").into(),
+            BulkString::from("0").into(),
+            BulkString::from("-3").into(),
+        ]);
+        let cmd: ZRemRangeByRank = frame.try_into()?;
+        assert_eq!((cmd.start, cmd.stop), (0, -3));
+
+        let backend = zcount_backend();
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(members(&backend, "zset"), ["c", "d"]);
+
+        let cmd = ZRemRangeByRank {
+            key: "zset".to_string(),
+            start: 0,
+            stop: -1,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.key_type("zset"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_score_shorthand_should_only_work_for_zrangebyscore() {
+        for name in ["zcount", "zremrangebyscore"] {
+            let frame = RespArray::new([
+                BulkString::from(name).into(),
+                BulkString::from("zset").into(),
+                BulkString::from("-").into(),
+                BulkString::from("+").into(),
+            ]);
+            let err = Command::try_from(frame).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                CommandError::InvalidArgument("min or max is not a float".to_string()).to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_zremrangebyscore_command() -> Result<()> {
+        let backend = zcount_backend();
+        let cmd = ZRemRangeByScore {
+            key: "zset".to_string(),
+            range: parse_score_range("(1", "3")?,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(members(&backend, "zset"), ["a", "d"]);
+        Ok(())
+    }
+
+    fn zrangebyscore(backend: &Backend, args: &[&str]) -> Result<Vec<String>> {
+        let frame = RespArray::new(
+            ["zrangebyscore", "zset"]
+                .iter()
+                .chain(args)
+                .map(|v| BulkString::from(*v).into())
+                .collect::<Vec<RespFrame>>(),
+        );
+        let cmd: ZRangeByScore = frame.try_into()?;
+        let RespFrame::Array(ret) = cmd.execute(backend) else {
+            anyhow::bail!("expected an array reply");
+        };
+        Ok(ret
+            .iter()
+            .map(|v| match v {
+                RespFrame::BulkString(v) => String::from_utf8_lossy(v).into_owned(),
+                v => format!("{:?}", v),
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_zrangebyscore_infinite_range() -> Result<()> {
+        let backend = zcount_backend();
+        assert_eq!(
+            zrangebyscore(&backend, &["-inf", "+inf"])?,
+            ["a", "b", "c", "d"]
+        );
+        assert_eq!(zrangebyscore(&backend, &["-", "+"])?, ["a", "b", "c", "d"]);
+        assert_eq!(
+            zrangebyscore(&backend, &["-inf", "2", "WITHSCORES"])?,
+            ["a", "1", "b", "2"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrangebyscore_exclusive_range() -> Result<()> {
+        let backend = zcount_backend();
+        assert_eq!(zrangebyscore(&backend, &["(1", "(4"])?, ["b", "c"]);
+        assert_eq!(zrangebyscore(&backend, &["(1", "+inf"])?, ["b", "c", "d"]);
+        assert!(zrangebyscore(&backend, &["(2", "(2"])?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrangebyscore_limit() -> Result<()> {
+        let backend = zcount_backend();
+        assert_eq!(
+            zrangebyscore(&backend, &["-inf", "+inf", "LIMIT", "1", "2"])?,
+            ["b", "c"]
+        );
+        assert_eq!(
+            zrangebyscore(&backend, &["-inf", "+inf", "limit", "2", "-1"])?,
+            ["c", "d"]
+        );
+        assert!(zrangebyscore(&backend, &["-inf", "+inf", "LIMIT", "-1", "2"])?.is_empty());
+        assert!(zrangebyscore(&backend, &["-inf", "+inf", "LIMIT", "1"]).is_err());
+        assert!(zrangebyscore(&backend, &["-inf", "+inf", "bogus"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrem_updates_zcard_and_type() -> Result<()> {
+        let backend = zcount_backend();
+        let zcard = |backend: &Backend| {
+            ZCard {
+                key: "zset".to_string(),
+            }
+            .execute(backend)
+        };
+        let key_type = |backend: &Backend| -> Result<RespFrame> {
+            let frame = RespArray::new([
+                BulkString::from("type").into(),
+                BulkString::from("zset").into(),
+            ]);
+            Ok(crate::cmd::Command::try_from(frame)?.execute(backend))
+        };
+        assert_eq!(key_type(&backend)?, crate::SimpleString::new("zset").into());
+
+        let cmd = ZRem {
+            key: "zset".to_string(),
+            members: vec!["a".to_string(), "a".to_string(), "b".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(zcard(&backend), RespFrame::Integer(2));
+        assert_eq!(
+            zrangebyscore(&backend, &["-inf", "+inf", "WITHSCORES"])?,
+            ["c", "3", "d", "4"]
+        );
+
+        let cmd = ZRem {
+            key: "zset".to_string(),
+            members: vec!["c".to_string(), "d".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(zcard(&backend), RespFrame::Integer(0));
+        assert_eq!(key_type(&backend)?, crate::SimpleString::new("none").into());
+        Ok(())
+    }
+}
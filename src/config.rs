@@ -0,0 +1,288 @@
+use crate::{network::NetworkConfig, BackendConfig, MaxMemoryPolicy};
+use std::{fs, path::Path, path::PathBuf, time::Duration};
+use thiserror::Error;
+
+/// Everything the server binary needs to start
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// TCP address to listen on
+    pub bind: String,
+    /// also accept connections on this unix domain socket path
+    pub unixsocket: Option<PathBuf>,
+    pub network: NetworkConfig,
+    pub backend: BackendConfig,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read the config file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("bad directive at line {line}: {msg}")]
+    Directive { line: usize, msg: String },
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:6379".to_string(),
+            unixsocket: None,
+            network: NetworkConfig::default(),
+            backend: BackendConfig::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Read a redis.conf style file, see `parse`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse "<directive> <args...>" lines on top of the defaults. Blank lines and lines starting
+    /// with # are skipped, an argument may be double quoted to hold spaces or be empty. Only the
+    /// directives the server supports are accepted, with the same names and units as redis
+    pub fn parse(s: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        let (mut host, mut port) = config
+            .bind
+            .rsplit_once(':')
+            .map(|(host, port)| (host.to_string(), port.to_string()))
+            .unwrap_or_default();
+        let mut dir = PathBuf::new();
+        let mut dbfilename = config.backend.dump_path.clone();
+        let mut appendfilename = config.backend.aof_path.clone();
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |msg: &str| ConfigError::Directive {
+                line: i + 1,
+                msg: msg.to_string(),
+            };
+            let args = split_args(line).ok_or_else(|| err("unbalanced quotes"))?;
+            let name = args[0].to_ascii_lowercase();
+            let value = match args.as_slice() {
+                [_, value] => value.as_str(),
+                [_, old, new] if name == "rename-command" => {
+                    config
+                        .backend
+                        .rename_commands
+                        .insert(old.to_ascii_lowercase(), new.to_ascii_lowercase());
+                    continue;
+                }
+                // redis may bind several addresses, only the first one is used
+                [_, first, ..] if name == "bind" => first.as_str(),
+                _ => return Err(err("wrong number of arguments")),
+            };
+            match name.as_str() {
+                "bind" => host = value.to_string(),
+                "port" => port = value.to_string(),
+                "unixsocket" => config.unixsocket = Some(PathBuf::from(value)),
+                "maxclients" => {
+                    config.network.maxclients =
+                        parse_number(value).ok_or_else(|| err("invalid maxclients"))?
+                }
+                "requirepass" => {
+                    config.backend.requirepass = (!value.is_empty()).then(|| value.to_string())
+                }
+                "maxmemory" => {
+                    config.backend.maxmemory =
+                        parse_memory(value).ok_or_else(|| err("invalid maxmemory"))?
+                }
+                "maxmemory-policy" => {
+                    config.backend.maxmemory_policy = value
+                        .parse::<MaxMemoryPolicy>()
+                        .map_err(|e| err(&e.to_string()))?
+                }
+                "busy-reply-threshold" => {
+                    let ms =
+                        parse_number(value).ok_or_else(|| err("invalid busy-reply-threshold"))?;
+                    config.backend.command_timeout =
+                        (ms > 0).then(|| Duration::from_millis(ms as u64));
+                }
+                "tcp-keepalive" => {
+                    let secs = parse_number(value).ok_or_else(|| err("invalid tcp-keepalive"))?;
+                    config.network.tcp_keepalive =
+                        (secs > 0).then(|| Duration::from_secs(secs as u64));
+                }
+                // not a redis directive, redis always sets TCP_NODELAY on client sockets
+                "tcp-nodelay" => {
+                    config.network.tcp_nodelay =
+                        parse_bool(value).ok_or_else(|| err("argument must be 'yes' or 'no'"))?
+                }
+                // like redis, the limit of a bulk string is also the limit of a string value
+                "proto-max-bulk-len" => {
+                    config.network.max_bulk_len =
+                        parse_memory(value).ok_or_else(|| err("invalid proto-max-bulk-len"))?;
+                    config.backend.max_string_size = config.network.max_bulk_len;
+                }
+                "hash-max-listpack-entries" => {
+                    config.backend.hash_max_listpack_entries = parse_number(value)
+                        .ok_or_else(|| err("invalid hash-max-listpack-entries"))?
+                }
+                // there is no replication, the flag applies to the server itself
+                "replica-read-only" => {
+                    config.backend.read_only =
+                        parse_bool(value).ok_or_else(|| err("argument must be 'yes' or 'no'"))?
+                }
+                "appendonly" => {
+                    config.backend.appendonly =
+                        parse_bool(value).ok_or_else(|| err("argument must be 'yes' or 'no'"))?
+                }
+                "databases" => {
+                    config.backend.databases = parse_number(value)
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| err("invalid number of databases"))?
+                }
+                "appendfilename" => appendfilename = PathBuf::from(value),
+                "dbfilename" => dbfilename = PathBuf::from(value),
+                "dir" => dir = PathBuf::from(value),
+                _ => return Err(err(&format!("unknown directive '{}'", args[0]))),
+            }
+        }
+
+        config.bind = format!("{}:{}", host, port);
+        config.backend.dump_path = dir.join(dbfilename);
+        config.backend.aof_path = dir.join(appendfilename);
+        Ok(config)
+    }
+}
+
+// split on whitespace, a double quoted argument keeps its spaces. None for a missing closing quote
+fn split_args(line: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            args.push(quoted[..end].to_string());
+            rest = quoted[end + 1..].trim_start();
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            args.push(rest[..end].to_string());
+            rest = rest[end..].trim_start();
+        }
+    }
+    Some(args)
+}
+
+fn parse_number(s: &str) -> Option<usize> {
+    s.parse().ok()
+}
+
+// a byte count with an optional unit like redis: k, m, g are powers of 1000, kb, mb, gb of 1024
+pub(crate) fn parse_memory(s: &str) -> Option<usize> {
+    let s = s.to_ascii_lowercase();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let unit = match &s[digits..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    s[..digits].parse::<usize>().ok()?.checked_mul(unit)
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.to_ascii_lowercase().as_str() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_load_config_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("simple-redis-{}.conf", std::process::id()));
+        fs::write(
+            &path,
+            r#"
+# a small config
+bind 127.0.0.1 ::1
+port 7000
+maxclients 50
+requirepass "secret pass"
+maxmemory 100mb
+maxmemory-policy allkeys-lru
+busy-reply-threshold 500
+tcp-keepalive 0
+tcp-nodelay no
+databases 4
+replica-read-only yes
+appendonly yes
+dir /var/lib/redis
+appendfilename "data.aof"
+rename-command FLUSHALL ""
+"#,
+        )?;
+        let config = ServerConfig::load(&path)?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(config.bind, "127.0.0.1:7000");
+        assert_eq!(config.network.maxclients, 50);
+        assert_eq!(config.network.tcp_keepalive, None);
+        assert!(!config.network.tcp_nodelay);
+        assert_eq!(config.backend.databases, 4);
+        assert_eq!(config.backend.requirepass.as_deref(), Some("secret pass"));
+        assert_eq!(config.backend.maxmemory, 100 * 1024 * 1024);
+        assert_eq!(config.backend.maxmemory_policy, MaxMemoryPolicy::AllKeysLru);
+        assert_eq!(
+            config.backend.command_timeout,
+            Some(Duration::from_millis(500))
+        );
+        assert!(config.backend.appendonly);
+        assert!(config.backend.read_only);
+        assert_eq!(
+            config.backend.aof_path,
+            PathBuf::from("/var/lib/redis/data.aof")
+        );
+        assert_eq!(
+            config.backend.dump_path,
+            PathBuf::from("/var/lib/redis/dump.rdb")
+        );
+        assert_eq!(
+            config
+                .backend
+                .rename_commands
+                .get("flushall")
+                .map(String::as_str),
+            Some("")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_errors() {
+        let config = ServerConfig::parse("").unwrap();
+        assert_eq!(config.bind, ServerConfig::default().bind);
+
+        for bad in [
+            "maxmemory lots",
+            "port",
+            "unknown 1",
+            "requirepass \"open",
+            "appendonly maybe",
+            "databases 0",
+        ] {
+            assert!(
+                matches!(
+                    ServerConfig::parse(bad),
+                    Err(ConfigError::Directive { line: 1, .. })
+                ),
+                "{}",
+                bad
+            );
+        }
+    }
+}
@@ -2,7 +2,9 @@ mod backend;
 mod resp;
 mod respv2;
 
+pub mod client;
 pub mod cmd;
+pub mod config;
 pub mod network;
 
 pub use backend::*;
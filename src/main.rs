@@ -1,30 +1,59 @@
 use anyhow::Result;
-use simple_redis::{network, Backend};
-use tokio::net::TcpListener;
-use tracing::{info, warn};
+use simple_redis::{config::ServerConfig, network, Backend};
+use std::{fs, io, time::Duration};
+use tokio::{
+    net::{TcpListener, UnixListener},
+    time,
+};
+use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
-    let addr = "0.0.0.0:6379";
-    info!("Simple-Redis-Server is listening on {}", addr);
-    let listener = TcpListener::bind(addr).await?;
+    // an optional redis.conf style file as the only argument
+    let config = match std::env::args().nth(1) {
+        Some(path) => ServerConfig::load(path)?,
+        None => ServerConfig::default(),
+    };
+    let backend = Backend::with_config(config.backend);
 
-    let backend = Backend::new();
-    loop {
-        let (stream, raddr) = listener.accept().await?;
-        info!("Accepted connection from: {}", raddr);
-        let cloned_backend = backend.clone();
-        tokio::spawn(async move {
-            match network::stream_handler(stream, cloned_backend).await {
-                Ok(_) => {
-                    info!("Connection from {} exited", raddr);
-                }
-                Err(e) => {
-                    warn!("handle error for {}: {:?}", raddr, e);
-                }
-            }
-        });
+    // the data of the last run comes back first, from the AOF when it's on since it has every
+    // write, from the dump file otherwise
+    let loaded = if backend.aof_enabled() {
+        backend.load_aof()?
+    } else {
+        backend.load_dump()?
+    };
+    info!("Loaded {} entries from disk", loaded);
+
+    // expired keys nobody looks up again are deleted in the background, 10 times a second
+    // like redis' default hz
+    let sweeper = backend.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_millis(100));
+        loop {
+            interval.tick().await;
+            sweeper.sweep_expired();
+        }
+    });
+
+    if let Some(path) = config.unixsocket {
+        // a socket file left behind by a previous run would make the bind fail
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        info!("Simple-Redis-Server is listening on {}", path.display());
+        let listener = UnixListener::bind(&path)?;
+        tokio::spawn(network::serve_unix(
+            listener,
+            backend.clone(),
+            config.network.clone(),
+        ));
     }
+
+    info!("Simple-Redis-Server is listening on {}", config.bind);
+    let listener = TcpListener::bind(&config.bind).await?;
+    network::serve(listener, backend, config.network).await
 }
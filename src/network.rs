@@ -1,16 +1,76 @@
 use crate::{
-    cmd::{Command, CommandExecutor},
-    Backend, RespDecodeV2, RespEncode, RespError, RespFrame,
+    cmd::{
+        lookup_command, rename_command, Auth, Command, CommandExecutor, DebugCommand, Hello,
+        PSubscribe, PUnsubscribe, Select, Subscribe, Unsubscribe,
+    },
+    Backend, BulkString, RespArray, RespDecodeV2, RespEncode, RespError, RespFrame, RespMap,
+    SimpleError, SimpleString, Subscriber,
 };
 use anyhow::Result;
-use futures::SinkExt;
-use tokio::net::TcpStream;
+use bytes::{Bytes, BytesMut};
+use futures::{FutureExt, SinkExt};
+use socket2::{SockRef, TcpKeepalive};
+use std::{
+    collections::HashSet,
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UnixListener},
+    sync::mpsc,
+};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    /// disable Nagle's algorithm, so small replies are not delayed waiting for more data
+    pub tcp_nodelay: bool,
+    /// send keepalive probes once the connection has been idle for this long
+    pub tcp_keepalive: Option<Duration>,
+    /// most arguments a single command may have, in the array or the inline form.
+    /// A client going over it gets a protocol error and is disconnected
+    pub max_command_args: usize,
+    /// longest bulk string a client may send, like proto-max-bulk-len in redis
+    pub max_bulk_len: usize,
+    /// most commands a MULTI may queue. Going over it aborts the transaction, so a client
+    /// can't grow the queue without bounds
+    pub max_transaction_commands: usize,
+    /// most clients connected at once, a connection over it gets an error and is closed
+    pub maxclients: usize,
+    /// bytes reserved up front for the replies of a connection. Replies are encoded straight
+    /// into this buffer and it is reused for the whole connection instead of allocating per reply
+    pub write_buffer_size: usize,
+    /// a write buffer that grew past this for a large reply is given back once it's flushed and
+    /// a fresh one of `write_buffer_size` takes its place, so an idle connection stays small
+    pub max_write_buffer_size: usize,
+}
+
+// longest inline command line, same as redis
+const INLINE_MAX_SIZE: usize = 64 * 1024;
 
 #[derive(Debug)]
-struct RespFrameCodec;
+struct RespFrameCodec {
+    max_args: usize,
+    max_bulk_len: usize,
+    // protocol the replies are encoded in, follows the HELLO of the connection
+    protocol: i64,
+}
+
+// the client broke the protocol, it gets the error as a reply and the connection is closed
+#[derive(Debug, Error)]
+#[error("ERR Protocol error: {0}")]
+struct ProtocolError(String);
+
+// the client hung up in the middle of a frame, what it sent of it is dropped
+#[derive(Debug, Error)]
+#[error("connection closed with {0} bytes of a partial frame")]
+struct PartialFrame(usize);
 
 #[derive(Debug)]
 struct RedisRequest {
@@ -20,44 +80,745 @@ struct RedisRequest {
 
 #[derive(Debug)]
 struct RedisResponse {
-    frame: RespFrame,
+    // most commands reply with a single frame, SUBSCRIBE replies once per channel
+    frames: Vec<RespFrame>,
+    // close the connection once the response is flushed
+    close: bool,
+}
+
+// commands a connection may still run while it has active subscriptions (RESP2 semantics)
+const SUBSCRIBED_COMMANDS: [&[u8]; 6] = [
+    b"subscribe",
+    b"unsubscribe",
+    b"psubscribe",
+    b"punsubscribe",
+    b"ping",
+    b"quit",
+];
+
+// state of a client connection that lives across requests
+struct Connection {
+    id: u64,
+    backend: Backend,
+    // published messages for this connection are sent through it
+    subscriber: Subscriber,
+    channels: HashSet<String>,
+    patterns: HashSet<String>,
+    // commands queued since MULTI, None outside of a transaction
+    transaction: Option<Transaction>,
+    max_transaction_commands: usize,
+    // passed AUTH, only checked when the server has a requirepass
+    authenticated: bool,
+    // RESP version picked with HELLO, connections start out on RESP2 like redis
+    protocol: i64,
+}
+
+// commands queued by MULTI until EXEC runs them
+#[derive(Debug, Default)]
+struct Transaction {
+    // each command with its AOF entry if it's a write command
+    commands: Vec<(Command, Option<Vec<u8>>)>,
+    // a command failed to queue, EXEC discards the whole transaction like redis
+    aborted: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            tcp_nodelay: true,
+            tcp_keepalive: Some(Duration::from_secs(300)),
+            max_command_args: 1024 * 1024,
+            max_bulk_len: 512 * 1024 * 1024,
+            max_transaction_commands: 100_000,
+            maxclients: 10_000,
+            write_buffer_size: 16 * 1024,
+            max_write_buffer_size: 1024 * 1024,
+        }
+    }
+}
+
+pub async fn serve(listener: TcpListener, backend: Backend, config: NetworkConfig) -> Result<()> {
+    loop {
+        let (stream, raddr) = listener.accept().await?;
+        info!("Accepted connection from: {}", raddr);
+        if let Err(e) = configure_stream(&stream, &config) {
+            warn!("failed to set socket options for {}: {:?}", raddr, e);
+        }
+        let cloned_backend = backend.clone();
+        let cloned_config = config.clone();
+        tokio::spawn(async move {
+            match stream_handler_with_config(stream, cloned_backend, cloned_config).await {
+                Ok(_) => {
+                    info!("Connection from {} exited", raddr);
+                }
+                Err(e) => {
+                    warn!("handle error for {}: {:?}", raddr, e);
+                }
+            }
+        });
+    }
+}
+
+/// Accept connections on a unix domain socket, they are handled exactly like TCP connections
+pub async fn serve_unix(
+    listener: UnixListener,
+    backend: Backend,
+    config: NetworkConfig,
+) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        info!("Accepted unix socket connection");
+        let cloned_backend = backend.clone();
+        let cloned_config = config.clone();
+        tokio::spawn(async move {
+            match stream_handler_with_config(stream, cloned_backend, cloned_config).await {
+                Ok(_) => {
+                    info!("Unix socket connection exited");
+                }
+                Err(e) => {
+                    warn!("handle error for unix socket connection: {:?}", e);
+                }
+            }
+        });
+    }
+}
+
+fn configure_stream(stream: &TcpStream, config: &NetworkConfig) -> io::Result<()> {
+    stream.set_nodelay(config.tcp_nodelay)?;
+    if let Some(time) = config.tcp_keepalive {
+        let keepalive = TcpKeepalive::new().with_time(time);
+        SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+    }
+    Ok(())
+}
+
+// keeps the connected clients count in sync on every exit path of a connection, including panics
+struct ConnectionGuard {
+    backend: Backend,
+}
+
+impl ConnectionGuard {
+    fn new(backend: &Backend) -> Self {
+        backend.connected_clients.fetch_add(1, Ordering::Relaxed);
+        Self {
+            backend: backend.clone(),
+        }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.backend
+            .connected_clients
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Connection {
+    fn new(backend: &Backend, subscriber: Subscriber, max_transaction_commands: usize) -> Self {
+        Self {
+            id: backend.next_client_id(),
+            backend: backend.clone(),
+            subscriber,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            transaction: None,
+            max_transaction_commands,
+            authenticated: false,
+            protocol: 2,
+        }
+    }
+
+    fn needs_auth(&self) -> bool {
+        !self.authenticated && self.backend.requirepass().is_some()
+    }
+
+    fn auth(&mut self, cmd: Auth) -> RespFrame {
+        let Some(password) = self.backend.requirepass() else {
+            return SimpleError::new(
+                "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?",
+            )
+            .into();
+        };
+        let default_user = cmd.username.as_deref().is_none_or(|u| u == "default");
+        if default_user && cmd.password == password {
+            self.authenticated = true;
+            return SimpleString::new("OK").into();
+        }
+        SimpleError::new("WRONGPASS invalid username-password pair or user is disabled.").into()
+    }
+
+    fn execute(&mut self, cmd: Command, aof_entry: Option<Vec<u8>>) -> Vec<RespFrame> {
+        let backend = self.backend.clone();
+        // a command looks its database up more than once, SWAPDB and DEBUG RELOAD wait until
+        // none is running so they all stay on the database they started on. MOVE too, a save
+        // running meanwhile could find the key in both databases or in neither
+        if matches!(
+            cmd,
+            Command::SwapDb(_) | Command::Move(_) | Command::Debug(DebugCommand::Reload)
+        ) {
+            let _guard = backend
+                .transaction_lock
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            return self.run(cmd, aof_entry);
+        }
+        let _guard = backend
+            .transaction_lock
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        self.run(cmd, aof_entry)
+    }
+
+    // execute without taking the transaction lock, the caller holds it
+    fn run(&mut self, cmd: Command, aof_entry: Option<Vec<u8>>) -> Vec<RespFrame> {
+        match cmd {
+            Command::Subscribe(cmd) => self.subscribe(cmd),
+            Command::Unsubscribe(cmd) => self.unsubscribe(cmd),
+            Command::PSubscribe(cmd) => self.psubscribe(cmd),
+            Command::PUnsubscribe(cmd) => self.punsubscribe(cmd),
+            Command::Select(cmd) => vec![self.select(cmd)],
+            Command::Hello(cmd) => vec![self.hello(cmd)],
+            cmd => match aof_entry {
+                Some(entry) => vec![self.backend.execute_logged(cmd, entry)],
+                None => vec![cmd.execute(&self.backend)],
+            },
+        }
+    }
+
+    // the connection keeps a handle on the selected database, the other handles are unaffected
+    fn select(&mut self, cmd: Select) -> RespFrame {
+        let backend = usize::try_from(cmd.index)
+            .ok()
+            .and_then(|index| self.backend.select(index));
+        match backend {
+            Some(backend) => {
+                self.backend = backend;
+                SimpleString::new("OK").into()
+            }
+            None => SimpleError::new("ERR DB index is out of range").into(),
+        }
+    }
+
+    // the reply is already encoded in the protocol the client switched to
+    fn hello(&mut self, cmd: Hello) -> RespFrame {
+        let protocol = cmd.protover.unwrap_or(self.protocol);
+        if !(2..=3).contains(&protocol) {
+            return SimpleError::new("NOPROTO unsupported protocol version").into();
+        }
+        match cmd.auth {
+            Some(auth) => {
+                let ret = self.auth(auth);
+                if matches!(ret, RespFrame::Error(_)) {
+                    return ret;
+                }
+            }
+            None if self.needs_auth() => {
+                return SimpleError::new(
+                    "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time",
+                )
+                .into();
+            }
+            None => {}
+        }
+        self.protocol = protocol;
+
+        let mut map = RespMap::new();
+        map.insert("server".to_string(), BulkString::from("redis").into());
+        map.insert("version".to_string(), BulkString::from("7.2.0").into());
+        map.insert("proto".to_string(), protocol.into());
+        map.insert("id".to_string(), (self.id as i64).into());
+        map.insert("mode".to_string(), BulkString::from("standalone").into());
+        map.insert("role".to_string(), BulkString::from("master").into());
+        map.insert("modules".to_string(), RespArray::new([]).into());
+        map.into()
+    }
+
+    fn multi(&mut self) -> RespFrame {
+        if self.transaction.is_some() {
+            return SimpleError::new("ERR MULTI calls can not be nested").into();
+        }
+        self.transaction = Some(Transaction::default());
+        SimpleString::new("OK").into()
+    }
+
+    fn queue(&mut self, cmd: Command, aof_entry: Option<Vec<u8>>) -> RespFrame {
+        let max = self.max_transaction_commands;
+        let tx = self.transaction.get_or_insert_with(Transaction::default);
+        if tx.commands.len() >= max {
+            self.abort_transaction();
+            return SimpleError::new(format!(
+                "ERR Transaction exceeds the limit of {} queued commands",
+                max
+            ))
+            .into();
+        }
+        // EXEC runs the queued commands under the transaction lock, a sleep there would hold
+        // up every client and block a worker thread
+        if matches!(cmd, Command::Debug(DebugCommand::Sleep { .. })) {
+            self.abort_transaction();
+            return SimpleError::new("ERR DEBUG SLEEP is not allowed in MULTI").into();
+        }
+        // an aborted transaction never runs, there is no point in keeping its commands
+        if !tx.aborted {
+            tx.commands.push((cmd, aof_entry));
+        }
+        SimpleString::new("QUEUED").into()
+    }
+
+    // an error while queueing makes EXEC discard the transaction
+    fn abort_transaction(&mut self) {
+        if let Some(tx) = self.transaction.as_mut() {
+            tx.aborted = true;
+            tx.commands.clear();
+        }
+    }
+
+    // the replies of the queued commands in one array
+    fn exec(&mut self) -> RespFrame {
+        let Some(tx) = self.transaction.take() else {
+            return SimpleError::new("ERR EXEC without MULTI").into();
+        };
+        if tx.aborted {
+            return SimpleError::new("EXECABORT Transaction discarded because of previous errors.")
+                .into();
+        }
+        // the queued commands run back to back, other clients wait until they're all done
+        let backend = self.backend.clone();
+        let _guard = backend
+            .transaction_lock
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        let replies = tx
+            .commands
+            .into_iter()
+            .flat_map(|(cmd, aof_entry)| self.run(cmd, aof_entry))
+            .collect::<Vec<_>>();
+        RespArray::new(replies).into()
+    }
+
+    fn discard(&mut self) -> RespFrame {
+        match self.transaction.take() {
+            Some(_) => SimpleString::new("OK").into(),
+            None => SimpleError::new("ERR DISCARD without MULTI").into(),
+        }
+    }
+
+    // channels and patterns both count, like redis
+    fn subscriptions(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    fn subscribe(&mut self, cmd: Subscribe) -> Vec<RespFrame> {
+        let mut replies = Vec::with_capacity(cmd.channels.len());
+        for channel in cmd.channels {
+            self.backend
+                .subscribe(&channel, self.id, self.subscriber.clone());
+            self.channels.insert(channel.clone());
+            replies.push(Subscribe::reply(&channel, self.subscriptions()));
+        }
+        replies
+    }
+
+    fn unsubscribe(&mut self, cmd: Unsubscribe) -> Vec<RespFrame> {
+        let channels = if cmd.channels.is_empty() {
+            self.channels.iter().cloned().collect()
+        } else {
+            cmd.channels
+        };
+        if channels.is_empty() {
+            return vec![Unsubscribe::reply(None, self.subscriptions())];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                self.backend.unsubscribe(&channel, self.id);
+                self.channels.remove(&channel);
+                Unsubscribe::reply(Some(&channel), self.subscriptions())
+            })
+            .collect()
+    }
+
+    fn psubscribe(&mut self, cmd: PSubscribe) -> Vec<RespFrame> {
+        let mut replies = Vec::with_capacity(cmd.patterns.len());
+        for pattern in cmd.patterns {
+            self.backend
+                .psubscribe(&pattern, self.id, self.subscriber.clone());
+            self.patterns.insert(pattern.clone());
+            replies.push(PSubscribe::reply(&pattern, self.subscriptions()));
+        }
+        replies
+    }
+
+    fn punsubscribe(&mut self, cmd: PUnsubscribe) -> Vec<RespFrame> {
+        let patterns = if cmd.patterns.is_empty() {
+            self.patterns.iter().cloned().collect()
+        } else {
+            cmd.patterns
+        };
+        if patterns.is_empty() {
+            return vec![PUnsubscribe::reply(None, self.subscriptions())];
+        }
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                self.backend.punsubscribe(&pattern, self.id);
+                self.patterns.remove(&pattern);
+                PUnsubscribe::reply(Some(&pattern), self.subscriptions())
+            })
+            .collect()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        for channel in self.channels.iter() {
+            self.backend.unsubscribe(channel, self.id);
+        }
+        for pattern in self.patterns.iter() {
+            self.backend.punsubscribe(pattern, self.id);
+        }
+    }
+}
+
+pub async fn stream_handler<S>(stream: S, backend: Backend) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream_handler_with_config(stream, backend, NetworkConfig::default()).await
+}
+
+pub async fn stream_handler_with_config<S>(
+    mut stream: S,
+    backend: Backend,
+    config: NetworkConfig,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let _guard = ConnectionGuard::new(&backend);
+    if backend.connected_clients() > config.maxclients {
+        warn!(
+            "Rejecting a connection over maxclients {}",
+            config.maxclients
+        );
+        stream
+            .write_all(b"-ERR max number of clients reached\r\n")
+            .await?;
+        return Ok(());
+    }
+    match handle_stream(stream, &backend, &config).await {
+        Err(e) if is_disconnected(&e) => {
+            debug!("Client disconnected: {:?}", e);
+            Ok(())
+        }
+        Err(e) if e.is::<PartialFrame>() => {
+            info!("Client disconnected: {}", e);
+            Ok(())
+        }
+        ret => ret,
+    }
 }
 
-pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
+async fn handle_stream<S>(stream: S, backend: &Backend, config: &NetworkConfig) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (tx, rx) = mpsc::channel(backend.config.pubsub_buffer_limit.max(1));
+    let subscriber = Subscriber::new(tx);
+    let conn = Connection::new(backend, subscriber.clone(), config.max_transaction_commands);
+    tokio::select! {
+        ret = process_stream(stream, conn, rx, config) => ret,
+        // dropping the connection future closes the stream even while it's stuck in a write
+        _ = subscriber.evicted() => {
+            warn!("Closing a subscriber that can't keep up with the published messages");
+            Ok(())
+        }
+    }
+}
+
+async fn process_stream<S>(
+    stream: S,
+    mut conn: Connection,
+    mut rx: mpsc::Receiver<Bytes>,
+    config: &NetworkConfig,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     // how to get a frame from the stream?
-    let mut framed = Framed::new(stream, RespFrameCodec);
+    let codec = RespFrameCodec {
+        max_args: config.max_command_args,
+        max_bulk_len: config.max_bulk_len,
+        protocol: conn.protocol,
+    };
+    let mut framed = Framed::new(stream, codec);
+    framed.write_buffer_mut().reserve(config.write_buffer_size);
     loop {
-        match framed.next().await {
-            Some(Ok(frame)) => {
-                info!("Received frame: {:?}", frame);
-                let request = RedisRequest {
-                    frame,
-                    backend: backend.clone(),
+        tokio::select! {
+            frame = framed.next() => {
+                let Some(frame) = frame else {
+                    break;
+                };
+                let mut close = match frame {
+                    Ok(frame) => feed_response(&mut framed, frame, &mut conn).await?,
+                    Err(e) => reply_protocol_error(&mut framed, e).await?,
                 };
-                let response = request_handler(request).await?;
-                info!("Sending response: {:?}", response.frame);
-                framed.send(response.frame).await?;
+
+                // pipelined requests which are already readable get answered in the same batch,
+                // so the responses are flushed with one write instead of one write per response
+                while !close {
+                    close = match framed.next().now_or_never() {
+                        Some(Some(Ok(frame))) => feed_response(&mut framed, frame, &mut conn).await?,
+                        Some(Some(Err(e))) => reply_protocol_error(&mut framed, e).await?,
+                        _ => break,
+                    };
+                }
+                framed.flush().await?;
+                recycle_write_buffer(framed.write_buffer_mut(), config);
+                if close {
+                    break;
+                }
+            }
+            Some(message) = rx.recv() => {
+                // messages published to the subscribed channels, batched the same way. They are
+                // encoded already, so they go straight to the write buffer
+                framed.write_buffer_mut().extend_from_slice(&message);
+                while let Ok(message) = rx.try_recv() {
+                    framed.write_buffer_mut().extend_from_slice(&message);
+                }
+                framed.flush().await?;
+                recycle_write_buffer(framed.write_buffer_mut(), config);
             }
-            Some(Err(e)) => return Err(e),
-            None => return Ok(()),
         }
     }
+    Ok(())
+}
+
+// a flushed buffer keeps its allocation for the next replies, unless a large reply made it grow
+// past the limit
+fn recycle_write_buffer(buf: &mut BytesMut, config: &NetworkConfig) {
+    if buf.is_empty() && buf.capacity() > config.max_write_buffer_size {
+        *buf = BytesMut::with_capacity(config.write_buffer_size);
+    }
+}
+
+// a protocol error is answered before the connection is closed, other errors end it right away.
+// Returns whether the connection should be closed, which is always the case
+async fn reply_protocol_error<S>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    e: anyhow::Error,
+) -> Result<bool>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let e = e.downcast::<ProtocolError>()?;
+    warn!("Closing a connection after a protocol error: {}", e);
+    framed.feed(SimpleError::new(e.to_string()).into()).await?;
+    Ok(true)
+}
+
+// the peer went away, this is a normal end of the connection rather than a server error
+fn is_disconnected(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<io::Error>().is_some_and(|e| {
+        matches!(
+            e.kind(),
+            io::ErrorKind::BrokenPipe
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::UnexpectedEof
+        )
+    })
 }
 
-async fn request_handler(request: RedisRequest) -> Result<RedisResponse> {
+// queue the response in the write buffer, it only hits the stream when the buffer is full
+// or when the batch is flushed. Returns whether the connection should be closed afterwards
+async fn feed_response<S>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    frame: RespFrame,
+    conn: &mut Connection,
+) -> Result<bool>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    info!("Received frame: {:?}", frame);
+    let request = RedisRequest {
+        frame,
+        backend: conn.backend.clone(),
+    };
+    let response = request_handler(request, conn).await?;
+    framed.codec_mut().protocol = conn.protocol;
+    for frame in response.frames {
+        info!("Sending response: {:?}", frame);
+        framed.feed(frame).await?;
+    }
+    Ok(response.close)
+}
+
+async fn request_handler(request: RedisRequest, conn: &mut Connection) -> Result<RedisResponse> {
     let (frame, backend) = (request.frame, request.backend);
-    let cmd = Command::try_from(frame)?;
+    // from here on the command goes by its original name, that's also how the AOF records it
+    let frame = match rename_command(frame, &backend.config.rename_commands) {
+        Ok(frame) => frame,
+        Err(e) => {
+            conn.abort_transaction();
+            return Ok(RedisResponse {
+                frames: vec![e.into()],
+                close: false,
+            });
+        }
+    };
+    if conn.needs_auth() && !is_auth_exempt(&frame) {
+        conn.abort_transaction();
+        return Ok(RedisResponse {
+            frames: vec![SimpleError::new("NOAUTH Authentication required.").into()],
+            close: false,
+        });
+    }
+    if conn.subscriptions() > 0 {
+        if let Some(err) = check_subscribed_command(&frame) {
+            return Ok(RedisResponse {
+                frames: vec![err],
+                close: false,
+            });
+        }
+    }
+
+    if backend.read_only() && is_write_command(&frame) {
+        conn.abort_transaction();
+        return Ok(RedisResponse {
+            frames: vec![
+                SimpleError::new("READONLY You can't write against a read only replica").into(),
+            ],
+            close: false,
+        });
+    }
+
+    if is_denyoom_command(&frame) {
+        if let Err(e) = backend.free_memory() {
+            conn.abort_transaction();
+            return Ok(RedisResponse {
+                frames: vec![SimpleError::new(e.to_string()).into()],
+                close: false,
+            });
+        }
+    }
+
+    // write commands are logged to the AOF in the form they were received
+    let aof_entry =
+        (backend.aof_enabled() && is_write_command(&frame)).then(|| frame.clone().encode());
+    let name = command_name(&frame).unwrap_or_default();
+    // an invalid command is answered with the error, the connection stays usable
+    let cmd = match Command::try_from(frame) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            conn.abort_transaction();
+            return Ok(RedisResponse {
+                frames: vec![e.into()],
+                close: false,
+            });
+        }
+    };
     info!("Executing command: {:?}", cmd);
-    let frame = cmd.execute(&backend);
-    Ok(RedisResponse { frame })
+    if let (Command::Debug(DebugCommand::Sleep { duration, blocking }), None) =
+        (&cmd, &conn.transaction)
+    {
+        debug_sleep(*duration, *blocking).await?;
+        return Ok(RedisResponse {
+            frames: vec![SimpleString::new("OK").into()],
+            close: false,
+        });
+    }
+    let close = matches!(cmd, Command::Quit(_));
+    // a panicking command is answered with an error instead of taking the connection down
+    let frames = panic::catch_unwind(AssertUnwindSafe(|| match cmd {
+        Command::Auth(cmd) => vec![conn.auth(cmd)],
+        Command::Multi(_) => vec![conn.multi()],
+        Command::Exec(_) => vec![conn.exec()],
+        Command::Discard(_) => vec![conn.discard()],
+        // QUIT isn't queued, it closes the connection right away
+        cmd if conn.transaction.is_some() && !close => vec![conn.queue(cmd, aof_entry)],
+        cmd => conn.execute(cmd, aof_entry),
+    }))
+    .unwrap_or_else(|e| {
+        let msg = e
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| e.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        warn!(
+            "Command '{}' panicked: {}",
+            String::from_utf8_lossy(&name),
+            msg
+        );
+        vec![SimpleError::new("ERR internal error").into()]
+    });
+    Ok(RedisResponse { frames, close })
+}
+
+// DEBUG SLEEP only holds up its own connection. The blocking variant ties up a thread of the
+// blocking pool the way a slow synchronous command would, the other one just waits on a timer
+async fn debug_sleep(duration: Duration, blocking: bool) -> Result<()> {
+    if blocking {
+        tokio::task::spawn_blocking(move || std::thread::sleep(duration)).await?;
+    } else {
+        tokio::time::sleep(duration).await;
+    }
+    Ok(())
+}
+
+// the lowercase command name of a request
+fn command_name(frame: &RespFrame) -> Option<Vec<u8>> {
+    match frame {
+        RespFrame::Array(array) => match array.first() {
+            Some(RespFrame::BulkString(name)) => Some(name.to_ascii_lowercase()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_write_command(frame: &RespFrame) -> bool {
+    command_name(frame)
+        .and_then(|name| lookup_command(&String::from_utf8_lossy(&name)))
+        .is_some_and(|spec| spec.is_write())
+}
+
+// commands a client may run before it authenticated, HELLO checks its AUTH option itself
+fn is_auth_exempt(frame: &RespFrame) -> bool {
+    command_name(frame).is_some_and(|name| name == b"auth" || name == b"hello" || name == b"quit")
+}
+
+fn is_denyoom_command(frame: &RespFrame) -> bool {
+    command_name(frame)
+        .and_then(|name| lookup_command(&String::from_utf8_lossy(&name)))
+        .is_some_and(|spec| spec.is_denyoom())
+}
+
+// reject the commands that are not allowed in subscribe mode
+fn check_subscribed_command(frame: &RespFrame) -> Option<RespFrame> {
+    let name = command_name(frame)?;
+    if SUBSCRIBED_COMMANDS.contains(&name.as_slice()) {
+        return None;
+    }
+    Some(
+        SimpleError::new(format!(
+            "ERR Can't execute '{}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING / QUIT are allowed in this context",
+            String::from_utf8_lossy(&name)
+        ))
+        .into(),
+    )
 }
 
 impl Encoder<RespFrame> for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: RespFrame, dst: &mut bytes::BytesMut) -> Result<()> {
-        let encoded = item.encode();
-        dst.extend_from_slice(&encoded);
+        if self.protocol < 3 {
+            item.into_resp2().encode_to(dst);
+        } else {
+            item.encode_to(dst);
+        }
         Ok(())
     }
 }
@@ -67,10 +828,975 @@ impl Decoder for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
+        match src.first() {
+            None => return Ok(None),
+            Some(b'*') => self.check_array_len(src)?,
+            Some(prefix) if !RESP_PREFIXES.contains(prefix) => return self.decode_inline(src),
+            Some(_) => {}
+        }
         match RespFrame::decode(src) {
             Ok(frame) => Ok(Some(frame)),
-            Err(RespError::NotComplete) => Ok(None),
+            Err(RespError::NotComplete) => {
+                // make room for the whole frame up front, so a large value is read in big chunks
+                // instead of growing the buffer a little on every read
+                if let Some(len) = self.expected_len(src)? {
+                    src.reserve(len.saturating_sub(src.len()));
+                }
+                Ok(None)
+            }
+            Err(RespError::ProtocolError(msg)) => Err(ProtocolError(msg).into()),
             Err(e) => Err(e.into()),
         }
     }
+
+    // the stream is done, leftover bytes can never become a complete frame
+    fn decode_eof(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            None => {
+                let len = src.len();
+                src.clear();
+                Err(PartialFrame(len).into())
+            }
+        }
+    }
+}
+
+// the length of a `<prefix><n>\r\n` header and its n, None if it isn't complete or valid
+fn parse_header(src: &[u8], prefix: u8) -> Option<(usize, usize)> {
+    if src.first() != Some(&prefix) {
+        return None;
+    }
+    let end = src.windows(2).position(|v| v == b"\r\n")?;
+    let n = std::str::from_utf8(&src[1..end]).ok()?.parse().ok()?;
+    Some((end + 2, n))
+}
+
+// first bytes of the RESP frames, anything else starts an inline command
+const RESP_PREFIXES: &[u8] = b"+-:$*_#,%~";
+
+impl RespFrameCodec {
+    // reject an oversized array from its header alone, before any element is parsed
+    fn check_array_len(&self, src: &[u8]) -> Result<()> {
+        let Some(end) = src.windows(2).position(|v| v == b"\r\n") else {
+            return Ok(());
+        };
+        let len = String::from_utf8_lossy(&src[1..end]);
+        match len.parse::<i64>() {
+            Ok(len) if len <= self.max_args as i64 => Ok(()),
+            _ => Err(ProtocolError("invalid multibulk length".to_string()).into()),
+        }
+    }
+
+    // a lower bound of the length of the incomplete request at the start of `src`, from the
+    // headers of the bulk strings received so far. Oversized bulk strings are rejected here,
+    // before their data is buffered
+    fn expected_len(&self, src: &[u8]) -> Result<Option<usize>> {
+        let Some((mut total, count)) = parse_header(src, b'*') else {
+            return Ok(None);
+        };
+        for _ in 0..count {
+            let Some((header, len)) = parse_header(&src[total..], b'$') else {
+                return Ok(None);
+            };
+            if len > self.max_bulk_len {
+                return Err(ProtocolError("invalid bulk length".to_string()).into());
+            }
+            total += header + len + 2;
+            if total > src.len() {
+                return Ok(Some(total));
+            }
+        }
+        Ok(Some(total))
+    }
+
+    // an inline command is a single line of space separated arguments, like `PING` from telnet
+    fn decode_inline(&self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
+        loop {
+            let Some(end) = src.iter().position(|b| *b == b'\n') else {
+                if src.len() > INLINE_MAX_SIZE {
+                    return Err(ProtocolError("too big inline request".to_string()).into());
+                }
+                return Ok(None);
+            };
+            let line = src.split_to(end + 1);
+            let args = line
+                .as_ref()
+                .split(|b: &u8| b.is_ascii_whitespace())
+                .filter(|arg| !arg.is_empty())
+                .collect::<Vec<_>>();
+            if args.len() > self.max_args {
+                return Err(ProtocolError("too many inline arguments".to_string()).into());
+            }
+            // empty lines are skipped, like redis
+            if args.is_empty() {
+                if src.is_empty() {
+                    return Ok(None);
+                }
+                continue;
+            }
+            let frames = args
+                .into_iter()
+                .map(|arg| BulkString::new(arg.to_vec()).into())
+                .collect::<Vec<RespFrame>>();
+            return Ok(Some(RespArray::new(frames).into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendConfig, BulkString, RespArray, RespNullBulkString};
+    use bytes::BytesMut;
+    use std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize},
+            Arc,
+        },
+        task::{Context, Poll},
+    };
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream, ReadBuf};
+
+    // counts the write calls the handler issues to the underlying stream
+    struct CountingStream {
+        inner: DuplexStream,
+        writes: Arc<AtomicUsize>,
+    }
+
+    impl AsyncRead for CountingStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for CountingStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.writes.fetch_add(1, Ordering::SeqCst);
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    fn command(args: &[&str]) -> Vec<u8> {
+        let frames = args
+            .iter()
+            .map(|arg| BulkString::from(*arg).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(frames).encode()
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_responses_are_flushed_in_batch() -> Result<()> {
+        let (mut client, server) = duplex(64 * 1024);
+        let writes = Arc::new(AtomicUsize::new(0));
+        let stream = CountingStream {
+            inner: server,
+            writes: writes.clone(),
+        };
+        let handle = tokio::spawn(stream_handler(stream, Backend::new()));
+
+        let mut pipeline = Vec::new();
+        for i in 0..50 {
+            pipeline.extend(command(&[
+                "set",
+                &format!("key{}", i),
+                &format!("value{}", i),
+            ]));
+            pipeline.extend(command(&["get", &format!("key{}", i)]));
+        }
+        client.write_all(&pipeline).await?;
+
+        let mut buf = BytesMut::new();
+        let mut responses = Vec::new();
+        while responses.len() < 100 {
+            client.read_buf(&mut buf).await?;
+            while let Ok(frame) = RespFrame::decode(&mut buf) {
+                responses.push(frame);
+            }
+        }
+
+        for (i, pair) in responses.chunks(2).enumerate() {
+            assert_eq!(pair[0], RespFrame::SimpleString("OK".into()));
+            assert_eq!(pair[1], BulkString::from(format!("value{}", i)).into());
+        }
+        // one write per response would be 100 writes
+        assert!(writes.load(Ordering::SeqCst) < 10);
+
+        drop(client);
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_dropped_mid_response_should_end_connection() -> Result<()> {
+        let (mut client, server) = duplex(64);
+        let backend = Backend::new();
+        backend.set("key".to_string(), BulkString::new(vec![b'x'; 4096]).into());
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+
+        client.write_all(&command(&["get", "key"])).await?;
+        let mut buf = [0u8; 16];
+        client.read_exact(&mut buf).await?;
+        assert_eq!(backend.connected_clients(), 1);
+        drop(client);
+
+        handle.await??;
+        assert_eq!(backend.connected_clients(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_configure_stream_should_apply_socket_options() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let _client = TcpStream::connect(addr).await?;
+        let (stream, _) = listener.accept().await?;
+
+        let config = NetworkConfig {
+            tcp_nodelay: true,
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        configure_stream(&stream, &config)?;
+
+        let sock = SockRef::from(&stream);
+        assert!(sock.nodelay()?);
+        assert!(sock.keepalive()?);
+        assert_eq!(sock.keepalive_time()?, Duration::from_secs(60));
+
+        let config = NetworkConfig {
+            tcp_nodelay: false,
+            tcp_keepalive: None,
+            ..Default::default()
+        };
+        configure_stream(&stream, &config)?;
+        assert!(!sock.nodelay()?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quit_should_close_connection_after_reply() -> Result<()> {
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, Backend::new()));
+
+        // the get after quit is never answered
+        let mut pipeline = command(&["quit"]);
+        pipeline.extend(command(&["get", "key"]));
+        client.write_all(&pipeline).await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"+OK\r\n");
+
+        handle.await??;
+        Ok(())
+    }
+
+    async fn read_frame(client: &mut DuplexStream, buf: &mut BytesMut) -> Result<RespFrame> {
+        loop {
+            if let Ok(frame) = RespFrame::decode(buf) {
+                return Ok(frame);
+            }
+            client.read_buf(buf).await?;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_connection_should_reject_other_commands() -> Result<()> {
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, Backend::new()));
+        let mut buf = BytesMut::new();
+
+        client.write_all(&command(&["subscribe", "a", "b"])).await?;
+        for (i, channel) in ["a", "b"].iter().enumerate() {
+            let expected = RespArray::new([
+                BulkString::from("subscribe").into(),
+                BulkString::from(*channel).into(),
+                RespFrame::Integer(i as i64 + 1),
+            ]);
+            assert_eq!(read_frame(&mut client, &mut buf).await?, expected.into());
+        }
+
+        client.write_all(&command(&["GET", "key"])).await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            SimpleError::new(
+                "ERR Can't execute 'get': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING / QUIT are allowed in this context"
+            )
+            .into()
+        );
+
+        // leaving every channel ends subscribe mode
+        client.write_all(&command(&["unsubscribe"])).await?;
+        read_frame(&mut client, &mut buf).await?;
+        read_frame(&mut client, &mut buf).await?;
+        client.write_all(&command(&["get", "key"])).await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            RespNullBulkString.into()
+        );
+
+        drop(client);
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_published_message_should_reach_subscribed_connection() -> Result<()> {
+        let backend = Backend::new();
+        let (mut subscriber, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+        let (mut publisher, server) = duplex(1024);
+        tokio::spawn(stream_handler(server, backend.clone()));
+        let mut buf = BytesMut::new();
+
+        subscriber
+            .write_all(&command(&["subscribe", "news"]))
+            .await?;
+        read_frame(&mut subscriber, &mut buf).await?;
+
+        let mut pub_buf = BytesMut::new();
+        publisher
+            .write_all(&command(&["publish", "news", "hello"]))
+            .await?;
+        assert_eq!(
+            read_frame(&mut publisher, &mut pub_buf).await?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            read_frame(&mut subscriber, &mut buf).await?,
+            RespArray::new([
+                BulkString::from("message").into(),
+                BulkString::from("news").into(),
+                BulkString::from("hello").into(),
+            ])
+            .into()
+        );
+
+        // the subscription goes away with the connection
+        drop(subscriber);
+        handle.await??;
+        assert!(backend.channels.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_should_be_disconnected() -> Result<()> {
+        let backend = Backend::with_config(BackendConfig {
+            pubsub_buffer_limit: 8,
+            ..Default::default()
+        });
+        let (mut subscriber, server) = duplex(64);
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+        let mut buf = BytesMut::new();
+
+        subscriber
+            .write_all(&command(&["subscribe", "news"]))
+            .await?;
+        read_frame(&mut subscriber, &mut buf).await?;
+
+        // the subscriber never reads again, the writes get stuck once the pipe is full
+        let message: RespFrame = BulkString::new(vec![b'x'; 32]).into();
+        for _ in 0..1000 {
+            if handle.is_finished() {
+                break;
+            }
+            backend.publish("news", message.clone());
+            tokio::task::yield_now().await;
+        }
+        assert!(handle.is_finished());
+        handle.await??;
+        assert!(backend.channels.is_empty());
+        assert_eq!(backend.publish("news", message), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_over_maxmemory_should_reply_oom() -> Result<()> {
+        let backend = Backend::with_config(BackendConfig {
+            maxmemory: 1,
+            ..Default::default()
+        });
+        backend.set("key".to_string(), BulkString::from("value").into());
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, backend));
+        let mut buf = BytesMut::new();
+
+        client
+            .write_all(&command(&["set", "other", "value"]))
+            .await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            SimpleError::new("OOM command not allowed when used memory > 'maxmemory'.").into()
+        );
+
+        // reads and deletes still go through
+        client.write_all(&command(&["get", "key"])).await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            BulkString::from("value").into()
+        );
+        client.write_all(&command(&["del", "key"])).await?;
+        assert_eq!(read_frame(&mut client, &mut buf).await?, 1.into());
+
+        drop(client);
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_too_many_arguments_should_close_connection() -> Result<()> {
+        let config = NetworkConfig {
+            max_command_args: 3,
+            ..Default::default()
+        };
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler_with_config(server, Backend::new(), config));
+
+        let mut pipeline = command(&["mset", "a", "1"]);
+        pipeline.extend(command(&["mset", "a", "1", "b", "2"]));
+        pipeline.extend(command(&["get", "a"]));
+        client.write_all(&pipeline).await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(
+            buf,
+            b"+OK\r\n-ERR Protocol error: invalid multibulk length\r\n"
+        );
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_inline_commands() -> Result<()> {
+        let config = NetworkConfig {
+            max_command_args: 3,
+            ..Default::default()
+        };
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler_with_config(server, Backend::new(), config));
+
+        client
+            .write_all(b"PING\r\n\r\nset  key value\nget key\r\nset a b c d\r\nget key\r\n")
+            .await?;
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(
+            buf,
+            b"+PONG\r\n+OK\r\n$5\r\nvalue\r\n-ERR Protocol error: too many inline arguments\r\n"
+        );
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_string_over_limit_should_close_connection() -> Result<()> {
+        let config = NetworkConfig {
+            max_bulk_len: 16,
+            ..Default::default()
+        };
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler_with_config(server, Backend::new(), config));
+
+        // rejected from the header, the data of the value is never sent
+        client.write_all(&command(&["set", "a", "1"])).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nset\r\n$1\r\nb\r\n$17\r\n")
+            .await?;
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"+OK\r\n-ERR Protocol error: invalid bulk length\r\n");
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_malformed_frame_should_close_connection() -> Result<()> {
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, Backend::new()));
+
+        // the value is longer than declared, nothing after it can be trusted
+        let mut pipeline = command(&["set", "a", "1"]);
+        pipeline.extend_from_slice(b"*2\r\n$3\r\nget\r\n$1\r\nab\r\n");
+        pipeline.extend(command(&["get", "a"]));
+        client.write_all(&pipeline).await?;
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        let buf = String::from_utf8(buf)?;
+        assert!(buf.starts_with("+OK\r\n-ERR Protocol error: "), "{}", buf);
+        assert!(!buf.contains("$1\r\n1\r\n"));
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_should_reserve_for_incomplete_request() -> Result<()> {
+        let mut codec = RespFrameCodec {
+            max_args: 16,
+            max_bulk_len: 1024 * 1024,
+            protocol: 2,
+        };
+        let mut src = BytesMut::from("*3\r\n$3\r\nset\r\n$1\r\na\r\n$100000\r\nxx");
+        assert!(codec.decode(&mut src)?.is_none());
+        assert!(src.capacity() >= 100_000);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_closed_mid_frame_should_end_connection() -> Result<()> {
+        let (mut client, server) = duplex(1024);
+        let backend = Backend::new();
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+
+        client.write_all(&command(&["set", "a", "1"])).await?;
+        client.write_all(b"*3\r\n$3\r\nset\r\n$1\r\nb").await?;
+        let mut buf = [0u8; 5];
+        client.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"+OK\r\n");
+        drop(client);
+
+        tokio::time::timeout(Duration::from_secs(1), handle).await???;
+        assert_eq!(backend.connected_clients(), 0);
+        assert_eq!(backend.get("b"), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_command_should_reply_error() -> Result<()> {
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, Backend::new()));
+
+        let mut pipeline = command(&["get"]);
+        pipeline.extend(command(&["ping"]));
+        client.write_all(&pipeline).await?;
+        client.shutdown().await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert!(buf.starts_with(b"-ERR "));
+        assert!(buf.ends_with(b"\r\n+PONG\r\n"));
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_renamed_commands() -> Result<()> {
+        let backend = Backend::with_config(BackendConfig {
+            rename_commands: [
+                ("flushall".to_string(), "".to_string()),
+                ("get".to_string(), "fetch".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        });
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, backend));
+
+        let mut pipeline = command(&["set", "key", "value"]);
+        pipeline.extend(command(&["FLUSHALL"]));
+        pipeline.extend(command(&["get", "key"]));
+        pipeline.extend(command(&["FETCH", "key"]));
+        client.write_all(&pipeline).await?;
+        client.shutdown().await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(
+            String::from_utf8(buf)?,
+            "+OK\r\n-ERR unknown command 'flushall'\r\n-ERR unknown command 'get'\r\n$5\r\nvalue\r\n"
+        );
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_should_run_queued_commands_on_exec() -> Result<()> {
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, Backend::new()));
+
+        let mut pipeline = command(&["multi"]);
+        pipeline.extend(command(&["set", "a", "1"]));
+        pipeline.extend(command(&["get", "a"]));
+        pipeline.extend(command(&["exec"]));
+        pipeline.extend(command(&["exec"]));
+        pipeline.extend(command(&["quit"]));
+        client.write_all(&pipeline).await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(
+            buf,
+            b"+OK\r\n+QUEUED\r\n+QUEUED\r\n*2\r\n+OK\r\n$1\r\n1\r\n-ERR EXEC without MULTI\r\n+OK\r\n"
+        );
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_transaction_should_not_interleave_with_other_clients() -> Result<()> {
+        let backend = Backend::new();
+        let done = Arc::new(AtomicBool::new(false));
+        // another client keeps overwriting the key while the transactions run
+        let (mut writer, server) = duplex(64 * 1024);
+        let writer_handle = tokio::spawn(stream_handler(server, backend.clone()));
+        let writes = tokio::spawn({
+            let done = done.clone();
+            async move {
+                let mut batch = Vec::new();
+                for _ in 0..50 {
+                    batch.extend(command(&["set", "key", "z"]));
+                }
+                let mut replies = vec![0; 50 * 5];
+                while !done.load(Ordering::SeqCst) {
+                    writer.write_all(&batch).await?;
+                    writer.read_exact(&mut replies).await?;
+                }
+                writer.write_all(&command(&["quit"])).await?;
+                anyhow::Ok(())
+            }
+        });
+
+        for _ in 0..200 {
+            let (mut client, server) = duplex(64 * 1024);
+            let handle = tokio::spawn(stream_handler(server, backend.clone()));
+            let mut pipeline = command(&["multi"]);
+            pipeline.extend(command(&["set", "key", "a"]));
+            for _ in 0..50 {
+                pipeline.extend(command(&["append", "key", "b"]));
+            }
+            pipeline.extend(command(&["get", "key"]));
+            pipeline.extend(command(&["exec"]));
+            pipeline.extend(command(&["quit"]));
+            client.write_all(&pipeline).await?;
+
+            let mut buf = Vec::new();
+            client.read_to_end(&mut buf).await?;
+            let expected = format!("$51\r\na{}\r\n+OK\r\n", "b".repeat(50));
+            assert!(String::from_utf8(buf)?.ends_with(&expected));
+            handle.await??;
+        }
+
+        done.store(true, Ordering::SeqCst);
+        writes.await??;
+        writer_handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_over_limit_should_abort() -> Result<()> {
+        let config = NetworkConfig {
+            max_transaction_commands: 2,
+            ..Default::default()
+        };
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler_with_config(server, Backend::new(), config));
+        let mut buf = BytesMut::new();
+
+        client.write_all(&command(&["multi"])).await?;
+        assert_eq!(read_frame(&mut client, &mut buf).await?, "OK".into());
+        for key in ["a", "b"] {
+            client.write_all(&command(&["set", key, "1"])).await?;
+            assert_eq!(read_frame(&mut client, &mut buf).await?, "QUEUED".into());
+        }
+        client.write_all(&command(&["set", "c", "1"])).await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            SimpleError::new("ERR Transaction exceeds the limit of 2 queued commands").into()
+        );
+        client.write_all(&command(&["exec"])).await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            SimpleError::new("EXECABORT Transaction discarded because of previous errors.").into()
+        );
+
+        // nothing ran and the connection is out of the transaction
+        client.write_all(&command(&["get", "a"])).await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            RespNullBulkString.into()
+        );
+
+        drop(client);
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_debug_sleep_in_transaction_should_abort() -> Result<()> {
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, Backend::new()));
+        let mut buf = BytesMut::new();
+
+        client.write_all(&command(&["multi"])).await?;
+        assert_eq!(read_frame(&mut client, &mut buf).await?, "OK".into());
+        client.write_all(&command(&["set", "a", "1"])).await?;
+        assert_eq!(read_frame(&mut client, &mut buf).await?, "QUEUED".into());
+        client
+            .write_all(&command(&["debug", "sleep", "10"]))
+            .await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            SimpleError::new("ERR DEBUG SLEEP is not allowed in MULTI").into()
+        );
+        client.write_all(&command(&["exec"])).await?;
+        assert_eq!(
+            read_frame(&mut client, &mut buf).await?,
+            SimpleError::new("EXECABORT Transaction discarded because of previous errors.").into()
+        );
+
+        drop(client);
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_requirepass_should_need_auth() -> Result<()> {
+        let backend = Backend::with_config(BackendConfig {
+            requirepass: Some("secret".to_string()),
+            ..Default::default()
+        });
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, backend));
+
+        let mut pipeline = command(&["get", "a"]);
+        pipeline.extend(command(&["auth", "wrong"]));
+        pipeline.extend(command(&["auth", "default", "secret"]));
+        pipeline.extend(command(&["get", "a"]));
+        pipeline.extend(command(&["quit"]));
+        client.write_all(&pipeline).await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(
+            buf,
+            b"-NOAUTH Authentication required.\r\n-WRONGPASS invalid username-password pair or user is disabled.\r\n+OK\r\n$-1\r\n+OK\r\n"
+        );
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connection_over_maxclients_should_be_rejected() -> Result<()> {
+        let backend = Backend::new();
+        let config = NetworkConfig {
+            maxclients: 1,
+            ..Default::default()
+        };
+        let (mut first, server) = duplex(1024);
+        tokio::spawn(stream_handler_with_config(
+            server,
+            backend.clone(),
+            config.clone(),
+        ));
+        first.write_all(&command(&["ping"])).await?;
+        let mut buf = BytesMut::new();
+        read_frame(&mut first, &mut buf).await?;
+
+        let (mut second, server) = duplex(1024);
+        stream_handler_with_config(server, backend.clone(), config).await?;
+        let mut buf = Vec::new();
+        second.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"-ERR max number of clients reached\r\n");
+        assert_eq!(backend.connected_clients(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_panicking_command_should_reply_internal_error() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("a".to_string(), BulkString::from("1").into());
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, backend));
+
+        let mut pipeline = command(&["debug", "panic"]);
+        pipeline.extend(command(&["get", "a"]));
+        pipeline.extend(command(&["quit"]));
+        client.write_all(&pipeline).await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"-ERR internal error\r\n$1\r\n1\r\n+OK\r\n");
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_only_should_reject_write_commands() -> Result<()> {
+        let backend = Backend::with_config(BackendConfig {
+            read_only: true,
+            ..Default::default()
+        });
+        backend.set("a".to_string(), BulkString::from("1").into());
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+
+        let mut pipeline = command(&["set", "a", "2"]);
+        pipeline.extend(command(&["get", "a"]));
+        pipeline.extend(command(&["del", "a"]));
+        pipeline.extend(command(&["quit"]));
+        client.write_all(&pipeline).await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        let readonly = "-READONLY You can't write against a read only replica\r\n";
+        assert_eq!(
+            String::from_utf8(buf)?,
+            format!("{}$1\r\n1\r\n{}+OK\r\n", readonly, readonly)
+        );
+        assert_eq!(backend.get("a"), Some(BulkString::from("1").into()));
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_only_can_be_toggled_at_runtime() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("a".to_string(), BulkString::from("1").into());
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+
+        let mut pipeline = command(&["config", "set", "replica-read-only", "yes"]);
+        pipeline.extend(command(&["set", "a", "2"]));
+        pipeline.extend(command(&["get", "a"]));
+        pipeline.extend(command(&["config", "set", "replica-read-only", "no"]));
+        pipeline.extend(command(&["set", "a", "3"]));
+        pipeline.extend(command(&["quit"]));
+        client.write_all(&pipeline).await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(
+            String::from_utf8(buf)?,
+            "+OK\r\n-READONLY You can't write against a read only replica\r\n$1\r\n1\r\n+OK\r\n+OK\r\n+OK\r\n"
+        );
+        assert!(!backend.read_only());
+        assert_eq!(backend.get("a"), Some(BulkString::from("3").into()));
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_should_switch_reply_encoding() -> Result<()> {
+        let backend = Backend::with_config(BackendConfig {
+            requirepass: Some("secret".to_string()),
+            ..Default::default()
+        });
+        let (mut client, server) = duplex(4096);
+        let handle = tokio::spawn(stream_handler(server, backend));
+
+        let mut pipeline = command(&["hello", "3"]);
+        pipeline.extend(command(&["hello", "4", "auth", "default", "secret"]));
+        pipeline.extend(command(&["hello", "2", "auth", "default", "secret"]));
+        pipeline.extend(command(&["get", "a"]));
+        pipeline.extend(command(&["hello", "3"]));
+        pipeline.extend(command(&["get", "a"]));
+        client.write_all(&pipeline).await?;
+        client.shutdown().await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        let buf = String::from_utf8(buf)?;
+        let (noauth, rest) = buf.split_once("\r\n").unwrap();
+        assert!(noauth.starts_with("-NOAUTH HELLO must be called"));
+        let rest = rest
+            .strip_prefix("-NOPROTO unsupported protocol version\r\n")
+            .unwrap();
+        // RESP2 flattens the map of HELLO into an array and spells null as a null bulk string
+        let (hello2, rest) = rest.split_once("$-1\r\n").unwrap();
+        assert!(hello2.starts_with("*14\r\n$2\r\nid\r\n"));
+        assert!(hello2.contains("$5\r\nproto\r\n:2\r\n"));
+        assert!(rest.starts_with("%7\r\n+id\r\n"));
+        assert!(rest.contains("+proto\r\n:3\r\n"));
+        assert!(rest.ends_with("+version\r\n$5\r\n7.2.0\r\n_\r\n"));
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swapdb_should_move_data_under_connections() -> Result<()> {
+        let backend = Backend::new();
+        let (mut client, server) = duplex(1024);
+        let handle = tokio::spawn(stream_handler(server, backend.clone()));
+
+        let mut pipeline = command(&["set", "key", "value"]);
+        pipeline.extend(command(&["swapdb", "0", "1"]));
+        pipeline.extend(command(&["get", "key"]));
+        pipeline.extend(command(&["select", "1"]));
+        pipeline.extend(command(&["get", "key"]));
+        pipeline.extend(command(&["select", "16"]));
+        client.write_all(&pipeline).await?;
+        client.shutdown().await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert_eq!(
+            String::from_utf8(buf)?,
+            "+OK\r\n+OK\r\n$-1\r\n+OK\r\n$5\r\nvalue\r\n-ERR DB index is out of range\r\n"
+        );
+        assert_eq!(backend.get("key"), None);
+        assert!(backend.select(1).unwrap().get("key").is_some());
+
+        handle.await??;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_buffer_should_be_reused_until_it_grows_too_large() {
+        let config = NetworkConfig {
+            write_buffer_size: 1024,
+            max_write_buffer_size: 4096,
+            ..Default::default()
+        };
+        let mut buf = BytesMut::with_capacity(config.write_buffer_size);
+        let ptr = buf.as_ptr();
+        for _ in 0..100 {
+            RespFrame::from(BulkString::from("value")).encode_to(&mut buf);
+            buf.clear();
+            recycle_write_buffer(&mut buf, &config);
+        }
+        // every reply went into the same allocation
+        assert_eq!(buf.as_ptr(), ptr);
+
+        RespFrame::from(BulkString::new(vec![b'x'; 8192])).encode_to(&mut buf);
+        recycle_write_buffer(&mut buf, &config);
+        assert!(
+            buf.capacity() > config.max_write_buffer_size,
+            "not flushed yet"
+        );
+        buf.clear();
+        recycle_write_buffer(&mut buf, &config);
+        assert!(buf.capacity() >= config.write_buffer_size);
+        assert!(buf.capacity() <= config.max_write_buffer_size);
+    }
 }
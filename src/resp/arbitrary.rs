@@ -0,0 +1,103 @@
+use crate::{
+    BulkString, RespArray, RespFrame, RespMap, RespNull, RespNullArray, RespNullBulkString,
+    RespSet, SimpleError, SimpleString,
+};
+use proptest::prelude::*;
+
+// simple strings, errors and map keys are line based and can't contain CR or LF
+const LINE: &str = "[^\r\n]*";
+
+// NaN never compares equal to itself, it has its own round trip tests in double.rs
+fn arb_double() -> impl Strategy<Value = f64> {
+    prop::num::f64::NORMAL
+        | prop::num::f64::SUBNORMAL
+        | prop::num::f64::ZERO
+        | prop::num::f64::INFINITE
+}
+
+/// Frames of every variant, aggregates are nested up to 4 levels deep
+impl Arbitrary for RespFrame {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<RespFrame>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        let leaf = prop_oneof![
+            LINE.prop_map(|s| SimpleString::new(s).into()),
+            LINE.prop_map(|s| SimpleError::new(s).into()),
+            any::<i64>().prop_map(RespFrame::Integer),
+            any::<Vec<u8>>().prop_map(|v| BulkString::new(v).into()),
+            Just(RespNullBulkString.into()),
+            Just(RespNullArray.into()),
+            Just(RespNull.into()),
+            any::<bool>().prop_map(RespFrame::Boolean),
+            arb_double().prop_map(RespFrame::Double),
+        ];
+        leaf.prop_recursive(4, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(|v| RespArray::new(v).into()),
+                prop::collection::vec(inner.clone(), 0..8).prop_map(|v| RespSet::new(v).into()),
+                prop::collection::btree_map(LINE, inner, 0..8)
+                    .prop_map(|v| RespMap::from(v).into()),
+            ]
+        })
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespDecode, RespDecodeV2, RespEncode};
+    use bytes::BytesMut;
+    use proptest::test_runner::TestRunner;
+    use std::mem::discriminant;
+
+    proptest! {
+        #[test]
+        fn test_frame_round_trip_through_v1(frame in any::<RespFrame>()) {
+            let encoded = frame.clone().encode();
+            let mut buf = BytesMut::from(encoded.as_slice());
+            prop_assert_eq!(<RespFrame as RespDecode>::expect_length(&buf)?, encoded.len());
+            prop_assert_eq!(<RespFrame as RespDecode>::decode(&mut buf)?, frame);
+            prop_assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn test_frame_round_trip_through_v2(frame in any::<RespFrame>()) {
+            let encoded = frame.clone().encode();
+            let mut buf = BytesMut::from(encoded.as_slice());
+            prop_assert_eq!(<RespFrame as RespDecodeV2>::expect_length(&buf)?, encoded.len());
+            prop_assert_eq!(<RespFrame as RespDecodeV2>::decode(&mut buf)?, frame);
+            prop_assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_frame_should_cover_all_variants() {
+        let variants: [RespFrame; 12] = [
+            SimpleString::new("").into(),
+            SimpleError::new("").into(),
+            RespFrame::Integer(0),
+            BulkString::new(vec![]).into(),
+            RespNullBulkString.into(),
+            RespArray::new([]).into(),
+            RespNullArray.into(),
+            RespNull.into(),
+            RespFrame::Boolean(false),
+            RespFrame::Double(0.0),
+            RespMap::new().into(),
+            RespSet::new([]).into(),
+        ];
+
+        let mut runner = TestRunner::deterministic();
+        let strategy = any::<RespFrame>();
+        let mut seen = vec![false; variants.len()];
+        for _ in 0..2000 {
+            let frame = strategy.new_tree(&mut runner).unwrap().current();
+            for (i, v) in variants.iter().enumerate() {
+                seen[i] |= discriminant(v) == discriminant(&frame);
+            }
+        }
+        assert!(seen.iter().all(|v| *v), "{:?}", seen);
+    }
+}
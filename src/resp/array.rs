@@ -14,13 +14,12 @@ pub struct RespNullArray;
 
 // - array: "*<number-of-elements>\r\n<element-1>...<element-n>"
 impl RespEncode for RespArray {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(BUF_CAP);
         buf.extend_from_slice(&format!("*{}\r\n", self.0.len()).into_bytes());
         for frame in self.0 {
-            buf.extend_from_slice(&frame.encode());
+            frame.encode_to(buf);
         }
-        buf
     }
 }
 
@@ -55,8 +54,8 @@ impl RespDecode for RespArray {
 
 // - null array: "*-1\r\n"
 impl RespEncode for RespNullArray {
-    fn encode(self) -> Vec<u8> {
-        b"*-1\r\n".to_vec()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"*-1\r\n");
     }
 }
 
@@ -68,7 +67,7 @@ impl RespDecode for RespNullArray {
     }
 
     fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
-        Ok(4)
+        Ok(5)
     }
 }
 
@@ -95,7 +94,7 @@ impl From<Vec<RespFrame>> for RespArray {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::BulkString;
+    use crate::{BulkString, RespMap, RespSet};
     use anyhow::Result;
 
     #[test]
@@ -147,4 +146,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_nested_mixed_array_round_trip() -> Result<()> {
+        let mut map = RespMap::new();
+        map.insert(
+            "set".to_string(),
+            RespSet::new([
+                RespArray::new([BulkString::from("a").into(), 1.into()]).into(),
+                BulkString::from("b").into(),
+            ])
+            .into(),
+        );
+        map.insert("double".to_string(), 1.5.into());
+        let frame: RespFrame =
+            RespArray::new([map.into(), (-42).into(), true.into(), RespNullArray.into()]).into();
+
+        let mut buf = BytesMut::from(frame.clone().encode().as_slice());
+        assert_eq!(RespFrame::expect_length(&buf)?, buf.len());
+        assert_eq!(RespFrame::decode(&mut buf)?, frame);
+        assert!(buf.is_empty());
+
+        Ok(())
+    }
 }
@@ -1,31 +1,49 @@
-use bytes::BytesMut;
+use bytes::{Buf, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::extract_fixed_data;
-
 // - boolean: "#<t|f>\r\n"
 impl RespEncode for bool {
-    fn encode(self) -> Vec<u8> {
-        format!("#{}\r\n", if self { "t" } else { "f" }).into_bytes()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(if self { b"#t\r\n" } else { b"#f\r\n" });
     }
 }
 
 impl RespDecode for bool {
     const PREFIX: &'static str = "#";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        match extract_fixed_data(buf, "#t\r\n", "Bool") {
-            Ok(_) => Ok(true),
-            Err(RespError::NotComplete) => Err(RespError::NotComplete),
-            Err(_) => match extract_fixed_data(buf, "#f\r\n", "Bool") {
-                Ok(_) => Ok(false),
-                Err(e) => Err(e),
-            },
+        if buf.len() < 4 {
+            return Err(RespError::NotComplete);
+        }
+        if !buf.starts_with(Self::PREFIX.as_bytes()) {
+            return Err(RespError::InvalidFrameType(format!(
+                "expect: Bool(#), got: {:?}",
+                buf
+            )));
         }
+        let value = parse_bool(buf)?;
+        buf.advance(4);
+        Ok(value)
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        parse_bool(buf).map(|_| 4)
     }
+}
 
-    fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
-        Ok(4)
+// only "#t" and "#f" are valid, followed right away by the CRLF. Anything else can't become a
+// boolean with more data, so it's a protocol error
+fn parse_bool(buf: &[u8]) -> Result<bool, RespError> {
+    if buf.len() < 4 {
+        return Err(RespError::NotComplete);
+    }
+    match (buf[1], &buf[2..4]) {
+        (b't', b"\r\n") => Ok(true),
+        (b'f', b"\r\n") => Ok(false),
+        _ => Err(RespError::ProtocolError(format!(
+            "expect: #t or #f, got: {:?}",
+            &buf[..4]
+        ))),
     }
 }
 
@@ -69,4 +87,38 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_boolean_expect_length() {
+        assert_eq!(RespFrame::expect_length(b"#t\r\n"), Ok(4));
+        assert_eq!(RespFrame::expect_length(b"#f\r\n+OK\r\n"), Ok(4));
+        for data in [&b"#"[..], b"#t", b"#t\r"] {
+            assert_eq!(RespFrame::expect_length(data), Err(RespError::NotComplete));
+        }
+    }
+
+    #[test]
+    fn test_boolean_decode_invalid_value_should_fail() {
+        for data in [&b"#x\r\n"[..], b"#T\r\n", b"#1\r\n", b"#tt\r\n", b"#t\n\r"] {
+            let mut buf = BytesMut::from(data);
+            let ret = bool::decode(&mut buf);
+            assert!(
+                matches!(ret, Err(RespError::ProtocolError(_))),
+                "{:?}",
+                data
+            );
+            assert_eq!(buf.as_ref(), data);
+
+            let ret = RespFrame::expect_length(data);
+            assert!(
+                matches!(ret, Err(RespError::ProtocolError(_))),
+                "{:?}",
+                data
+            );
+        }
+
+        let mut buf = BytesMut::from("+t\r\n");
+        let ret = bool::decode(&mut buf);
+        assert!(matches!(ret, Err(RespError::InvalidFrameType(_))));
+    }
 }
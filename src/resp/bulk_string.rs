@@ -14,12 +14,11 @@ pub struct RespNullBulkString;
 
 // - bulk string: "$<length>\r\n<data>\r\n"
 impl RespEncode for BulkString {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(self.len() + 16);
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(self.len() + 16);
         buf.extend_from_slice(&format!("${}\r\n", self.len()).into_bytes());
         buf.extend_from_slice(&self);
         buf.extend_from_slice(b"\r\n");
-        buf
     }
 }
 
@@ -28,9 +27,10 @@ impl RespDecode for BulkString {
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         let (end, len) = parse_length(buf, Self::PREFIX)?;
         let remained = &buf[end + CRLF_LEN..];
-        if remained.len() < len + CRLF_LEN {
+        if remained.len().saturating_sub(CRLF_LEN) < len {
             return Err(RespError::NotComplete);
         }
+        check_terminator(&remained[len..len + CRLF_LEN], len)?;
 
         buf.advance(end + CRLF_LEN);
 
@@ -40,14 +40,32 @@ impl RespDecode for BulkString {
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         let (end, len) = parse_length(buf, Self::PREFIX)?;
-        Ok(end + CRLF_LEN + len + CRLF_LEN)
+        let total = len
+            .checked_add(end + CRLF_LEN + CRLF_LEN)
+            .ok_or_else(|| RespError::ProtocolError("invalid bulk length".to_string()))?;
+        if let Some(terminator) = buf.get(total - CRLF_LEN..total) {
+            check_terminator(terminator, len)?;
+        }
+        Ok(total)
+    }
+}
+
+// the data must be followed right away by CRLF, otherwise the declared length is wrong and
+// reading on would desync the stream
+fn check_terminator(terminator: &[u8], len: usize) -> Result<(), RespError> {
+    if terminator != b"\r\n" {
+        return Err(RespError::ProtocolError(format!(
+            "bulk string data doesn't match the declared length {}",
+            len
+        )));
     }
+    Ok(())
 }
 
 // - null bulk string: "$-1\r\n"
 impl RespEncode for RespNullBulkString {
-    fn encode(self) -> Vec<u8> {
-        b"$-1\r\n".to_vec()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"$-1\r\n");
     }
 }
 
@@ -109,7 +127,7 @@ impl<const N: usize> From<&[u8; N]> for BulkString {
 
 #[cfg(test)]
 mod tests {
-    use crate::RespFrame;
+    use crate::{RespArray, RespFrame};
 
     use super::*;
     use anyhow::Result;
@@ -145,6 +163,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bulk_string_decode_with_embedded_crlf() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"$4\r\na\r\nb\r\n");
+
+        let len = BulkString::expect_length(&buf)?;
+        assert_eq!(len, buf.len());
+
+        let frame = BulkString::decode(&mut buf)?;
+        assert_eq!(frame, BulkString::new(b"a\r\nb"));
+        assert!(buf.is_empty());
+
+        buf.extend_from_slice(b"*2\r\n$4\r\na\r\nb\r\n$2\r\n\r\n\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespArray::new([b"a\r\nb".into(), b"\r\n".into()]).into()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_null_bulk_string_decode() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -155,4 +195,24 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_bulk_string_length_mismatch_should_fail() {
+        let mut buf = BytesMut::from("$3\r\nhello\r\n");
+        let ret = BulkString::decode(&mut buf);
+        assert!(matches!(ret, Err(RespError::ProtocolError(_))));
+        // nothing is consumed
+        assert_eq!(buf.as_ref(), b"$3\r\nhello\r\n");
+
+        let ret = BulkString::expect_length(b"$3\r\nhello\r\n");
+        assert!(matches!(ret, Err(RespError::ProtocolError(_))));
+
+        // declares more than it sends: the rest may still be on the way
+        let mut buf = BytesMut::from("$7\r\nhello\r\n");
+        assert_eq!(BulkString::decode(&mut buf), Err(RespError::NotComplete));
+
+        let mut buf = BytesMut::from("*1\r\n$2\r\nhello\r\n");
+        let ret = RespFrame::decode(&mut buf);
+        assert!(matches!(ret, Err(RespError::ProtocolError(_))));
+    }
 }
@@ -6,8 +6,22 @@ use super::{extract_simple_frame_data, CRLF_LEN};
 
 // - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
 impl RespEncode for f64 {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(32);
+    fn encode_to(self, buf: &mut BytesMut) {
+        // RESP3 spells the special values out, rust would format them as "inf" / "NaN"
+        if self.is_nan() {
+            buf.extend_from_slice(b",nan\r\n");
+            return;
+        }
+        if self.is_infinite() {
+            let s: &[u8] = if self > 0.0 {
+                b",inf\r\n"
+            } else {
+                b",-inf\r\n"
+            };
+            buf.extend_from_slice(s);
+            return;
+        }
+
         let ret = if self.abs() > 1e+8 || self.abs() < 1e-8 {
             format!(",{:+e}\r\n", self)
         } else {
@@ -16,7 +30,6 @@ impl RespEncode for f64 {
         };
 
         buf.extend_from_slice(&ret.into_bytes());
-        buf
     }
 }
 
@@ -27,7 +40,12 @@ impl RespDecode for f64 {
         let end = extract_simple_frame_data(buf, Self::PREFIX)?;
         let data = buf.split_to(end + CRLF_LEN);
         let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        Ok(s.parse()?)
+        match s.as_ref() {
+            "inf" | "+inf" => Ok(f64::INFINITY),
+            "-inf" => Ok(f64::NEG_INFINITY),
+            "nan" => Ok(f64::NAN),
+            s => Ok(s.parse()?),
+        }
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
@@ -72,4 +90,26 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_double_special_values() -> Result<()> {
+        let frame: RespFrame = f64::INFINITY.into();
+        assert_eq!(frame.encode(), b",inf\r\n");
+
+        let frame: RespFrame = f64::NEG_INFINITY.into();
+        assert_eq!(frame.encode(), b",-inf\r\n");
+
+        let frame: RespFrame = f64::NAN.into();
+        assert_eq!(frame.encode(), b",nan\r\n");
+
+        for v in [f64::INFINITY, f64::NEG_INFINITY] {
+            let mut buf = BytesMut::from(v.encode().as_slice());
+            assert_eq!(f64::decode(&mut buf)?, v);
+        }
+
+        let mut buf = BytesMut::from(f64::NAN.encode().as_slice());
+        assert!(f64::decode(&mut buf)?.is_nan());
+
+        Ok(())
+    }
 }
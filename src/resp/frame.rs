@@ -1,9 +1,13 @@
 use crate::{
-    BulkString, RespArray, RespDecode, RespError, RespMap, RespNull, RespNullArray,
+    BulkString, RespArray, RespDecode, RespEncode, RespError, RespMap, RespNull, RespNullArray,
     RespNullBulkString, RespSet, SimpleError, SimpleString,
 };
 use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
+use std::{io, mem::size_of};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::BUF_CAP;
 
 #[enum_dispatch(RespEncode)]
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
@@ -39,27 +43,22 @@ impl RespDecode for RespFrame {
                 let frame = i64::decode(buf)?;
                 Ok(frame.into())
             }
+            // a short buffer like "*0\r\n" is not an incomplete null, so match the null form exactly
+            Some(b'$') if buf.starts_with(b"$-1\r\n") => {
+                let frame = RespNullBulkString::decode(buf)?;
+                Ok(frame.into())
+            }
             Some(b'$') => {
-                // try null bulk string first
-                match RespNullBulkString::decode(buf) {
-                    Ok(frame) => Ok(frame.into()),
-                    Err(RespError::NotComplete) => Err(RespError::NotComplete),
-                    Err(_) => {
-                        let frame = BulkString::decode(buf)?;
-                        Ok(frame.into())
-                    }
-                }
+                let frame = BulkString::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'*') if buf.starts_with(b"*-1\r\n") => {
+                let frame = RespNullArray::decode(buf)?;
+                Ok(frame.into())
             }
             Some(b'*') => {
-                // try null array first
-                match RespNullArray::decode(buf) {
-                    Ok(frame) => Ok(frame.into()),
-                    Err(RespError::NotComplete) => Err(RespError::NotComplete),
-                    Err(_) => {
-                        let frame = RespArray::decode(buf)?;
-                        Ok(frame.into())
-                    }
-                }
+                let frame = RespArray::decode(buf)?;
+                Ok(frame.into())
             }
             Some(b'_') => {
                 let frame = RespNull::decode(buf)?;
@@ -92,9 +91,11 @@ impl RespDecode for RespFrame {
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         let mut iter = buf.iter().peekable();
         match iter.peek() {
+            Some(b'*') if buf.starts_with(b"*-1\r\n") => RespNullArray::expect_length(buf),
             Some(b'*') => RespArray::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
+            Some(b'$') if buf.starts_with(b"$-1\r\n") => RespNullBulkString::expect_length(buf),
             Some(b'$') => BulkString::expect_length(buf),
             Some(b':') => i64::expect_length(buf),
             Some(b'+') => SimpleString::expect_length(buf),
@@ -107,6 +108,194 @@ impl RespDecode for RespFrame {
     }
 }
 
+impl RespFrame {
+    /// Decode a buffer that must hold exactly one complete frame, trailing bytes are an error
+    /// rather than the start of the next pipelined frame. The buffer is untouched on error
+    pub fn decode_exact(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let len = Self::expect_length(buf)?;
+        if len != buf.len() {
+            return Err(RespError::InvalidFrame(format!(
+                "expect exactly one frame of {} bytes, got {} trailing bytes",
+                len,
+                buf.len() - len
+            )));
+        }
+        Self::decode(buf)
+    }
+
+    /// Decode every complete frame in the buffer in order. A trailing partial frame is not an
+    /// error, its bytes stay in the buffer until more data arrives
+    pub fn decode_all(buf: &mut BytesMut) -> Result<Vec<Self>, RespError> {
+        let mut frames = Vec::new();
+        while !buf.is_empty() {
+            match Self::decode(buf) {
+                Ok(frame) => frames.push(frame),
+                Err(RespError::NotComplete) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Encode the frame straight into `writer`. The elements of an aggregate frame are written
+    /// out in chunks of about `BUF_CAP` bytes, so a large reply never sits in memory as a whole.
+    pub async fn encode_async<W>(self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut buf = BytesMut::with_capacity(BUF_CAP);
+        let frames = match self {
+            RespFrame::Array(array) => {
+                buf.extend_from_slice(&format!("*{}\r\n", array.len()).into_bytes());
+                array.0
+            }
+            RespFrame::Set(set) => {
+                buf.extend_from_slice(&format!("~{}\r\n", set.len()).into_bytes());
+                set.0
+            }
+            RespFrame::Map(map) => {
+                buf.extend_from_slice(&format!("%{}\r\n", map.len()).into_bytes());
+                map.0
+                    .into_iter()
+                    .flat_map(|(k, v)| [SimpleString::new(k).into(), v])
+                    .collect()
+            }
+            frame => {
+                frame.encode_to(&mut buf);
+                vec![]
+            }
+        };
+
+        for frame in frames {
+            frame.encode_to(&mut buf);
+            if buf.len() >= BUF_CAP {
+                writer.write_all(&buf).await?;
+                buf.clear();
+            }
+        }
+        writer.write_all(&buf).await
+    }
+
+    /// Compare two frames by meaning rather than layout: the elements of sets may come in any
+    /// order, arrays stay order sensitive and NaN doubles are equal to each other. Maps are
+    /// compared entry by entry with the same rules
+    pub fn semantically_eq(&self, other: &RespFrame) -> bool {
+        match (self, other) {
+            (RespFrame::Array(a), RespFrame::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.semantically_eq(b))
+            }
+            (RespFrame::Map(a), RespFrame::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(k, v)| b.get(k).is_some_and(|other| v.semantically_eq(other)))
+            }
+            (RespFrame::Set(a), RespFrame::Set(b)) => {
+                // every element has to be matched by a distinct element of the other set
+                let mut matched = vec![false; b.len()];
+                a.len() == b.len()
+                    && a.iter().all(|v| {
+                        let found = b
+                            .iter()
+                            .enumerate()
+                            .position(|(i, other)| !matched[i] && v.semantically_eq(other));
+                        found.map(|i| matched[i] = true).is_some()
+                    })
+            }
+            (RespFrame::Double(a), RespFrame::Double(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (a, b) => a == b,
+        }
+    }
+
+    /// Approximate heap bytes owned by the frame, not counting the frame itself: the allocated
+    /// capacity of strings and element vectors plus whatever the elements own in turn. Map
+    /// entries are counted by their key and value size, the tree nodes around them are ignored
+    pub fn heap_size(&self) -> usize {
+        match self {
+            RespFrame::SimpleString(s) => s.0.capacity(),
+            RespFrame::Error(e) => e.0.capacity(),
+            RespFrame::BulkString(s) => s.0.capacity(),
+            RespFrame::Array(v) => elements_heap_size(&v.0),
+            RespFrame::Set(v) => elements_heap_size(&v.0),
+            RespFrame::Map(v) => v
+                .iter()
+                .map(|(k, v)| {
+                    size_of::<String>() + k.capacity() + size_of::<RespFrame>() + v.heap_size()
+                })
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    /// Downgrade the frame to the types a RESP2 client understands: maps become flat arrays of
+    /// key/value pairs, sets arrays, null a null bulk string, booleans 1 / 0 and doubles bulk
+    /// strings. Aggregate frames are downgraded all the way down
+    pub fn into_resp2(self) -> RespFrame {
+        match self {
+            RespFrame::Array(array) => downgrade_elements(array.0).into(),
+            RespFrame::Set(set) => downgrade_elements(set.0).into(),
+            RespFrame::Map(map) => RespArray::new(
+                map.0
+                    .into_iter()
+                    .flat_map(|(k, v)| [BulkString::from(k).into(), v.into_resp2()])
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Null(_) => RespNullBulkString.into(),
+            RespFrame::Boolean(b) => RespFrame::Integer(b as i64),
+            RespFrame::Double(d) => BulkString::from(format_double(d)).into(),
+            frame => frame,
+        }
+    }
+}
+
+fn downgrade_elements(frames: Vec<RespFrame>) -> RespArray {
+    RespArray::new(
+        frames
+            .into_iter()
+            .map(RespFrame::into_resp2)
+            .collect::<Vec<_>>(),
+    )
+}
+
+// a double as redis spells it in a bulk string
+fn format_double(d: f64) -> String {
+    if d.is_nan() {
+        "nan".to_string()
+    } else if d.is_infinite() {
+        if d > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        d.to_string()
+    }
+}
+
+fn elements_heap_size(frames: &Vec<RespFrame>) -> usize {
+    frames.capacity() * size_of::<RespFrame>()
+        + frames.iter().map(RespFrame::heap_size).sum::<usize>()
+}
+
+// the error reply for a frame that failed to decode or convert, with redis' error messages
+impl From<RespError> for RespFrame {
+    fn from(e: RespError) -> Self {
+        let msg = match e {
+            RespError::InvalidFrame(msg) => format!("ERR Protocol error: {}", msg),
+            RespError::InvalidFrameType(msg) => {
+                format!("ERR Protocol error: invalid frame type: {}", msg)
+            }
+            RespError::InvalidFrameLength(len) => {
+                format!("ERR Protocol error: invalid frame length: {}", len)
+            }
+            RespError::NotComplete => "ERR Protocol error: incomplete frame".to_string(),
+            RespError::ProtocolError(msg) => format!("ERR Protocol error: {}", msg),
+            RespError::ParseIntError(_) => {
+                "ERR value is not an integer or out of range".to_string()
+            }
+            RespError::Utf8Error(e) => format!("ERR invalid UTF-8 argument: {}", e),
+            RespError::ParseFloatError(_) => "ERR value is not a valid float".to_string(),
+        };
+        SimpleError::new(msg).into()
+    }
+}
+
 impl From<&str> for RespFrame {
     fn from(s: &str) -> Self {
         SimpleString(s.to_string()).into()
@@ -127,5 +316,221 @@ impl<const N: usize> From<&[u8; N]> for RespFrame {
 
 #[cfg(test)]
 mod tests {
-    // TODO: Add tests
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_encode_async_nested_frame() -> Result<()> {
+        let mut map = RespMap::new();
+        map.insert(
+            "set".to_string(),
+            RespSet::new([BulkString::from("a").into(), 1.into()]).into(),
+        );
+        map.insert(
+            "value".to_string(),
+            BulkString::new(vec![b'x'; BUF_CAP]).into(),
+        );
+        let frame: RespFrame = RespArray::new([
+            map.into(),
+            RespArray::new([true.into(), RespNull.into()]).into(),
+            BulkString::new(vec![b'y'; BUF_CAP * 2]).into(),
+        ])
+        .into();
+
+        let mut writer = Vec::new();
+        frame.clone().encode_async(&mut writer).await?;
+        assert_eq!(writer, frame.clone().encode());
+
+        let mut buf = BytesMut::from(writer.as_slice());
+        assert_eq!(RespFrame::decode(&mut buf)?, frame);
+        assert!(buf.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_resp2() {
+        let mut map = RespMap::new();
+        map.insert("a".to_string(), RespSet::new([1.5.into()]).into());
+        let frame: RespFrame = RespArray::new([
+            map.into(),
+            RespNull.into(),
+            true.into(),
+            f64::NEG_INFINITY.into(),
+        ])
+        .into();
+        assert_eq!(
+            frame.into_resp2().encode(),
+            b"*4\r\n*2\r\n$1\r\na\r\n*1\r\n$3\r\n1.5\r\n$-1\r\n:1\r\n$4\r\n-inf\r\n"
+        );
+    }
+
+    #[test]
+    fn test_decode_exact() -> Result<()> {
+        let mut buf = BytesMut::from("*2\r\n$3\r\nget\r\n$5\r\nhello\r\n");
+        let frame = RespFrame::decode_exact(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespArray::new([
+                BulkString::from("get").into(),
+                BulkString::from("hello").into()
+            ])
+            .into()
+        );
+        assert!(buf.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_exact_with_trailing_data_should_fail() {
+        let mut buf = BytesMut::from("+OK\r\n+OK\r\n");
+        let ret = RespFrame::decode_exact(&mut buf);
+        assert!(matches!(ret, Err(RespError::InvalidFrame(_))));
+        assert_eq!(buf.len(), 10);
+
+        let mut buf = BytesMut::from("+OK\r\ngarbage");
+        let ret = RespFrame::decode_exact(&mut buf);
+        assert!(matches!(ret, Err(RespError::InvalidFrame(_))));
+
+        let mut buf = BytesMut::from("+OK\r");
+        assert_eq!(
+            RespFrame::decode_exact(&mut buf),
+            Err(RespError::NotComplete)
+        );
+    }
+
+    #[test]
+    fn test_semantically_eq() {
+        let set = |values: &[i64]| -> RespFrame {
+            RespSet::new(
+                values
+                    .iter()
+                    .map(|v| (*v).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into()
+        };
+        // sets ignore the order of their elements
+        assert_ne!(set(&[1, 2, 3]), set(&[3, 1, 2]));
+        assert!(set(&[1, 2, 3]).semantically_eq(&set(&[3, 1, 2])));
+        assert!(!set(&[1, 1, 2]).semantically_eq(&set(&[1, 2, 2])));
+        assert!(!set(&[1, 2]).semantically_eq(&set(&[1, 2, 3])));
+
+        // arrays don't, even when they hold a flattened map
+        let array = |values: &[&str]| -> RespFrame {
+            RespArray::new(
+                values
+                    .iter()
+                    .map(|v| BulkString::from(*v).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into()
+        };
+        assert!(array(&["a", "1", "b", "2"]).semantically_eq(&array(&["a", "1", "b", "2"])));
+        assert!(!array(&["a", "1", "b", "2"]).semantically_eq(&array(&["b", "2", "a", "1"])));
+
+        // the rules apply to nested frames too
+        let mut a = RespMap::new();
+        a.insert("set".to_string(), set(&[1, 2]));
+        a.insert("nan".to_string(), f64::NAN.into());
+        let mut b = RespMap::new();
+        b.insert("nan".to_string(), f64::NAN.into());
+        b.insert("set".to_string(), set(&[2, 1]));
+        let (a, b): (RespFrame, RespFrame) = (a.into(), b.into());
+        assert_ne!(a, b);
+        assert!(a.semantically_eq(&b));
+    }
+
+    #[test]
+    fn test_resp_error_into_frame() {
+        let error = |e: RespError| match RespFrame::from(e) {
+            RespFrame::Error(e) => e.0,
+            v => panic!("expect an error frame, got {:?}", v),
+        };
+        assert_eq!(
+            error(RespError::InvalidFrame("bad".to_string())),
+            "ERR Protocol error: bad"
+        );
+        assert_eq!(
+            error(RespError::InvalidFrameType("!".to_string())),
+            "ERR Protocol error: invalid frame type: !"
+        );
+        assert_eq!(
+            error(RespError::InvalidFrameLength(-2)),
+            "ERR Protocol error: invalid frame length: -2"
+        );
+        assert_eq!(
+            error(RespError::NotComplete),
+            "ERR Protocol error: incomplete frame"
+        );
+        assert_eq!(
+            error("abc".parse::<i64>().unwrap_err().into()),
+            "ERR value is not an integer or out of range"
+        );
+        assert_eq!(
+            error("abc".parse::<f64>().unwrap_err().into()),
+            "ERR value is not a valid float"
+        );
+        assert!(error(String::from_utf8(vec![0xff]).unwrap_err().into())
+            .starts_with("ERR invalid UTF-8 argument"));
+    }
+
+    #[test]
+    fn test_heap_size_of_bulk_string() {
+        let frame: RespFrame = BulkString::new(vec![b'x'; 100]).into();
+        assert!(frame.heap_size() >= 100 && frame.heap_size() < 200);
+        assert_eq!(RespFrame::Integer(42).heap_size(), 0);
+        assert_eq!(RespFrame::Null(RespNull).heap_size(), 0);
+    }
+
+    #[test]
+    fn test_heap_size_of_nested_array() {
+        let inner: RespFrame = RespArray::new([BulkString::new(vec![b'x'; 100]).into()]).into();
+        let inner_size = inner.heap_size();
+        assert!(inner_size >= 100 + size_of::<RespFrame>());
+
+        let frame: RespFrame =
+            RespArray::new([inner, BulkString::new(vec![b'y'; 50]).into()]).into();
+        let size = frame.heap_size();
+        assert!(size >= inner_size + 50 + 2 * size_of::<RespFrame>());
+        assert!(size < inner_size + 50 + 2 * size_of::<RespFrame>() + 256);
+    }
+
+    #[test]
+    fn test_heap_size_of_map() {
+        let mut map = RespMap::new();
+        map.insert("key".to_string(), BulkString::new(vec![b'x'; 100]).into());
+        map.insert("num".to_string(), RespFrame::Integer(1));
+        let frame: RespFrame = map.into();
+
+        let entry = size_of::<String>() + size_of::<RespFrame>();
+        let size = frame.heap_size();
+        assert!(size >= 2 * entry + 6 + 100);
+        assert!(size < 2 * entry + 6 + 100 + 256);
+    }
+
+    #[test]
+    fn test_decode_all_should_leave_partial_frame() -> Result<()> {
+        let mut buf = BytesMut::from(&b"+OK\r\n*2\r\n$3\r\nget\r\n$1\r\na\r\n*2\r\n$3\r\nge"[..]);
+        let frames = RespFrame::decode_all(&mut buf)?;
+        assert_eq!(
+            frames,
+            vec![
+                SimpleString::new("OK").into(),
+                RespArray::new([BulkString::from("get").into(), BulkString::from("a").into()])
+                    .into(),
+            ]
+        );
+        assert_eq!(&buf[..], b"*2\r\n$3\r\nge");
+
+        buf.extend_from_slice(b"t\r\n$1\r\nb\r\n");
+        assert_eq!(RespFrame::decode_all(&mut buf)?.len(), 1);
+        assert!(buf.is_empty());
+        assert!(RespFrame::decode_all(&mut buf)?.is_empty());
+
+        let mut buf = BytesMut::from(&b":1\r\n!oops\r\n"[..]);
+        assert!(RespFrame::decode_all(&mut buf).is_err());
+        Ok(())
+    }
 }
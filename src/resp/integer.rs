@@ -5,10 +5,10 @@ use crate::{RespDecode, RespEncode, RespError};
 use super::{extract_simple_frame_data, CRLF_LEN};
 
 // - integer: ":[<+|->]<value>\r\n"
+// the sign is optional, like redis only negative values are encoded with one
 impl RespEncode for i64 {
-    fn encode(self) -> Vec<u8> {
-        let sign = if self < 0 { "" } else { "+" };
-        format!(":{}{}\r\n", sign, self).into_bytes()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(&format!(":{}\r\n", self).into_bytes());
     }
 }
 
@@ -37,7 +37,10 @@ mod tests {
     #[test]
     fn test_integer_encode() {
         let frame: RespFrame = 123.into();
-        assert_eq!(frame.encode(), b":+123\r\n");
+        assert_eq!(frame.encode(), b":123\r\n");
+
+        let frame: RespFrame = 0.into();
+        assert_eq!(frame.encode(), b":0\r\n");
 
         let frame: RespFrame = (-123).into();
         assert_eq!(frame.encode(), b":-123\r\n");
@@ -56,6 +59,21 @@ mod tests {
         let frame = i64::decode(&mut buf)?;
         assert_eq!(frame, -123);
 
+        buf.extend_from_slice(b":123\r\n");
+
+        let frame = i64::decode(&mut buf)?;
+        assert_eq!(frame, 123);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_integer_encode_decode_round_trip() -> Result<()> {
+        for value in [0, 1, -1, 1234, -1234, i64::MAX, i64::MIN] {
+            let mut buf = BytesMut::from(value.encode().as_slice());
+            assert_eq!(i64::decode(&mut buf)?, value);
+            assert!(buf.is_empty());
+        }
         Ok(())
     }
 }
@@ -0,0 +1,85 @@
+use super::RespFrame;
+use serde_json::{Map, Value};
+
+// strings that aren't valid UTF-8 are converted lossily, errors become {"error": <message>} so
+// they can't be mistaken for a string reply. JSON has no infinity or NaN, those doubles are null
+impl From<RespFrame> for Value {
+    fn from(frame: RespFrame) -> Self {
+        match frame {
+            RespFrame::SimpleString(s) => Value::String(s.0),
+            RespFrame::Error(e) => {
+                Value::Object(Map::from_iter([("error".to_string(), Value::String(e.0))]))
+            }
+            RespFrame::Integer(n) => n.into(),
+            RespFrame::BulkString(s) => Value::String(String::from_utf8_lossy(&s.0).into_owned()),
+            RespFrame::NullBulkString(_) | RespFrame::NullArray(_) | RespFrame::Null(_) => {
+                Value::Null
+            }
+            RespFrame::Array(array) => array.0.into_iter().map(Value::from).collect(),
+            RespFrame::Set(set) => set.0.into_iter().map(Value::from).collect(),
+            RespFrame::Boolean(b) => b.into(),
+            RespFrame::Double(d) => d.into(),
+            RespFrame::Map(map) => map
+                .0
+                .into_iter()
+                .map(|(k, v)| (k, Value::from(v)))
+                .collect::<Map<_, _>>()
+                .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray, RespMap, RespNull, SimpleError, SimpleString};
+    use serde_json::json;
+
+    #[test]
+    fn test_nested_array_to_json() {
+        let frame: RespFrame = RespArray::new([
+            BulkString::from("a").into(),
+            42.into(),
+            RespArray::new([
+                SimpleString::new("OK").into(),
+                1.5.into(),
+                RespFrame::Null(RespNull),
+            ])
+            .into(),
+            true.into(),
+        ])
+        .into();
+        assert_eq!(
+            Value::from(frame),
+            json!(["a", 42, ["OK", 1.5, null], true])
+        );
+    }
+
+    #[test]
+    fn test_map_to_json() {
+        let mut map = RespMap::new();
+        map.insert("name".to_string(), BulkString::from("redis").into());
+        map.insert("proto".to_string(), 3.into());
+        map.insert(
+            "modules".to_string(),
+            RespArray::new(Vec::<RespFrame>::new()).into(),
+        );
+        assert_eq!(
+            Value::from(RespFrame::from(map)),
+            json!({"name": "redis", "proto": 3, "modules": []})
+        );
+    }
+
+    #[test]
+    fn test_special_values_to_json() {
+        assert_eq!(
+            Value::from(RespFrame::from(SimpleError::new("ERR oops"))),
+            json!({"error": "ERR oops"})
+        );
+        assert_eq!(Value::from(RespFrame::Double(f64::INFINITY)), Value::Null);
+        assert_eq!(
+            Value::from(RespFrame::from(BulkString::new(vec![0xff, b'a']))),
+            json!("\u{fffd}a")
+        );
+    }
+}
@@ -14,14 +14,13 @@ pub struct RespMap(pub(crate) BTreeMap<String, RespFrame>);
 // - map: "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
 // we only support string key which encode to SimpleString
 impl RespEncode for RespMap {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(BUF_CAP);
         buf.extend_from_slice(&format!("%{}\r\n", self.len()).into_bytes());
         for (key, value) in self.0 {
-            buf.extend_from_slice(&SimpleString::new(key).encode());
-            buf.extend_from_slice(&value.encode());
+            SimpleString::new(key).encode_to(buf);
+            value.encode_to(buf);
         }
-        buf
     }
 }
 
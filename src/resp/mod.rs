@@ -1,9 +1,13 @@
+#[cfg(test)]
+mod arbitrary;
 mod array;
 mod bool;
 mod bulk_string;
 mod double;
 mod frame;
 mod integer;
+#[cfg(feature = "json")]
+mod json;
 mod map;
 mod null;
 mod set;
@@ -30,8 +34,15 @@ pub use self::{
 };
 
 #[enum_dispatch]
-pub trait RespEncode {
-    fn encode(self) -> Vec<u8>;
+pub trait RespEncode: Sized {
+    /// Append the encoded frame to `buf`, aggregate frames encode their children into the same buffer
+    fn encode_to(self, buf: &mut BytesMut);
+
+    fn encode(self) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        self.encode_to(&mut buf);
+        buf.into()
+    }
 }
 
 pub trait RespDecode: Sized {
@@ -50,6 +61,10 @@ pub enum RespError {
     InvalidFrameLength(isize),
     #[error("Frame is not complete")]
     NotComplete,
+    /// The bytes can never become a valid frame, unlike NotComplete waiting for more data won't
+    /// help and the connection can't be resynchronized
+    #[error("Protocol error: {0}")]
+    ProtocolError(String),
 
     #[error("Parse error: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
@@ -115,7 +130,10 @@ fn find_crlf(buf: &[u8], nth: usize) -> Option<usize> {
 fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, usize), RespError> {
     let end = extract_simple_frame_data(buf, prefix)?;
     let s = String::from_utf8_lossy(&buf[prefix.len()..end]);
-    Ok((end, s.parse()?))
+    let len = s
+        .parse()
+        .map_err(|_| RespError::ProtocolError(format!("invalid length: {:?}", s)))?;
+    Ok((end, len))
 }
 
 fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result<usize, RespError> {
@@ -126,7 +144,7 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
             // find nth CRLF in the buffer, for array and set, we need to find 1 CRLF for each element
             for _ in 0..len {
                 let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                data = data.get(len..).ok_or(RespError::NotComplete)?;
                 total += len;
             }
             Ok(total)
@@ -140,7 +158,7 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
                 total += len;
 
                 let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                data = data.get(len..).ok_or(RespError::NotComplete)?;
                 total += len;
             }
             Ok(total)
@@ -168,4 +186,25 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_calc_array_length_with_truncated_element() -> Result<()> {
+        // the bulk string claims 10 bytes but only 2 arrived so far
+        let buf = b"*1\r\n$10\r\nab";
+        let (end, len) = parse_length(buf, "*")?;
+        let ret = calc_total_length(buf, end, len, "*");
+        assert_eq!(ret.unwrap_err(), RespError::NotComplete);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bad_length_should_be_protocol_error() {
+        for buf in [&b"*abc\r\n"[..], b"$-5\r\n", b"*1x\r\n$1\r\na\r\n"] {
+            let ret = parse_length(buf, &String::from_utf8_lossy(&buf[..1]));
+            assert!(matches!(ret, Err(RespError::ProtocolError(_))), "{:?}", buf);
+        }
+        // a header that isn't complete yet is no violation
+        assert_eq!(parse_length(b"*12", "*"), Err(RespError::NotComplete));
+    }
 }
@@ -10,13 +10,12 @@ pub struct RespSet(pub(crate) Vec<RespFrame>);
 
 // - set: "~<number-of-elements>\r\n<element-1>...<element-n>"
 impl RespEncode for RespSet {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(BUF_CAP);
         buf.extend_from_slice(&format!("~{}\r\n", self.len()).into_bytes());
         for frame in self.0 {
-            buf.extend_from_slice(&frame.encode());
+            frame.encode_to(buf);
         }
-        buf
     }
 }
 
@@ -78,7 +77,7 @@ mod tests {
         .into();
         assert_eq!(
             frame.encode(),
-            b"~2\r\n*2\r\n:+1234\r\n#t\r\n$5\r\nworld\r\n"
+            b"~2\r\n*2\r\n:1234\r\n#t\r\n$5\r\nworld\r\n"
         );
     }
 
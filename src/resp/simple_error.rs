@@ -11,8 +11,8 @@ pub struct SimpleError(pub(crate) String);
 
 // - error: "-Error message\r\n"
 impl RespEncode for SimpleError {
-    fn encode(self) -> Vec<u8> {
-        format!("-{}\r\n", self.0).into_bytes()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(&format!("-{}\r\n", self.0).into_bytes());
     }
 }
 
@@ -17,8 +17,8 @@ impl SimpleString {
 
 // - simple string: "+OK\r\n"
 impl RespEncode for SimpleString {
-    fn encode(self) -> Vec<u8> {
-        format!("+{}\r\n", self.0).into_bytes()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(&format!("+{}\r\n", self.0).into_bytes());
     }
 }
 
@@ -3,7 +3,9 @@ mod parser;
 use crate::{RespError, RespFrame};
 use bytes::BytesMut;
 
-pub use self::parser::{parse_frame, parse_frame_length};
+pub use self::parser::{
+    parse_frame, parse_frame_length, parse_frame_length_with, parse_frame_with,
+};
 
 pub trait RespDecodeV2: Sized {
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError>;
@@ -15,7 +17,8 @@ impl RespDecodeV2 for RespFrame {
         let len = Self::expect_length(buf)?;
         let data = buf.split_to(len);
 
-        parse_frame(&mut data.as_ref()).map_err(|e| RespError::InvalidFrame(e.to_string()))
+        // the whole frame is there, so failing to parse it is a violation rather than missing data
+        parse_frame(&mut data.as_ref()).map_err(|e| RespError::ProtocolError(e.to_string()))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
@@ -26,7 +29,9 @@ impl RespDecodeV2 for RespFrame {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{RespNullArray, RespNullBulkString};
+    use crate::{
+        BulkString, RespArray, RespEncode, RespMap, RespNullArray, RespNullBulkString, RespSet,
+    };
     use std::collections::BTreeMap;
 
     #[test]
@@ -92,6 +97,45 @@ mod tests {
         assert_eq!(frame, RespFrame::BulkString("foobar".into()));
     }
 
+    #[test]
+    fn respv2_bulk_string_with_embedded_crlf_should_work() {
+        let mut buf = BytesMut::from("$4\r\na\r\nb\r\n");
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        assert_eq!(frame, RespFrame::BulkString("a\r\nb".into()));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn respv2_bulk_string_length_mismatch_should_fail() {
+        let buf = b"$3\r\nhello\r\n";
+        let err = RespFrame::expect_length(buf).unwrap_err();
+        assert!(matches!(err, RespError::ProtocolError(_)));
+
+        let mut buf = BytesMut::from("*2\r\n$3\r\nget\r\n$1\r\nhello\r\n");
+        let err = RespFrame::decode(&mut buf).unwrap_err();
+        assert!(matches!(err, RespError::ProtocolError(_)));
+
+        // the data isn't there yet, that's not a mismatch
+        let err = RespFrame::expect_length(b"$5\r\nhel").unwrap_err();
+        assert_eq!(err, RespError::NotComplete);
+    }
+
+    #[test]
+    fn respv2_boolean_should_only_accept_t_or_f() {
+        for (data, value) in [("#t\r\n", true), ("#f\r\n", false)] {
+            let mut buf = BytesMut::from(data);
+            assert_eq!(
+                RespFrame::decode(&mut buf).unwrap(),
+                RespFrame::Boolean(value)
+            );
+        }
+        for data in ["#x\r\n", "#T\r\n", "#tt\r\n"] {
+            let mut buf = BytesMut::from(data);
+            let err = RespFrame::decode(&mut buf).unwrap_err();
+            assert!(matches!(err, RespError::ProtocolError(_)), "{:?}", data);
+        }
+    }
+
     #[test]
     fn respv2_null_bulk_string_length_should_work() {
         let buf = b"$-1\r\n";
@@ -173,4 +217,104 @@ mod tests {
         .collect();
         assert_eq!(frame, RespFrame::Map(items.into()));
     }
+
+    #[test]
+    fn respv2_integer_should_round_trip() {
+        for value in [0, 1, -1, 1234, -1234, i64::MAX, i64::MIN] {
+            let frame: RespFrame = value.into();
+            let mut buf = BytesMut::from(frame.clone().encode().as_slice());
+            assert_eq!(RespFrame::decode(&mut buf).unwrap(), frame);
+            assert!(buf.is_empty());
+        }
+        // an explicit plus sign is still accepted
+        let mut buf = BytesMut::from(":+1234\r\n");
+        assert_eq!(
+            RespFrame::decode(&mut buf).unwrap(),
+            RespFrame::Integer(1234)
+        );
+    }
+
+    #[test]
+    fn respv2_set_should_work() {
+        let mut buf = BytesMut::from("~2\r\n+OK\r\n:+1\r\n");
+        assert_eq!(RespFrame::expect_length(&buf).unwrap(), buf.len());
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        assert_eq!(
+            frame,
+            RespSet::new([RespFrame::SimpleString("OK".into()), 1.into()]).into()
+        );
+    }
+
+    #[test]
+    fn respv2_empty_map_should_work() {
+        let mut buf = BytesMut::from("%0\r\n");
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        assert_eq!(frame, RespMap::new().into());
+    }
+
+    #[test]
+    fn respv2_nested_mixed_frame_should_round_trip() {
+        let mut map = RespMap::new();
+        map.insert(
+            "set".to_string(),
+            RespSet::new([
+                RespArray::new([BulkString::from("a").into(), 1.into()]).into(),
+                BulkString::from("b").into(),
+            ])
+            .into(),
+        );
+        map.insert("double".to_string(), 1.5.into());
+        map.insert("empty".to_string(), RespMap::new().into());
+        let frame: RespFrame = RespArray::new([
+            map.into(),
+            (-42).into(),
+            true.into(),
+            RespNullBulkString.into(),
+        ])
+        .into();
+
+        let mut buf = BytesMut::from(frame.clone().encode().as_slice());
+        assert_eq!(RespFrame::expect_length(&buf).unwrap(), buf.len());
+        assert_eq!(RespFrame::decode(&mut buf).unwrap(), frame);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn respv2_double_special_values_should_round_trip() {
+        for v in [f64::INFINITY, f64::NEG_INFINITY] {
+            let mut buf = BytesMut::from(RespFrame::Double(v).encode().as_slice());
+            let frame = RespFrame::decode(&mut buf).unwrap();
+            assert_eq!(frame, RespFrame::Double(v));
+        }
+
+        let mut buf = BytesMut::from(RespFrame::Double(f64::NAN).encode().as_slice());
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        assert!(matches!(frame, RespFrame::Double(v) if v.is_nan()));
+    }
+
+    #[test]
+    fn respv2_lf_only_should_work_in_lenient_mode() {
+        let buf = b"+OK\n";
+        assert_eq!(parse_frame_length_with(buf, true).unwrap(), buf.len());
+        let frame = parse_frame_with(&mut buf.as_slice(), true).unwrap();
+        assert_eq!(frame, RespFrame::SimpleString("OK".into()));
+
+        let buf = b"*2\n$3\nget\r\n:-12\n";
+        assert_eq!(parse_frame_length_with(buf, true).unwrap(), buf.len());
+        let frame = parse_frame_with(&mut buf.as_slice(), true).unwrap();
+        assert_eq!(
+            frame,
+            RespArray::new([BulkString::from("get").into(), RespFrame::Integer(-12)]).into()
+        );
+    }
+
+    #[test]
+    fn respv2_lf_only_should_fail_in_strict_mode() {
+        let buf = b"+OK\n";
+        assert_eq!(
+            parse_frame_length_with(buf, false).unwrap_err(),
+            RespError::NotComplete
+        );
+        assert!(parse_frame(&mut buf.as_slice()).is_err());
+    }
 }
@@ -1,6 +1,6 @@
 use crate::{
     BulkString, RespArray, RespError, RespFrame, RespMap, RespNull, RespNullArray,
-    RespNullBulkString, SimpleError, SimpleString,
+    RespNullBulkString, RespSet, SimpleError, SimpleString,
 };
 use std::{collections::BTreeMap, num::NonZeroUsize};
 use winnow::{
@@ -12,11 +12,25 @@ use winnow::{
 };
 
 const CRLF: &[u8] = b"\r\n";
+const LF: &[u8] = b"\n";
+
+// The parsers below are generic over `LENIENT`: when it is set, line based frames and length
+// prefixes may also be terminated by a bare "\n" (e.g. users typing into telnet).
+// Bulk string payloads are always expected to be followed by "\r\n".
 
 //
 pub fn parse_frame_length(input: &[u8]) -> Result<usize, RespError> {
+    parse_frame_length_with(input, false)
+}
+
+/// Same as `parse_frame_length`, but accepts "\n" as a line terminator when `lenient` is set
+pub fn parse_frame_length_with(input: &[u8], lenient: bool) -> Result<usize, RespError> {
     let target = &mut (&*input);
-    let ret = parse_frame_len(target);
+    let ret = if lenient {
+        parse_frame_len::<true>(target)
+    } else {
+        parse_frame_len::<false>(target)
+    };
     match ret {
         Ok(_) => {
             // calculate the distance between target and input
@@ -25,75 +39,95 @@ pub fn parse_frame_length(input: &[u8]) -> Result<usize, RespError> {
             let len = end - start;
             Ok(len)
         }
+        // a cut means the frame is malformed, more data wouldn't make it valid
+        Err(ErrMode::Cut(_)) => Err(RespError::ProtocolError(
+            "malformed frame length".to_string(),
+        )),
         Err(_) => Err(RespError::NotComplete),
     }
 }
 
-fn parse_frame_len(input: &mut &[u8]) -> PResult<()> {
-    let mut simple_parser = terminated(take_until(0.., CRLF), CRLF).value(());
+fn parse_frame_len<const LENIENT: bool>(input: &mut &[u8]) -> PResult<()> {
+    let mut simple_parser = line::<LENIENT>.value(());
     dispatch! {any;
         b'+' => simple_parser,
         b'-' => simple_parser,
         b':' => simple_parser,
-        b'$' => bulk_string_len,
-        b'*' => array_len,
+        b'$' => bulk_string_len::<LENIENT>,
+        b'*' => array_len::<LENIENT>,
         b'_' => simple_parser,
         b'#' => simple_parser,
         b',' => simple_parser,
-        b'%' => map_len,
-        // b'~' => set,
+        b'%' => map_len::<LENIENT>,
+        b'~' => array_len::<LENIENT>,
         _v => fail::<_, _, _>
     }
     .parse_next(input)
 }
 
 pub fn parse_frame(input: &mut &[u8]) -> PResult<RespFrame> {
+    parse_frame_inner::<false>(input)
+}
+
+/// Same as `parse_frame`, but accepts "\n" as a line terminator when `lenient` is set
+pub fn parse_frame_with(input: &mut &[u8], lenient: bool) -> PResult<RespFrame> {
+    if lenient {
+        parse_frame_inner::<true>(input)
+    } else {
+        parse_frame_inner::<false>(input)
+    }
+}
+
+fn parse_frame_inner<const LENIENT: bool>(input: &mut &[u8]) -> PResult<RespFrame> {
     // frame type has been processed
     dispatch! {any;
-        b'+' => simple_string.map(RespFrame::SimpleString),
-        b'-' => error.map(RespFrame::Error),
-        b':' => integer.map(RespFrame::Integer),
-        b'$' => alt((null_bulk_string.map(RespFrame::NullBulkString),bulk_string.map(RespFrame::BulkString))),
-        b'*' => alt((null_array.map(RespFrame::NullArray), array.map(RespFrame::Array))),
-        b'_' => null.map(RespFrame::Null),
-        b'#' => boolean.map(RespFrame::Boolean),
-        b',' => double.map(RespFrame::Double),
-        b'%' => map.map(RespFrame::Map),
-        // b'~' => set,
+        b'+' => simple_string::<LENIENT>.map(RespFrame::SimpleString),
+        b'-' => error::<LENIENT>.map(RespFrame::Error),
+        b':' => integer::<LENIENT>.map(RespFrame::Integer),
+        b'$' => alt((null_bulk_string::<LENIENT>.map(RespFrame::NullBulkString),bulk_string::<LENIENT>.map(RespFrame::BulkString))),
+        b'*' => alt((null_array::<LENIENT>.map(RespFrame::NullArray), array::<LENIENT>.map(RespFrame::Array))),
+        b'_' => null::<LENIENT>.map(RespFrame::Null),
+        b'#' => boolean::<LENIENT>.map(RespFrame::Boolean),
+        b',' => double::<LENIENT>.map(RespFrame::Double),
+        b'%' => map::<LENIENT>.map(RespFrame::Map),
+        b'~' => set::<LENIENT>.map(RespFrame::Set),
         _v => fail::<_, _, _>
     }
     .parse_next(input)
 }
 
 // - simple string: "+OK\r\n"
-fn simple_string(input: &mut &[u8]) -> PResult<SimpleString> {
-    parse_string.map(SimpleString).parse_next(input)
+fn simple_string<const LENIENT: bool>(input: &mut &[u8]) -> PResult<SimpleString> {
+    parse_string::<LENIENT>.map(SimpleString).parse_next(input)
 }
 
 // - error: "-ERR unknown command 'foobar'\r\n"
-fn error(input: &mut &[u8]) -> PResult<SimpleError> {
-    parse_string.map(SimpleError).parse_next(input)
+fn error<const LENIENT: bool>(input: &mut &[u8]) -> PResult<SimpleError> {
+    parse_string::<LENIENT>.map(SimpleError).parse_next(input)
 }
 
 // - integer: ":-1234\r\n", need to take care of the sign
-fn integer(input: &mut &[u8]) -> PResult<i64> {
-    let sign = opt('-').parse_next(input)?.is_some();
-    let v: i64 = terminated(digit1.parse_to(), CRLF).parse_next(input)?;
-    Ok(if sign { -v } else { v })
+fn integer<const LENIENT: bool>(input: &mut &[u8]) -> PResult<i64> {
+    // parse the sign together with the digits, so that i64::MIN doesn't overflow
+    let v: i64 = terminated(
+        (opt(alt(('-', '+'))), digit1).recognize().parse_to(),
+        eol::<LENIENT>,
+    )
+    .parse_next(input)?;
+    Ok(v)
 }
 
 // - null bulk string: "$-1\r\n"
-fn null_bulk_string(input: &mut &[u8]) -> PResult<RespNullBulkString> {
-    "-1\r\n".value(RespNullBulkString).parse_next(input)
+fn null_bulk_string<const LENIENT: bool>(input: &mut &[u8]) -> PResult<RespNullBulkString> {
+    terminated("-1", eol::<LENIENT>)
+        .value(RespNullBulkString)
+        .parse_next(input)
 }
 
 // - bulk string: "$6\r\nfoobar\r\n"
-#[allow(clippy::comparison_chain)]
-fn bulk_string(input: &mut &[u8]) -> PResult<BulkString> {
-    let len: i64 = integer.parse_next(input)?;
-    if len == 0 {
-        return Ok(BulkString(vec![]));
-    } else if len < 0 {
+fn bulk_string<const LENIENT: bool>(input: &mut &[u8]) -> PResult<BulkString> {
+    let len: i64 = integer::<LENIENT>.parse_next(input)?;
+    if len < 0 {
         return Err(err_cut("bulk string length must be non-negative"));
     }
     let data = terminated(take(len as usize), CRLF)
@@ -102,9 +136,9 @@ fn bulk_string(input: &mut &[u8]) -> PResult<BulkString> {
     Ok(BulkString(data))
 }
 
-fn bulk_string_len(input: &mut &[u8]) -> PResult<()> {
-    let len: i64 = integer.parse_next(input)?;
-    if len == 0 || len == -1 {
+fn bulk_string_len<const LENIENT: bool>(input: &mut &[u8]) -> PResult<()> {
+    let len: i64 = integer::<LENIENT>.parse_next(input)?;
+    if len == -1 {
         return Ok(());
     } else if len < -1 {
         return Err(err_cut("bulk string length must be non-negative"));
@@ -117,96 +151,138 @@ fn bulk_string_len(input: &mut &[u8]) -> PResult<()> {
         let size = NonZeroUsize::new((len_with_crlf - input.len()) as usize).unwrap();
         return Err(ErrMode::Incomplete(Needed::Size(size)));
     }
-    *input = &input[(len + 2) as usize..];
+    if &input[len as usize..len_with_crlf] != CRLF {
+        return Err(err_cut(
+            "bulk string data doesn't match the declared length",
+        ));
+    }
+    *input = &input[len_with_crlf..];
     Ok(())
 }
 
 // - null array: "*-1\r\n"
-fn null_array(input: &mut &[u8]) -> PResult<RespNullArray> {
-    "-1\r\n".value(RespNullArray).parse_next(input)
+fn null_array<const LENIENT: bool>(input: &mut &[u8]) -> PResult<RespNullArray> {
+    terminated("-1", eol::<LENIENT>)
+        .value(RespNullArray)
+        .parse_next(input)
 }
 
 // - array: "*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n"
 #[allow(clippy::comparison_chain)]
-fn array(input: &mut &[u8]) -> PResult<RespArray> {
-    let len: i64 = integer.parse_next(input)?;
+fn array<const LENIENT: bool>(input: &mut &[u8]) -> PResult<RespArray> {
+    let len: i64 = integer::<LENIENT>.parse_next(input)?;
     if len == 0 {
         return Ok(RespArray(vec![]));
     } else if len < 0 {
         return Err(err_cut("array length must be non-negative"));
     }
-    let mut arr = Vec::with_capacity(len as usize);
+    // every element takes at least 3 bytes, don't trust the length for the allocation
+    let mut arr = Vec::with_capacity((len as usize).min(input.len() / 3));
     for _ in 0..len {
-        arr.push(parse_frame(input)?);
+        arr.push(parse_frame_inner::<LENIENT>(input)?);
     }
     Ok(RespArray(arr))
 }
 
-fn array_len(input: &mut &[u8]) -> PResult<()> {
-    let len: i64 = integer.parse_next(input)?;
+fn array_len<const LENIENT: bool>(input: &mut &[u8]) -> PResult<()> {
+    let len: i64 = integer::<LENIENT>.parse_next(input)?;
     if len == 0 || len == -1 {
         return Ok(());
     } else if len < -1 {
         return Err(err_cut("array length must be non-negative"));
     }
     for _ in 0..len {
-        parse_frame_len(input)?;
+        parse_frame_len::<LENIENT>(input)?;
     }
     Ok(())
 }
 
+// - set: "~2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n", same layout as array
+fn set<const LENIENT: bool>(input: &mut &[u8]) -> PResult<RespSet> {
+    let len: i64 = integer::<LENIENT>.parse_next(input)?;
+    if len < 0 {
+        return Err(err_cut("set length must be non-negative"));
+    }
+    let mut set = Vec::with_capacity((len as usize).min(input.len() / 3));
+    for _ in 0..len {
+        set.push(parse_frame_inner::<LENIENT>(input)?);
+    }
+    Ok(RespSet(set))
+}
+
 // - boolean: "#t\r\n"
-fn boolean(input: &mut &[u8]) -> PResult<bool> {
-    let b = alt(('t', 'f')).parse_next(input)?;
+fn boolean<const LENIENT: bool>(input: &mut &[u8]) -> PResult<bool> {
+    let b = terminated(alt(('t', 'f')), eol::<LENIENT>).parse_next(input)?;
     Ok(b == 't')
 }
 
-// - float: ",3.14\r\n"
-fn double(input: &mut &[u8]) -> PResult<f64> {
-    terminated(float, CRLF).parse_next(input)
+// - float: ",3.14\r\n", ",inf\r\n", ",-inf\r\n" or ",nan\r\n"
+fn double<const LENIENT: bool>(input: &mut &[u8]) -> PResult<f64> {
+    let special = alt((
+        alt(("inf", "+inf")).value(f64::INFINITY),
+        "-inf".value(f64::NEG_INFINITY),
+        "nan".value(f64::NAN),
+    ));
+    terminated(alt((special, float)), eol::<LENIENT>).parse_next(input)
 }
 
 // my understanding of map len is incorrect: https://redis.io/docs/latest/develop/reference/protocol-spec/#maps
 // - map: "%1\r\n+foo\r\n-bar\r\n"
-fn map(input: &mut &[u8]) -> PResult<RespMap> {
-    let len: i64 = integer.parse_next(input)?;
-    if len <= 0 {
+fn map<const LENIENT: bool>(input: &mut &[u8]) -> PResult<RespMap> {
+    let len: i64 = integer::<LENIENT>.parse_next(input)?;
+    if len < 0 {
         return Err(err_cut("map length must be non-negative"));
     }
     let mut map = BTreeMap::new();
     for _ in 0..len {
-        let key = preceded('+', parse_string).parse_next(input)?;
-        let value = parse_frame(input)?;
+        let key = preceded('+', parse_string::<LENIENT>).parse_next(input)?;
+        let value = parse_frame_inner::<LENIENT>(input)?;
         map.insert(key, value);
     }
     Ok(RespMap(map))
 }
 
-fn map_len(input: &mut &[u8]) -> PResult<()> {
-    let len: i64 = integer.parse_next(input)?;
-    if len <= 0 {
+fn map_len<const LENIENT: bool>(input: &mut &[u8]) -> PResult<()> {
+    let len: i64 = integer::<LENIENT>.parse_next(input)?;
+    if len < 0 {
         return Err(err_cut("map length must be non-negative"));
     }
     for _ in 0..len {
-        terminated(take_until(0.., CRLF), CRLF)
-            .value(())
-            .parse_next(input)?;
-        parse_frame_len(input)?;
+        line::<LENIENT>.value(()).parse_next(input)?;
+        parse_frame_len::<LENIENT>(input)?;
     }
     Ok(())
 }
 
 // - null: "_\r\n"
-fn null(input: &mut &[u8]) -> PResult<RespNull> {
-    CRLF.value(RespNull).parse_next(input)
+fn null<const LENIENT: bool>(input: &mut &[u8]) -> PResult<RespNull> {
+    eol::<LENIENT>.value(RespNull).parse_next(input)
 }
 
-fn parse_string(input: &mut &[u8]) -> PResult<String> {
-    terminated(take_until(0.., CRLF), CRLF)
+fn parse_string<const LENIENT: bool>(input: &mut &[u8]) -> PResult<String> {
+    line::<LENIENT>
         .map(|s: &[u8]| String::from_utf8_lossy(s).into_owned())
         .parse_next(input)
 }
 
+// the content of a line without its terminator
+fn line<'a, const LENIENT: bool>(input: &mut &'a [u8]) -> PResult<&'a [u8]> {
+    if LENIENT {
+        let s: &[u8] = terminated(take_until(0.., LF), LF).parse_next(input)?;
+        Ok(s.strip_suffix(b"\r").unwrap_or(s))
+    } else {
+        terminated(take_until(0.., CRLF), CRLF).parse_next(input)
+    }
+}
+
+fn eol<const LENIENT: bool>(input: &mut &[u8]) -> PResult<()> {
+    if LENIENT {
+        alt((CRLF, LF)).value(()).parse_next(input)
+    } else {
+        CRLF.value(()).parse_next(input)
+    }
+}
+
 fn err_cut(_s: impl Into<String>) -> ErrMode<ContextError> {
     let context = ContextError::default();
     ErrMode::Cut(context)
@@ -0,0 +1,95 @@
+use anyhow::{anyhow, Result};
+use bytes::BytesMut;
+use simple_redis::{
+    network::{self, NetworkConfig},
+    Backend, BulkString, RespArray, RespDecodeV2, RespEncode, RespError, RespFrame,
+};
+use std::{net::SocketAddr, path::Path};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UnixListener, UnixStream},
+};
+
+/// Start a server on an ephemeral port in a background task, returns its address
+pub async fn start_server() -> Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(network::serve(
+        listener,
+        Backend::new(),
+        NetworkConfig::default(),
+    ));
+    Ok(addr)
+}
+
+/// Start a server on the unix domain socket `path` in a background task
+pub fn start_unix_server(path: &Path) -> Result<()> {
+    let listener = UnixListener::bind(path)?;
+    tokio::spawn(network::serve_unix(
+        listener,
+        Backend::new(),
+        NetworkConfig::default(),
+    ));
+    Ok(())
+}
+
+/// Encode a command as an array of bulk strings, the way redis clients send it
+pub fn encode_command(args: &[&str]) -> Vec<u8> {
+    let frames = args
+        .iter()
+        .map(|arg| BulkString::from(*arg).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(frames).encode()
+}
+
+pub struct TestClient<S = TcpStream> {
+    stream: S,
+    buf: BytesMut,
+}
+
+impl TestClient {
+    pub async fn connect(addr: SocketAddr) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Self {
+            stream,
+            buf: BytesMut::new(),
+        })
+    }
+}
+
+impl TestClient<UnixStream> {
+    pub async fn connect_unix(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path).await?;
+        Ok(Self {
+            stream,
+            buf: BytesMut::new(),
+        })
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> TestClient<S> {
+    pub async fn send(&mut self, args: &[&str]) -> Result<RespFrame> {
+        self.stream.write_all(&encode_command(args)).await?;
+        self.read_frame().await
+    }
+
+    /// Write bytes as they are, for pipelines and malformed input
+    pub async fn write_raw(&mut self, data: &[u8]) -> Result<()> {
+        self.stream.write_all(data).await?;
+        Ok(())
+    }
+
+    pub async fn read_frame(&mut self) -> Result<RespFrame> {
+        loop {
+            match RespFrame::decode(&mut self.buf) {
+                Ok(frame) => return Ok(frame),
+                Err(RespError::NotComplete) => {
+                    if self.stream.read_buf(&mut self.buf).await? == 0 {
+                        return Err(anyhow!("connection closed by server"));
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
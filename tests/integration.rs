@@ -0,0 +1,275 @@
+mod common;
+
+use anyhow::Result;
+use common::{encode_command, start_server, start_unix_server, TestClient};
+use simple_redis::{BulkString, RespArray, RespFrame, RespNull, RespNullBulkString, SimpleString};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn set_and_get_should_work() -> Result<()> {
+    let addr = start_server().await?;
+    let mut client = TestClient::connect(addr).await?;
+
+    let ret = client.send(&["GET", "hello"]).await?;
+    assert_eq!(ret, RespNullBulkString.into());
+
+    let ret = client.send(&["SET", "hello", "world"]).await?;
+    assert_eq!(ret, RespFrame::SimpleString("OK".into()));
+
+    let ret = client.send(&["GET", "hello"]).await?;
+    assert_eq!(ret, BulkString::from("world").into());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn hset_and_hgetall_should_work() -> Result<()> {
+    let addr = start_server().await?;
+    let mut client = TestClient::connect(addr).await?;
+
+    let ret = client.send(&["HSET", "map", "hello", "world"]).await?;
+    assert_eq!(ret, RespFrame::SimpleString("OK".into()));
+
+    let ret = client.send(&["HGET", "map", "hello"]).await?;
+    assert_eq!(ret, BulkString::from("world").into());
+
+    let ret = client.send(&["HGETALL", "map"]).await?;
+    let expected = RespArray::new([
+        BulkString::from("hello").into(),
+        BulkString::from("world").into(),
+    ]);
+    assert_eq!(ret, expected.into());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ping_over_unix_socket_should_work() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("simple-redis-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    start_unix_server(&path)?;
+    let mut client = TestClient::connect_unix(&path).await?;
+
+    let ret = client.send(&["PING"]).await?;
+    assert_eq!(ret, SimpleString::new("PONG").into());
+
+    let ret = client.send(&["SET", "hello", "world"]).await?;
+    assert_eq!(ret, SimpleString::new("OK").into());
+    let ret = client.send(&["GET", "hello"]).await?;
+    assert_eq!(ret, BulkString::from("world").into());
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribe_should_confirm_every_channel() -> Result<()> {
+    let addr = start_server().await?;
+    let mut client = TestClient::connect(addr).await?;
+
+    let ret = client.send(&["SUBSCRIBE", "a", "b", "c"]).await?;
+    let mut replies = vec![ret];
+    for _ in 0..2 {
+        replies.push(client.read_frame().await?);
+    }
+    for (i, channel) in ["a", "b", "c"].iter().enumerate() {
+        let expected = RespArray::new([
+            BulkString::from("subscribe").into(),
+            BulkString::from(*channel).into(),
+            RespFrame::Integer(i as i64 + 1),
+        ]);
+        assert_eq!(replies[i], expected.into());
+    }
+
+    // subscribing again to a channel doesn't change the count
+    let ret = client.send(&["SUBSCRIBE", "b"]).await?;
+    let expected = RespArray::new([
+        BulkString::from("subscribe").into(),
+        BulkString::from("b").into(),
+        RespFrame::Integer(3),
+    ]);
+    assert_eq!(ret, expected.into());
+
+    // no arguments unsubscribes from every channel, counting down to 0
+    let ret = client.send(&["UNSUBSCRIBE"]).await?;
+    let mut replies = vec![ret];
+    for _ in 0..2 {
+        replies.push(client.read_frame().await?);
+    }
+    let mut channels = Vec::new();
+    for (i, reply) in replies.into_iter().enumerate() {
+        let RespFrame::Array(reply) = reply else {
+            panic!("UNSUBSCRIBE should reply an array");
+        };
+        assert_eq!(reply[0], BulkString::from("unsubscribe").into());
+        assert_eq!(reply[2], RespFrame::Integer(2 - i as i64));
+        channels.push(reply[1].clone());
+    }
+    channels.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(
+        channels,
+        ["a", "b", "c"].map(|v| BulkString::from(v).into())
+    );
+
+    // back to normal mode
+    let ret = client.send(&["GET", "hello"]).await?;
+    assert_eq!(ret, RespNullBulkString.into());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn psubscribe_should_receive_matching_messages() -> Result<()> {
+    let addr = start_server().await?;
+    let mut subscriber = TestClient::connect(addr).await?;
+    let mut publisher = TestClient::connect(addr).await?;
+
+    let ret = subscriber.send(&["PSUBSCRIBE", "news.*"]).await?;
+    let expected = RespArray::new([
+        BulkString::from("psubscribe").into(),
+        BulkString::from("news.*").into(),
+        RespFrame::Integer(1),
+    ]);
+    assert_eq!(ret, expected.into());
+
+    let ret = publisher.send(&["PUBLISH", "sports.tech", "nope"]).await?;
+    assert_eq!(ret, RespFrame::Integer(0));
+    let ret = publisher.send(&["PUBLISH", "news.tech", "hello"]).await?;
+    assert_eq!(ret, RespFrame::Integer(1));
+
+    let expected = RespArray::new([
+        BulkString::from("pmessage").into(),
+        BulkString::from("news.*").into(),
+        BulkString::from("news.tech").into(),
+        BulkString::from("hello").into(),
+    ]);
+    assert_eq!(subscriber.read_frame().await?, expected.into());
+
+    let ret = subscriber.send(&["PUNSUBSCRIBE"]).await?;
+    let expected = RespArray::new([
+        BulkString::from("punsubscribe").into(),
+        BulkString::from("news.*").into(),
+        RespFrame::Integer(0),
+    ]);
+    assert_eq!(ret, expected.into());
+    let ret = publisher.send(&["PUBLISH", "news.tech", "hello"]).await?;
+    assert_eq!(ret, RespFrame::Integer(0));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn large_value_should_round_trip() -> Result<()> {
+    let addr = start_server().await?;
+    let mut client = TestClient::connect(addr).await?;
+
+    let value = "x".repeat(5 * 1024 * 1024);
+    let ret = client.send(&["SET", "big", &value]).await?;
+    assert_eq!(ret, RespFrame::SimpleString("OK".into()));
+
+    let ret = client.send(&["GET", "big"]).await?;
+    assert_eq!(ret, BulkString::from(value).into());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pipelined_invalid_command_should_not_abort_the_batch() -> Result<()> {
+    let addr = start_server().await?;
+    let mut client = TestClient::connect(addr).await?;
+
+    // an inline line that isn't a command and an array that isn't made of bulk strings
+    client
+        .write_raw(b"SET a 1\r\n\x00\xffgarbage !!\r\n*2\r\n:1\r\n:2\r\nGET a\r\n")
+        .await?;
+    assert_eq!(client.read_frame().await?, SimpleString::new("OK").into());
+    assert!(matches!(client.read_frame().await?, RespFrame::Error(_)));
+    assert!(matches!(client.read_frame().await?, RespFrame::Error(_)));
+    assert_eq!(client.read_frame().await?, BulkString::from("1").into());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn hello_3_should_switch_the_connection_to_resp3() -> Result<()> {
+    let addr = start_server().await?;
+    let mut client = TestClient::connect(addr).await?;
+    client.send(&["HSET", "map", "a", "1"]).await?;
+
+    // RESP2 until HELLO: null is a null bulk string, the HELLO map is a flat array
+    assert_eq!(
+        client.send(&["GET", "missing"]).await?,
+        RespNullBulkString.into()
+    );
+    let RespFrame::Array(hello) = client.send(&["HELLO", "2"]).await? else {
+        panic!("HELLO 2 should reply an array");
+    };
+    assert_eq!(hello.len(), 14);
+    // HGETALL is a map flattened to field/value pairs
+    assert_eq!(
+        client.send(&["HGETALL", "map"]).await?,
+        RespArray::new([BulkString::from("a").into(), BulkString::from("1").into()]).into()
+    );
+
+    let RespFrame::Map(hello) = client.send(&["HELLO", "3"]).await? else {
+        panic!("HELLO 3 should reply a map");
+    };
+    assert_eq!(hello.get("proto"), Some(&RespFrame::Integer(3)));
+    assert_eq!(
+        client.send(&["GET", "missing"]).await?,
+        RespFrame::Null(RespNull)
+    );
+    let RespFrame::Map(hgetall) = client.send(&["HGETALL", "map"]).await? else {
+        panic!("HGETALL should reply a map after HELLO 3");
+    };
+    assert_eq!(hgetall.get("a"), Some(&BulkString::from("1").into()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn time_should_reply_two_bulk_strings() -> Result<()> {
+    let addr = start_server().await?;
+    let mut client = TestClient::connect(addr).await?;
+
+    // clients expect the exact shape: an array of two numeric bulk strings, in either protocol
+    for protocol in ["2", "3"] {
+        client.send(&["HELLO", protocol]).await?;
+        let RespFrame::Array(reply) = client.send(&["TIME"]).await? else {
+            panic!("TIME should reply an array");
+        };
+        assert_eq!(reply.len(), 2);
+        for part in reply.iter() {
+            let RespFrame::BulkString(part) = part else {
+                panic!("TIME should reply bulk strings, got {:?}", part);
+            };
+            String::from_utf8(part.to_vec())?.parse::<u64>()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn debug_sleep_blocking_should_not_stall_other_connections() -> Result<()> {
+    let addr = start_server().await?;
+    let mut sleeper = TestClient::connect(addr).await?;
+    let mut client = TestClient::connect(addr).await?;
+
+    let start = Instant::now();
+    sleeper
+        .write_raw(&encode_command(&["DEBUG", "SLEEP-BLOCKING", "0.5"]))
+        .await?;
+    // give the server a moment to start sleeping
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(
+        client.send(&["PING"]).await?,
+        SimpleString::new("PONG").into()
+    );
+    assert!(start.elapsed() < Duration::from_millis(300));
+
+    assert_eq!(sleeper.read_frame().await?, SimpleString::new("OK").into());
+    assert!(start.elapsed() >= Duration::from_millis(500));
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use bytes::BytesMut;
+use proptest::prelude::*;
+use simple_redis::{
+    parse_frame, BulkString, RespArray, RespEncode, RespError, RespFrame, RespMap, RespNull,
+    RespNullArray, RespNullBulkString, RespSet, SimpleError, SimpleString,
+};
+
+// simple strings, errors and map keys are line based and can't contain CR or LF
+const LINE: &str = "[^\r\n]*";
+
+fn arb_double() -> impl Strategy<Value = f64> {
+    // NaN is left out since it never compares equal to itself
+    prop::num::f64::NORMAL
+        | prop::num::f64::SUBNORMAL
+        | prop::num::f64::ZERO
+        | prop::num::f64::INFINITE
+}
+
+fn arb_frame() -> impl Strategy<Value = RespFrame> {
+    let leaf = prop_oneof![
+        LINE.prop_map(|s| SimpleString::new(s).into()),
+        LINE.prop_map(|s| SimpleError::new(s).into()),
+        any::<i64>().prop_map(RespFrame::Integer),
+        any::<Vec<u8>>().prop_map(|v| BulkString::new(v).into()),
+        Just(RespNullBulkString.into()),
+        Just(RespNullArray.into()),
+        Just(RespNull.into()),
+        any::<bool>().prop_map(RespFrame::Boolean),
+        arb_double().prop_map(RespFrame::Double),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(|v| RespArray::new(v).into()),
+            prop::collection::vec(inner.clone(), 0..8).prop_map(|v| RespSet::new(v).into()),
+            prop::collection::btree_map(LINE, inner, 0..8).prop_map(|v| RespMap::from(v).into()),
+        ]
+    })
+}
+
+fn v1_decode(buf: &mut BytesMut) -> Result<(usize, RespFrame), RespError> {
+    use simple_redis::RespDecode;
+    let len = RespFrame::expect_length(buf)?;
+    Ok((len, RespFrame::decode(buf)?))
+}
+
+fn v2_decode(buf: &mut BytesMut) -> Result<(usize, RespFrame), RespError> {
+    use simple_redis::RespDecodeV2;
+    let len = RespFrame::expect_length(buf)?;
+    Ok((len, RespFrame::decode(buf)?))
+}
+
+proptest! {
+    #[test]
+    fn encoded_frame_should_decode_to_itself(frame in arb_frame()) {
+        let encoded = frame.clone().encode();
+
+        let mut buf = BytesMut::from(encoded.as_slice());
+        prop_assert_eq!(v1_decode(&mut buf)?, (encoded.len(), frame.clone()));
+        prop_assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from(encoded.as_slice());
+        prop_assert_eq!(v2_decode(&mut buf)?, (encoded.len(), frame));
+        prop_assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn decoding_random_bytes_should_not_panic(data in any::<Vec<u8>>()) {
+        let _ = v1_decode(&mut BytesMut::from(data.as_slice()));
+        let _ = v2_decode(&mut BytesMut::from(data.as_slice()));
+        let _ = parse_frame(&mut data.as_slice());
+    }
+
+    #[test]
+    fn decoding_random_frame_like_bytes_should_not_panic(
+        prefix in prop::sample::select(b"+-:$*_#,%~".to_vec()),
+        body in "([0-9-]{1,20}\r\n[-+:$*_#,%~]?){0,4}[-a-z0-9\r\n+:$*_#,%~]{0,16}",
+    ) {
+        let mut data = vec![prefix];
+        data.extend_from_slice(body.as_bytes());
+        let _ = v1_decode(&mut BytesMut::from(data.as_slice()));
+        let _ = v2_decode(&mut BytesMut::from(data.as_slice()));
+        let _ = parse_frame(&mut data.as_slice());
+    }
+}